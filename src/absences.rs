@@ -0,0 +1,456 @@
+use std::fs;
+
+use chrono::{Datelike, NaiveDate};
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, ListView, SelectView, TextView};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_context, stats_view, time_picker};
+use crate::targets::Targets;
+
+const ABSENCES_LIST: &str = "absences_list";
+const ABSENCE_DATE: &str = "absence_date";
+const ABSENCE_KIND: &str = "absence_kind";
+const ABSENCE_HALF_DAY: &str = "absence_half_day";
+const ABSENCE_NOTE: &str = "absence_note";
+const ICS_FILE_NAME: &str = "ics_file_name";
+
+#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum AbsenceKind {
+    Vacation,
+    Sick,
+    PublicHoliday,
+}
+
+impl AbsenceKind {
+    pub fn parse(value: &str) -> Option<AbsenceKind> {
+        match value {
+            "vacation" => Some(AbsenceKind::Vacation),
+            "sick" => Some(AbsenceKind::Sick),
+            "holiday" | "public-holiday" => Some(AbsenceKind::PublicHoliday),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            AbsenceKind::Vacation => "Vacation",
+            AbsenceKind::Sick => "Sick",
+            AbsenceKind::PublicHoliday => "Public holiday",
+        }
+    }
+}
+
+/// A day, or half a day, off work. Absences lower the working time target of their day.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Absence {
+    pub date: NaiveDate,
+    pub kind: AbsenceKind,
+    #[serde(default)]
+    pub half_day: bool,
+    #[serde(default)]
+    pub note: String,
+}
+
+impl Absence {
+    pub fn days(&self) -> f64 {
+        if self.half_day { 0.5 } else { 1.0 }
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = format!("{} {}", self.date, self.kind.as_str());
+        if self.half_day {
+            label += " (half day)";
+        }
+        if !self.note.is_empty() {
+            label += &format!(": {}", self.note);
+        }
+        label
+    }
+}
+
+/// The part of the day that is off, from 0 to 1.
+pub fn absent_fraction(absences: &[Absence], date: NaiveDate) -> f64 {
+    absences.iter()
+        .filter(|it| it.date == date)
+        .map(Absence::days)
+        .sum::<f64>()
+        .min(1.0)
+}
+
+/// Adds the absence, replacing any other absence on the same day.
+pub fn add(absences: &mut Vec<Absence>, absence: Absence) {
+    absences.retain(|it| it.date != absence.date);
+    absences.push(absence);
+    absences.sort_by_key(|it| it.date);
+}
+
+/// Adds the absences on the days that don't have one yet, and returns how many were added.
+pub fn merge(absences: &mut Vec<Absence>, imported: Vec<Absence>) -> usize {
+    let mut added = 0;
+    for absence in imported {
+        if !absences.iter().any(|it| it.date == absence.date) {
+            absences.push(absence);
+            added += 1;
+        }
+    }
+    absences.sort_by_key(|it| it.date);
+    added
+}
+
+/// The public holidays found in a calendar, and why the other events were left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IcsImport {
+    pub holidays: Vec<Absence>,
+    pub skipped: Vec<String>,
+}
+
+/// Reads the all-day events of an iCalendar file as public holidays. Events that aren't all-day, or that can't be
+/// read, are skipped with the reason.
+pub fn parse_ics(content: &str) -> Result<IcsImport, String> {
+    // Long lines are folded, continuing on the next line after a space or a tab
+    let unfolded = content.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    if !unfolded.trim_start().starts_with("BEGIN:VCALENDAR") {
+        return Err("Not an iCalendar file, it should start with BEGIN:VCALENDAR".to_string());
+    }
+
+    let mut import = IcsImport::default();
+    let mut event: Option<Vec<(&str, &str)>> = None;
+    for line in unfolded.lines() {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.trim()),
+            None => continue,
+        };
+        match (name, event.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(Vec::new()),
+            ("END", Some(properties)) if value == "VEVENT" => {
+                let summary = property(properties, "SUMMARY").map(|(_, value)| unescape(value)).unwrap_or_default();
+                match event_holidays(properties, &summary) {
+                    Ok(holidays) => import.holidays.extend(holidays),
+                    Err(e) => import.skipped.push(format!("'{}': {}", summary, e)),
+                }
+                event = None;
+            },
+            (_, Some(properties)) => properties.push((name, value)),
+            _ => {},
+        }
+    }
+    Ok(import)
+}
+
+/// The property with its parameters, like `DTSTART;VALUE=DATE`, and its value.
+fn property<'a>(properties: &[(&'a str, &'a str)], name: &str) -> Option<(&'a str, &'a str)> {
+    properties.iter().copied().find(|(it, _)| it.split(';').next() == Some(name))
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("\\,", ",").replace("\\;", ";")
+}
+
+fn event_holidays(properties: &[(&str, &str)], note: &str) -> Result<Vec<Absence>, String> {
+    let (name, value) = property(properties, "DTSTART").ok_or("no start date")?;
+    if !name.split(';').any(|it| it == "VALUE=DATE") {
+        return Err("not an all-day event".to_string());
+    }
+    let start = parse_ics_date(value)?;
+    // The end date of an all-day event is exclusive
+    let end = match property(properties, "DTEND") {
+        Some((_, value)) => parse_ics_date(value)?.pred_opt().unwrap_or(start).max(start),
+        None => start,
+    };
+    let years = match property(properties, "RRULE") {
+        Some((_, rule)) => yearly_repeats(rule, start)?,
+        None => vec![0],
+    };
+    Ok(years.into_iter()
+        .flat_map(|years| start.iter_days().take_while(|it| *it <= end).filter_map(move |it| it.with_year(it.year() + years)))
+        .map(|date| Absence { date, kind: AbsenceKind::PublicHoliday, half_day: false, note: note.to_string() })
+        .collect())
+}
+
+/// The years after the start an event repeats in, for a yearly rule with a `COUNT` or an `UNTIL`.
+fn yearly_repeats(rule: &str, start: NaiveDate) -> Result<Vec<i32>, String> {
+    let unsupported = || format!("repeats by the unsupported rule {}", rule);
+    let (mut yearly, mut interval, mut count, mut until) = (false, 1, None, None);
+    for part in rule.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", "YEARLY")) => yearly = true,
+            Some(("INTERVAL", value)) => interval = value.parse::<i32>().ok().filter(|it| *it > 0).ok_or_else(unsupported)?,
+            Some(("COUNT", value)) => count = Some(value.parse::<i32>().ok().filter(|it| (1..=100).contains(it)).ok_or_else(unsupported)?),
+            Some(("UNTIL", value)) => until = Some(parse_ics_date(value)?),
+            _ => return Err(unsupported()),
+        }
+    }
+    match (yearly, count, until) {
+        (true, Some(count), _) => Ok((0..count).map(|it| it * interval).collect()),
+        (true, None, Some(until)) => Ok((0..=(until.year() - start.year()).min(100))
+            .step_by(interval as usize)
+            .filter(|years| start.with_year(start.year() + years).is_some_and(|it| it <= until))
+            .collect()),
+        (true, None, None) => Err("repeats every year without an end, add the years as separate events".to_string()),
+        _ => Err(unsupported()),
+    }
+}
+
+fn parse_ics_date(value: &str) -> Result<NaiveDate, String> {
+    value.get(0..8)
+        .and_then(|it| NaiveDate::parse_from_str(it, "%Y%m%d").ok())
+        .ok_or_else(|| format!("Invalid iCalendar date: {}", value))
+}
+
+/// Moves the holidays of targets from older databases to the absences, they used to be days without a target.
+pub fn migrate_holidays(targets: &mut Targets, absences: &mut Vec<Absence>) {
+    let holidays = targets.holidays.drain(..)
+        .map(|date| Absence { date, kind: AbsenceKind::PublicHoliday, half_day: false, note: String::new() })
+        .collect();
+    merge(absences, holidays);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VacationSummary {
+    pub year: i32,
+    pub allowance: Option<f64>,
+    pub vacation_days: f64,
+    pub sick_days: f64,
+    pub public_holidays: f64,
+}
+
+impl VacationSummary {
+    pub fn remaining(&self) -> Option<f64> {
+        self.allowance.map(|it| it - self.vacation_days)
+    }
+}
+
+/// Counts the absences of the year. With targets, only the absences on working days count.
+pub fn vacation_summary(absences: &[Absence], targets: Option<&Targets>, year: i32) -> VacationSummary {
+    let days = |kind: AbsenceKind| absences.iter()
+        .filter(|it| it.kind == kind && it.date.year() == year)
+        .filter(|it| targets.is_none_or(|targets| targets.hours[it.date.weekday().num_days_from_monday() as usize] > 0.0))
+        .map(Absence::days)
+        .fold(0.0, |total, days| total + days);
+    VacationSummary {
+        year,
+        allowance: targets.and_then(|it| it.vacation_days),
+        vacation_days: days(AbsenceKind::Vacation),
+        sick_days: days(AbsenceKind::Sick),
+        public_holidays: days(AbsenceKind::PublicHoliday),
+    }
+}
+
+pub fn render_vacation_summary(summary: &VacationSummary) -> String {
+    let mut lines = vec![
+        format!("Vacation {}", summary.year),
+        format!("{:<18}{}", "Vacation days:", summary.vacation_days),
+    ];
+    if let (Some(allowance), Some(remaining)) = (summary.allowance, summary.remaining()) {
+        lines.push(format!("{:<18}{}", "Allowance:", allowance));
+        lines.push(format!("{:<18}{}", "Remaining:", remaining));
+    }
+    lines.push(format!("{:<18}{}", "Sick days:", summary.sick_days));
+    lines.push(format!("{:<18}{}", "Public holidays:", summary.public_holidays));
+    lines.join("\n") + "\n"
+}
+
+pub fn manage_absences(s: &mut Cursive) {
+    let year = time_picker::today().year();
+    let absences = app_context::fetch(s).absences();
+    let targets = app_context::fetch(s).targets();
+    let mut list = SelectView::new();
+    absences.iter()
+        .filter(|it| it.date.year() == year)
+        .for_each(|it| list.add_item(it.label(), it.date));
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new(render_vacation_summary(&vacation_summary(&absences, targets.as_ref(), year))))
+                .child(list.with_name(ABSENCES_LIST).scrollable().max_height(15).min_size((50, 5)))
+        )
+            .title(format!("Absences {}", year))
+            .button("Add", add_absence)
+            .button("Remove", remove_absence)
+            .button("Import .ics", import_ics)
+            .button("Close", |s| { s.pop_layer(); })
+    );
+}
+
+fn add_absence(s: &mut Cursive) {
+    let mut kind = SelectView::new().popup();
+    kind.add_item(AbsenceKind::Vacation.as_str(), AbsenceKind::Vacation);
+    kind.add_item(AbsenceKind::Sick.as_str(), AbsenceKind::Sick);
+    kind.add_item(AbsenceKind::PublicHoliday.as_str(), AbsenceKind::PublicHoliday);
+    s.add_layer(
+        Dialog::around(
+            ListView::new()
+                .child("Date", EditView::new().content(time_picker::today().to_string()).with_name(ABSENCE_DATE).min_width(30))
+                .child("Kind", kind.with_name(ABSENCE_KIND))
+                .child("Half day", Checkbox::new().with_name(ABSENCE_HALF_DAY))
+                .child("Note", EditView::new().with_name(ABSENCE_NOTE).min_width(30))
+        )
+            .title("Add absence")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", submit_absence)
+    );
+}
+
+fn submit_absence(s: &mut Cursive) {
+    let date = s.call_on_name(ABSENCE_DATE, |e: &mut EditView| time_picker::parse_date(&e.get_content())).flatten();
+    let date = match date {
+        Some(date) => date,
+        None => {
+            s.add_layer(Dialog::info("Invalid date, use YYYY-MM-DD, today or yesterday."));
+            return;
+        },
+    };
+    let absence = Absence {
+        date,
+        kind: s.call_on_name(ABSENCE_KIND, |v: &mut SelectView<AbsenceKind>| v.selection()).flatten().map(|it| *it).expect("A kind should be selected"),
+        half_day: s.call_on_name(ABSENCE_HALF_DAY, |c: &mut Checkbox| c.is_checked()).expect("Half day should be defined"),
+        note: s.call_on_name(ABSENCE_NOTE, |e: &mut EditView| e.get_content().trim().to_string()).expect("Note should be defined"),
+    };
+    let mut absences = app_context::fetch(s).absences();
+    add(&mut absences, absence);
+    app_context::fetch(s).set_absences(absences);
+    s.pop_layer();
+    s.pop_layer();
+    manage_absences(s);
+    stats_view::update_stats(s);
+}
+
+fn remove_absence(s: &mut Cursive) {
+    let selected = s.call_on_name(ABSENCES_LIST, |l: &mut SelectView<NaiveDate>| l.selection())
+        .expect("The absences list should be defined");
+    if let Some(date) = selected {
+        let mut absences = app_context::fetch(s).absences();
+        absences.retain(|it| it.date != *date);
+        app_context::fetch(s).set_absences(absences);
+        s.pop_layer();
+        manage_absences(s);
+        stats_view::update_stats(s);
+    }
+}
+
+fn import_ics(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(EditView::new().with_name(ICS_FILE_NAME).min_width(40))
+            .title("Import public holidays from an .ics file")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Import", |s| {
+                let file_name = s.call_on_name(ICS_FILE_NAME, |e: &mut EditView| e.get_content().trim().to_string())
+                    .expect("The file name should be defined");
+                let imported = fs::read_to_string(&file_name).map_err(|e| e.to_string()).and_then(|it| parse_ics(&it));
+                match imported {
+                    Ok(imported) => {
+                        let mut absences = app_context::fetch(s).absences();
+                        let added = merge(&mut absences, imported.holidays);
+                        app_context::fetch(s).set_absences(absences);
+                        s.pop_layer();
+                        s.pop_layer();
+                        manage_absences(s);
+                        stats_view::update_stats(s);
+                        let skipped = imported.skipped.iter().map(|it| format!("\nSkipped {}", it)).collect::<String>();
+                        s.add_layer(Dialog::info(format!("Imported {} public holidays{}", added, skipped)));
+                    },
+                    Err(e) => s.add_layer(Dialog::info(format!("Unable to import {}: {}", file_name, e))),
+                }
+            })
+    );
+}
+
+#[cfg(test)]
+mod absences_test {
+    use chrono::NaiveDate;
+
+    use crate::absences::{Absence, absent_fraction, AbsenceKind, merge, migrate_holidays, parse_ics, vacation_summary};
+    use crate::targets::Targets;
+
+    fn absence(day: u32, kind: AbsenceKind, half_day: bool) -> Absence {
+        Absence { date: NaiveDate::from_ymd(2022, 12, day), kind, half_day, note: String::new() }
+    }
+
+    #[test]
+    fn parses_all_day_events() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20221225\r\nDTEND;VALUE=DATE:20221227\r\nSUMMARY:Christmas\\, and Boxing \r\n Day\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20230101\r\nSUMMARY:New Year\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let import = parse_ics(ics).unwrap();
+        let holidays = import.holidays;
+        assert_eq!(holidays.len(), 3);
+        assert_eq!(holidays[0].date, NaiveDate::from_ymd(2022, 12, 25));
+        assert_eq!(holidays[0].note, "Christmas, and Boxing Day");
+        assert_eq!(holidays[1].date, NaiveDate::from_ymd(2022, 12, 26));
+        assert_eq!(holidays[2].date, NaiveDate::from_ymd(2023, 1, 1));
+        assert!(holidays.iter().all(|it| it.kind == AbsenceKind::PublicHoliday));
+        assert!(import.skipped.is_empty());
+        assert!(parse_ics("not a calendar").is_err());
+    }
+
+    #[test]
+    fn skips_events_that_are_not_all_day_or_unreadable() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:20230101T000000Z\nSUMMARY:Party\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:2023-13\nSUMMARY:Typo\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20230501\nSUMMARY:Labour Day\nEND:VEVENT\nEND:VCALENDAR\n";
+        let import = parse_ics(ics).unwrap();
+        assert_eq!(import.holidays.iter().map(|it| it.date).collect::<Vec<NaiveDate>>(), vec![NaiveDate::from_ymd(2023, 5, 1)]);
+        assert_eq!(import.skipped, vec![
+            "'Party': not an all-day event".to_string(),
+            "'Typo': Invalid iCalendar date: 2023-13".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn repeats_yearly_events() {
+        let event = |rule: &str| format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20221225\nRRULE:{}\nSUMMARY:Christmas\nEND:VEVENT\nEND:VCALENDAR\n", rule);
+        let dates = |rule: &str| parse_ics(&event(rule)).unwrap().holidays.into_iter().map(|it| it.date).collect::<Vec<NaiveDate>>();
+
+        assert_eq!(dates("FREQ=YEARLY;COUNT=3"), vec![
+            NaiveDate::from_ymd(2022, 12, 25),
+            NaiveDate::from_ymd(2023, 12, 25),
+            NaiveDate::from_ymd(2024, 12, 25),
+        ]);
+        assert_eq!(dates("FREQ=YEARLY;UNTIL=20241224"), vec![NaiveDate::from_ymd(2022, 12, 25), NaiveDate::from_ymd(2023, 12, 25)]);
+        assert!(parse_ics(&event("FREQ=YEARLY")).unwrap().skipped[0].contains("without an end"));
+        assert!(parse_ics(&event("FREQ=WEEKLY;COUNT=3")).unwrap().skipped[0].contains("unsupported rule"));
+    }
+
+    #[test]
+    fn migrates_the_holidays_of_the_targets() {
+        let mut targets = Targets { holidays: vec![NaiveDate::from_ymd(2022, 12, 25), NaiveDate::from_ymd(2022, 12, 26)], ..Targets::default() };
+        let mut absences = vec![absence(26, AbsenceKind::Vacation, false)];
+
+        migrate_holidays(&mut targets, &mut absences);
+
+        assert!(targets.holidays.is_empty());
+        assert_eq!(absences, vec![absence(25, AbsenceKind::PublicHoliday, false), absence(26, AbsenceKind::Vacation, false)]);
+        assert!(!serde_json::to_string(&targets).unwrap().contains("holidays"));
+    }
+
+    #[test]
+    fn merges_without_overwriting() {
+        let mut absences = vec![absence(26, AbsenceKind::Vacation, false)];
+        assert_eq!(merge(&mut absences, vec![absence(25, AbsenceKind::PublicHoliday, false), absence(26, AbsenceKind::PublicHoliday, false)]), 1);
+        assert_eq!(absences, vec![absence(25, AbsenceKind::PublicHoliday, false), absence(26, AbsenceKind::Vacation, false)]);
+    }
+
+    #[test]
+    fn half_days_count_half() {
+        let absences = vec![absence(19, AbsenceKind::Vacation, true)];
+        assert_eq!(absent_fraction(&absences, NaiveDate::from_ymd(2022, 12, 19)), 0.5);
+        assert_eq!(absent_fraction(&absences, NaiveDate::from_ymd(2022, 12, 20)), 0.0);
+    }
+
+    #[test]
+    fn summarizes_the_vacation_of_the_year() {
+        let absences = vec![
+            absence(19, AbsenceKind::Vacation, false),
+            absence(20, AbsenceKind::Vacation, true),
+            absence(24, AbsenceKind::Vacation, false),
+            absence(21, AbsenceKind::Sick, false),
+            absence(26, AbsenceKind::PublicHoliday, false),
+        ];
+        let targets = Targets { vacation_days: Some(25.0), ..Targets::default() };
+        let summary = vacation_summary(&absences, Some(&targets), 2022);
+        assert_eq!(summary.vacation_days, 1.5);
+        assert_eq!(summary.remaining(), Some(23.5));
+        assert_eq!(summary.sick_days, 1.0);
+        assert_eq!(summary.public_holidays, 1.0);
+        assert_eq!(vacation_summary(&absences, None, 2022).vacation_days, 2.5);
+        assert_eq!(vacation_summary(&absences, None, 2023).vacation_days, 0.0);
+    }
+}
@@ -2,7 +2,8 @@ use cursive::Cursive;
 use scheduled_thread_pool::ScheduledThreadPool;
 use crate::db;
 
-pub fn start_autosave_loop(siv: &Cursive) {
+/// Saves every 30 seconds, as long as the returned pool isn't dropped.
+pub fn start_autosave_loop(siv: &Cursive) -> ScheduledThreadPool {
     let cb_sink = siv.cb_sink().clone();
     let thread_pool = ScheduledThreadPool::new(1);
     thread_pool.execute_at_fixed_rate(
@@ -10,4 +11,5 @@ pub fn start_autosave_loop(siv: &Cursive) {
         core::time::Duration::from_secs(30),
         move || { cb_sink.send(Box::new(db::save_to_db)).unwrap() }
     );
+    thread_pool
 }
\ No newline at end of file
@@ -0,0 +1,294 @@
+use std::error::Error;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{absences, ClockEntry, ClockKing};
+use crate::billing::Billing;
+use crate::config::Config;
+
+pub const EXPORT_FORMAT: &str = "clockking-export";
+pub const EXPORT_VERSION: u32 = 2;
+
+/// The versioned envelope of a full export, see the "Backup and migration" section of the README.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Export {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub database: ClockKing,
+    /// Only exported on request, as they can hold API tokens
+    #[serde(default)]
+    pub settings: Option<Config>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImportMode {
+    Replace,
+    Merge,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub added: usize,
+    pub unchanged: usize,
+    pub conflicts: Vec<String>,
+}
+
+pub fn export(database: &ClockKing, settings: Option<&Config>) -> Export {
+    Export {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        exported_at: Local::now().naive_local(),
+        database: database.clone(),
+        settings: settings.cloned(),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Export, Box<dyn Error>> {
+    let value: serde_json::Value = serde_json::from_str(content)?;
+    if value["format"] != EXPORT_FORMAT {
+        return Err(format!("Not a Clock King export, the `format` field should be \"{}\"", EXPORT_FORMAT).into());
+    }
+    match value["version"].as_u64() {
+        Some(version) if version <= EXPORT_VERSION as u64 => Ok(serde_json::from_value(value)?),
+        Some(version) => Err(format!("Export version {} is newer than the supported version {}", version, EXPORT_VERSION).into()),
+        None => Err("The export is missing its `version`".into()),
+    }
+}
+
+/// Applies the export to the local database, and the settings when it has any. Merging keeps the local data on
+/// conflicts.
+pub fn import(database: &mut ClockKing, settings: &mut Option<Config>, export: Export, mode: ImportMode) -> ImportReport {
+    match mode {
+        ImportMode::Replace => {
+            let report = ImportReport { added: export.database.clock_entries.len(), ..ImportReport::default() };
+            *database = export.database;
+            if export.settings.is_some() {
+                *settings = export.settings;
+            }
+            report
+        },
+        ImportMode::Merge => {
+            if settings.is_none() {
+                *settings = export.settings;
+            }
+            merge(database, export.database, export.version)
+        },
+    }
+}
+
+fn merge(database: &mut ClockKing, imported: ClockKing, version: u32) -> ImportReport {
+    let mut report = ImportReport::default();
+    for entry in imported.clock_entries {
+        match database.clock_entries.iter().find(|it| is_same_identity(it, &entry, version)) {
+            Some(existing) if has_same_content(existing, &entry) => report.unchanged += 1,
+            Some(existing) => report.conflicts.push(format!(
+                "Entry '{}' on {} at {} differs, kept the local version",
+                existing.description, existing.date, existing.from
+            )),
+            None => {
+                database.clock_entries.push(entry);
+                report.added += 1;
+            },
+        }
+    }
+
+    for project in imported.projects {
+        match database.projects.iter().find(|it| it.name == project.name) {
+            Some(existing) if *existing != project => report.conflicts.push(format!(
+                "Project '{}' differs, kept the local version",
+                project.name
+            )),
+            Some(_) => {},
+            None => database.projects.push(project),
+        }
+    }
+
+    absences::merge(&mut database.absences, imported.absences);
+
+    for timer in imported.timers {
+        if !database.timers.iter().any(|it| it.entry.id == timer.entry.id) {
+            database.timers.push(timer);
+        }
+    }
+
+    for template in imported.templates {
+        if !database.templates.contains(&template) {
+            database.templates.push(template);
+        }
+    }
+
+    if database.granularity != imported.granularity {
+        report.conflicts.push(format!(
+            "Granularity {:?} differs from the local {:?}, kept the local one",
+            imported.granularity, database.granularity
+        ));
+    }
+    if database.billing == Billing::default() {
+        database.billing = imported.billing;
+    } else if database.billing != imported.billing {
+        report.conflicts.push("Billing differs from the local billing, kept the local one".to_string());
+    }
+    match (&database.targets, imported.targets) {
+        (None, targets) => database.targets = targets,
+        (Some(local), Some(targets)) if *local != targets => report.conflicts.push(
+            "Targets differ from the local targets, kept the local ones".to_string()
+        ),
+        _ => {},
+    }
+    match (&database.recording, imported.recording) {
+        (None, recording) => {
+            database.recording = recording;
+            database.breaks = imported.breaks;
+        },
+        (Some(local), Some(recording)) if *local != recording => report.conflicts.push(format!(
+            "Recording '{}' differs from the ongoing local recording, kept the local one",
+            recording.description
+        )),
+        _ => {},
+    }
+    report
+}
+
+fn has_same_content(a: &ClockEntry, b: &ClockEntry) -> bool {
+    *a == ClockEntry { id: a.id, created_at: a.created_at, updated_at: a.updated_at, ..b.clone() }
+}
+
+/// Entries are the same when their IDs are. Version 1 exports had no IDs, so there it's the same start and description.
+fn is_same_identity(a: &ClockEntry, b: &ClockEntry, version: u32) -> bool {
+    if version < 2 {
+        a.date == b.date && a.from == b.from && a.description == b.description
+    } else {
+        a.id == b.id
+    }
+}
+
+#[cfg(test)]
+mod import_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, ClockKing, Granularity, Project};
+    use crate::backup::{export, import, ImportMode, parse};
+    use crate::billing::Billing;
+    use crate::config::Config;
+    use crate::targets::Targets;
+
+    fn entry(from: u32, to: u32, description: &str, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(from, 0, 0),
+            to: NaiveTime::from_hms(to, 0, 0),
+            description: description.to_string(),
+            is_clocked,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn database(clock_entries: Vec<ClockEntry>) -> ClockKing {
+        ClockKing { clock_entries, ..ClockKing::default() }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let original = export(&database(vec![entry(9, 10, "Coding", false)]), Some(&Config::default()));
+        let json = serde_json::to_string(&original).unwrap();
+        assert_eq!(parse(&json).unwrap(), original);
+    }
+
+    #[test]
+    fn rejects_foreign_and_newer_files() {
+        assert!(parse(r#"{"clock_entries": []}"#).unwrap_err().to_string().contains("Not a Clock King export"));
+        let mut newer = serde_json::to_value(export(&ClockKing::default(), None)).unwrap();
+        newer["version"] = serde_json::json!(99);
+        assert!(parse(&newer.to_string()).unwrap_err().to_string().contains("newer"));
+    }
+
+    #[test]
+    fn replace_overwrites_everything() {
+        let mut local = database(vec![entry(9, 10, "Coding", false)]);
+        let mut settings = None;
+        let review = entry(11, 12, "Review", true);
+        let imported = export(&database(vec![review.clone()]), Some(&Config::default()));
+
+        let report = import(&mut local, &mut settings, imported, ImportMode::Replace);
+
+        assert_eq!(local.clock_entries, vec![review]);
+        assert_eq!(settings, Some(Config::default()));
+        assert_eq!(report.added, 1);
+    }
+
+    #[test]
+    fn replace_keeps_the_local_settings_without_exported_ones() {
+        let mut local = database(vec![]);
+        let mut settings = None;
+        let imported = export(&database(vec![entry(9, 10, "Coding", false)]), None);
+
+        let report = import(&mut local, &mut settings, imported, ImportMode::Replace);
+
+        assert_eq!(settings, None);
+        assert_eq!(report.added, 1);
+    }
+
+    #[test]
+    fn merge_adds_new_entries_and_reports_conflicts() {
+        let coding = entry(9, 10, "Coding", false);
+        let meeting = entry(10, 11, "Meeting", false);
+        let deploy = entry(15, 15, "Deploy", false);
+        let mut local = database(vec![coding.clone(), meeting.clone()]);
+        local.billing = Billing { default_rate: Some(80.0), ..Billing::default() };
+        local.targets = Some(Targets::default());
+        let mut settings = Some(Config::default());
+        let mut imported = database(vec![
+            coding,
+            ClockEntry { to: NaiveTime::from_hms(12, 0, 0), is_clocked: true, ..meeting.clone() },
+            entry(10, 11, "Meeting", false),
+        ]);
+        imported.granularity = Granularity::Relaxed;
+        imported.recording = Some(deploy.clone());
+        imported.projects = vec![Project { name: "Website".to_string(), client: None, rate: None }];
+        imported.billing = Billing { default_rate: Some(95.0), ..Billing::default() };
+        imported.targets = Some(Targets { hours: [6.0; 7], ..Targets::default() });
+
+        let report = import(&mut local, &mut settings, export(&imported, Some(&Config::default())), ImportMode::Merge);
+
+        assert_eq!(report.added, 1);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.conflicts.len(), 4);
+        assert_eq!(local.clock_entries.len(), 3);
+        assert_eq!(local.clock_entries[1], meeting);
+        assert_eq!(local.granularity, Granularity::Detailed);
+        assert_eq!(local.billing.default_rate, Some(80.0));
+        assert_eq!(local.targets, Some(Targets::default()));
+        assert_eq!(local.recording, Some(deploy));
+        assert_eq!(local.projects, vec![Project { name: "Website".to_string(), client: None, rate: None }]);
+    }
+
+    #[test]
+    fn merges_version_1_exports_by_start_and_description() {
+        let mut local = database(vec![entry(9, 10, "Coding", false)]);
+        let mut legacy = export(&database(vec![entry(9, 10, "Coding", false), entry(10, 11, "Coding", false)]), Some(&Config::default()));
+        legacy.version = 1;
+
+        let report = import(&mut local, &mut Some(Config::default()), legacy, ImportMode::Merge);
+
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.added, 1);
+        assert_eq!(local.clock_entries.len(), 2);
+    }
+
+    #[test]
+    fn merge_takes_billing_and_targets_missing_locally() {
+        let mut local = database(vec![]);
+        let mut imported = database(vec![]);
+        imported.billing = Billing { default_rate: Some(95.0), ..Billing::default() };
+        imported.targets = Some(Targets::default());
+
+        let report = import(&mut local, &mut None, export(&imported, None), ImportMode::Merge);
+
+        assert!(report.conflicts.is_empty());
+        assert_eq!(local.billing, imported.billing);
+        assert_eq!(local.targets, imported.targets);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{ClockEntry, Granularity, Project, stats_view};
+
+pub const BILLABLE_HOURS: &str     = "Billable hours";
+pub const NON_BILLABLE_HOURS: &str = "Non-billable";
+pub const BILLABLE_AMOUNT: &str    = "Billable amount";
+
+/// The rate used for projects without their own rate, and the currency of all the rates.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Billing {
+    #[serde(default)]
+    pub default_rate: Option<f64>,
+    #[serde(default = "default_currency")]
+    pub currency: String,
+}
+
+impl Default for Billing {
+    fn default() -> Self {
+        Billing {
+            default_rate: None,
+            currency: default_currency(),
+        }
+    }
+}
+
+fn default_currency() -> String {
+    "EUR".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Revenue {
+    pub project: Option<String>,
+    pub billable_seconds: i64,
+    pub rate: Option<f64>,
+    pub amount: f64,
+}
+
+pub fn rate(project: &Option<String>, projects: &[Project], billing: &Billing) -> Option<f64> {
+    project.as_ref()
+        .and_then(|name| projects.iter().find(|it| it.name == *name))
+        .and_then(|it| it.rate)
+        .or(billing.default_rate)
+}
+
+/// Cuts the seconds down to the whole steps of the granularity, the same way the durations are displayed.
+pub fn rounded_seconds(granularity: Granularity, seconds: i64) -> i64 {
+    let step = match granularity {
+        Granularity::Relaxed => 3600,
+        Granularity::Reasonable => 1800,
+        Granularity::Detailed => 900,
+        Granularity::Paranoid => 300,
+        Granularity::Ocd => 60,
+        Granularity::Scientific => 1,
+    };
+    seconds / step * step
+}
+
+pub fn billable_seconds<'a>(entries: impl IntoIterator<Item = &'a ClockEntry>) -> i64 {
+    entries.into_iter()
+        .filter(|it| it.billable && !it.excluded_from_totals)
+        .map(|it| it.duration().num_seconds())
+        .sum()
+}
+
+/// Revenue of the billable entries per project, with the hours of each project rounded to the granularity.
+pub fn revenue<'a>(
+    entries: impl IntoIterator<Item = &'a ClockEntry>,
+    projects: &[Project],
+    billing: &Billing,
+    granularity: Granularity,
+) -> Vec<Revenue> {
+    stats_view::project_totals(entries.into_iter().filter(|it| it.billable))
+        .into_iter()
+        .map(|(project, totals)| {
+            let billable_seconds = rounded_seconds(granularity, totals.total_seconds);
+            let rate = rate(&project, projects, billing);
+            Revenue {
+                amount: rate.map(|rate| billable_seconds as f64 / 3600.0 * rate).unwrap_or_default(),
+                project,
+                billable_seconds,
+                rate,
+            }
+        })
+        .collect()
+}
+
+pub fn total_amount(revenue: &[Revenue]) -> f64 {
+    revenue.iter().map(|it| it.amount).sum()
+}
+
+pub fn format_money(amount: f64, currency: &str) -> String {
+    format!("{:.2} {}", amount, currency)
+}
+
+#[cfg(test)]
+mod billing_test {
+    use chrono::NaiveTime;
+
+    use crate::{ClockEntry, Granularity, Project};
+    use crate::billing::{Billing, format_money, revenue, rounded_seconds, total_amount};
+
+    fn entry(from: (u32, u32), to: (u32, u32), project: Option<&str>, billable: bool) -> ClockEntry {
+        ClockEntry {
+            from: NaiveTime::from_hms(from.0, from.1, 0),
+            to: NaiveTime::from_hms(to.0, to.1, 0),
+            project: project.map(str::to_string),
+            billable,
+            ..ClockEntry::default()
+        }
+    }
+
+    #[test]
+    fn rounds_to_the_granularity() {
+        assert_eq!(rounded_seconds(Granularity::Relaxed, 3 * 3600 - 1), 2 * 3600);
+        assert_eq!(rounded_seconds(Granularity::Detailed, 3600 + 29 * 60), 3600 + 15 * 60);
+        assert_eq!(rounded_seconds(Granularity::Scientific, 1234), 1234);
+    }
+
+    #[test]
+    fn computes_revenue_per_project() {
+        let entries = vec![
+            entry((9, 0), (10, 30), Some("Website"), true),
+            entry((10, 30), (11, 10), Some("Website"), true),
+            entry((11, 10), (12, 0), Some("Website"), false),
+            entry((13, 0), (14, 0), None, true),
+            entry((14, 0), (15, 0), Some("Internal"), true),
+        ];
+        let projects = vec![
+            Project { name: "Website".to_string(), client: None, rate: Some(100.0) },
+            Project { name: "Internal".to_string(), client: None, rate: None },
+        ];
+        let billing = Billing { default_rate: Some(50.0), ..Billing::default() };
+
+        let revenue = revenue(&entries, &projects, &billing, Granularity::Detailed);
+
+        assert_eq!(revenue.len(), 3);
+        assert_eq!(revenue[0].project.as_deref(), Some("Internal"));
+        assert_eq!(revenue[0].amount, 50.0);
+        assert_eq!(revenue[1].project.as_deref(), Some("Website"));
+        assert_eq!(revenue[1].billable_seconds, 2 * 3600);
+        assert_eq!(revenue[1].amount, 200.0);
+        assert_eq!(revenue[2].project, None);
+        assert_eq!(revenue[2].amount, 50.0);
+        assert_eq!(format_money(total_amount(&revenue), &billing.currency), "300.00 EUR");
+    }
+
+    #[test]
+    fn no_rate_means_no_revenue() {
+        let revenue = revenue(&[entry((9, 0), (10, 0), None, true)], &[], &Billing::default(), Granularity::Detailed);
+        assert_eq!(revenue[0].rate, None);
+        assert_eq!(revenue[0].amount, 0.0);
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{Duration, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{ClockEntry, Granularity, granularity_picker};
+
+/// A pause of the ongoing recording, still going on while it has no end.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Break {
+    pub from: NaiveTime,
+    #[serde(default)]
+    pub to: Option<NaiveTime>,
+}
+
+impl Break {
+    /// The length of the break, up to `now` while it's still going on.
+    pub fn duration(&self, now: NaiveTime) -> Duration {
+        Duration::zero().max(self.to.unwrap_or(now).signed_duration_since(self.from))
+    }
+}
+
+/// How a stopped recording with breaks turns into entries.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StopMode {
+    /// One entry from the start of the recording, shortened by the breaks
+    WithoutBreaks,
+    /// An entry for each part of the recording between the breaks
+    SplitAroundBreaks,
+}
+
+pub fn is_paused(breaks: &[Break]) -> bool {
+    breaks.last().is_some_and(|it| it.to.is_none())
+}
+
+/// Starts a break at `at`, unless the recording is already paused.
+pub fn pause(breaks: &mut Vec<Break>, at: NaiveTime) -> bool {
+    if is_paused(breaks) {
+        return false;
+    }
+    breaks.push(Break { from: at, to: None });
+    true
+}
+
+/// Ends the ongoing break at `at`, unless the recording isn't paused.
+pub fn resume(breaks: &mut [Break], at: NaiveTime) -> bool {
+    match breaks.last_mut().filter(|it| it.to.is_none()) {
+        Some(ongoing) => {
+            ongoing.to = Some(at.max(ongoing.from));
+            true
+        },
+        None => false,
+    }
+}
+
+pub fn total(breaks: &[Break], now: NaiveTime) -> Duration {
+    breaks.iter().fold(Duration::zero(), |total, it| total + it.duration(now))
+}
+
+/// Rounds the breaks to the granularity, like the recording they belong to.
+pub fn normalize(breaks: &mut [Break], granularity: Granularity) {
+    for it in breaks {
+        it.from = granularity_picker::normalize(it.from, granularity);
+        it.to = it.to.map(|to| granularity_picker::normalize(to, granularity));
+    }
+}
+
+/// The time recorded so far, without the breaks.
+pub fn elapsed(recording: &ClockEntry, breaks: &[Break], now: NaiveDateTime) -> Duration {
+    let since_start = now.signed_duration_since(recording.date.and_time(recording.from));
+    Duration::zero().max(since_start - total(breaks, now.time()))
+}
+
+/// The entry shortened by the breaks within it, a break still going on ends when the entry does.
+pub fn without_breaks(entry: &ClockEntry, breaks: &[Break]) -> ClockEntry {
+    let to = entry.to - within(entry, breaks).iter().fold(Duration::zero(), |total, (from, to)| total + to.signed_duration_since(*from));
+    ClockEntry { to: to.max(entry.from), ..entry.clone() }
+}
+
+/// The parts of the entry between the breaks. The first part keeps the ID of the entry.
+pub fn split_around(entry: &ClockEntry, breaks: &[Break]) -> Vec<ClockEntry> {
+    let mut parts = Vec::new();
+    let mut from = entry.from;
+    for (break_from, break_to) in within(entry, breaks).into_iter().chain(std::iter::once((entry.to, entry.to))) {
+        if break_from > from {
+            let part = if parts.is_empty() { entry.clone() } else { entry.copy() };
+            parts.push(ClockEntry { from, to: break_from, ..part });
+        }
+        from = from.max(break_to);
+    }
+    parts
+}
+
+/// The breaks clipped to the times of the entry, in order.
+fn within(entry: &ClockEntry, breaks: &[Break]) -> Vec<(NaiveTime, NaiveTime)> {
+    let mut clipped: Vec<(NaiveTime, NaiveTime)> = breaks.iter()
+        .map(|it| (it.from.max(entry.from), it.to.unwrap_or(entry.to).min(entry.to)))
+        .filter(|(from, to)| from < to)
+        .collect();
+    clipped.sort();
+    clipped
+}
+
+#[cfg(test)]
+mod breaks_test {
+    use chrono::{Duration, NaiveDate, NaiveTime};
+
+    use crate::breaks::{Break, elapsed, is_paused, normalize, pause, resume, split_around, total, without_breaks};
+    use crate::{ClockEntry, Granularity};
+
+    fn time(hours: u32, minutes: u32) -> NaiveTime {
+        NaiveTime::from_hms(hours, minutes, 0)
+    }
+
+    fn times(entry: &ClockEntry) -> (NaiveTime, NaiveTime) {
+        (entry.from, entry.to)
+    }
+
+    fn recording() -> ClockEntry {
+        ClockEntry { from: time(9, 0), to: time(17, 0), description: "Coding".to_string(), ..ClockEntry::default() }
+    }
+
+    #[test]
+    fn pauses_and_resumes() {
+        let mut breaks = Vec::new();
+
+        assert!(pause(&mut breaks, time(12, 0)));
+        assert!(!pause(&mut breaks, time(12, 15)));
+        assert!(is_paused(&breaks));
+        assert_eq!(total(&breaks, time(12, 30)), Duration::minutes(30));
+        assert!(resume(&mut breaks, time(12, 45)));
+        assert!(!resume(&mut breaks, time(13, 0)));
+
+        assert_eq!(breaks, vec![Break { from: time(12, 0), to: Some(time(12, 45)) }]);
+        assert_eq!(total(&breaks, time(16, 0)), Duration::minutes(45));
+    }
+
+    #[test]
+    fn counts_the_elapsed_time_without_the_breaks() {
+        let recording = ClockEntry { date: NaiveDate::from_ymd(2022, 3, 14), ..recording() };
+        let now = NaiveDate::from_ymd(2022, 3, 14).and_hms(12, 30, 15);
+
+        assert_eq!(elapsed(&recording, &[], now), Duration::seconds(3 * 3600 + 30 * 60 + 15));
+        assert_eq!(elapsed(&recording, &[Break { from: time(12, 0), to: None }], now), Duration::seconds(3 * 3600));
+        assert_eq!(elapsed(&recording, &[], NaiveDate::from_ymd(2022, 3, 14).and_hms(8, 0, 0)), Duration::zero());
+    }
+
+    #[test]
+    fn shortens_the_entry_by_the_breaks() {
+        let breaks = vec![
+            Break { from: time(12, 0), to: Some(time(13, 0)) },
+            Break { from: time(16, 30), to: None },
+        ];
+
+        assert_eq!(times(&without_breaks(&recording(), &breaks)), (time(9, 0), time(15, 30)));
+    }
+
+    #[test]
+    fn splits_the_entry_around_the_breaks() {
+        let entry = recording();
+        let breaks = vec![
+            Break { from: time(15, 0), to: Some(time(15, 15)) },
+            Break { from: time(12, 0), to: Some(time(13, 0)) },
+            Break { from: time(16, 30), to: None },
+        ];
+
+        let parts = split_around(&entry, &breaks);
+
+        assert_eq!(parts.iter().map(times).collect::<Vec<_>>(), vec![
+            (time(9, 0), time(12, 0)),
+            (time(13, 0), time(15, 0)),
+            (time(15, 15), time(16, 30)),
+        ]);
+        assert_eq!(parts[0].id, entry.id);
+        assert_ne!(parts[1].id, entry.id);
+        assert!(parts.iter().all(|it| it.description == "Coding"));
+        assert_eq!(split_around(&entry, &[]), vec![entry]);
+    }
+
+    #[test]
+    fn rounds_the_breaks_to_the_granularity() {
+        let mut breaks = vec![Break { from: time(12, 7), to: Some(time(12, 52)) }, Break { from: time(15, 20), to: None }];
+
+        normalize(&mut breaks, Granularity::Reasonable);
+
+        assert_eq!(breaks, vec![Break { from: time(12, 0), to: Some(time(12, 30)) }, Break { from: time(15, 0), to: None }]);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+use std::io;
+
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, ListView, SelectView};
+use cursive_table_view::TableView;
+use uuid::Uuid;
+
+use crate::{app_context, backup, CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, ClockKing, granularity_picker, history, invoice, project_picker, stats_view, tags, time_picker};
+use crate::clock_entries_table::{ClockEntryColumn, Row};
+use crate::history::Command;
+
+const ADD_TAGS: &str = "bulk_add_tags";
+const REMOVE_TAGS: &str = "bulk_remove_tags";
+const MOVE_TO_DATE: &str = "bulk_move_to_date";
+const EXPORT_FILE_NAME: &str = "bulk_export_file_name";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum BulkAction {
+    Clocked,
+    Unclocked,
+    Delete,
+    Retag,
+    Move,
+    Project,
+    Export,
+}
+
+/// Marks or unmarks the selected entry, and moves on to the next row.
+pub fn toggle_mark(s: &mut Cursive) {
+    let selected = s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        let selected = selected_id(t);
+        move_selection(t, 1);
+        selected
+    }).expect("Unable to get clock entries table");
+    if let Some(id) = selected {
+        app_context::fetch(s).toggle_mark(id);
+    }
+    clock_entries_table::update_marks(s);
+}
+
+/// Marks the selected entry and the one above or below it, like a shift-click range.
+pub fn extend_marks(s: &mut Cursive, step: isize) {
+    let selected = s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        let first = selected_id(t);
+        move_selection(t, step);
+        [first, selected_id(t)]
+    }).expect("Unable to get clock entries table");
+    selected.into_iter().flatten().for_each(|id| app_context::fetch(s).mark(id));
+    clock_entries_table::update_marks(s);
+}
+
+/// Marks all the visible entries, or clears the marks when there are any.
+pub fn toggle_all_marks(s: &mut Cursive) {
+    let marks = if marked_entries(s).is_empty() {
+        clock_entries_table::get_visible_clock_entries(s).into_iter().map(|it| it.id).collect()
+    } else {
+        HashSet::new()
+    };
+    app_context::fetch(s).set_marks(marks);
+    clock_entries_table::update_marks(s);
+}
+
+fn selected_id(t: &mut TableView<Row, ClockEntryColumn>) -> Option<Uuid> {
+    clock_entries_table::selected_entry(t)
+        .and_then(|index| t.borrow_item(index))
+        .and_then(Row::entry)
+        .map(|it| it.id)
+}
+
+fn move_selection(t: &mut TableView<Row, ClockEntryColumn>, step: isize) {
+    if let Some(row) = t.row().and_then(|it| it.checked_add_signed(step)).filter(|it| *it < t.len()) {
+        t.set_selected_row(row);
+    }
+}
+
+/// The marked entries among the visible ones, the filter may hide some.
+pub fn marked_entries(s: &mut Cursive) -> Vec<ClockEntry> {
+    let marks = app_context::fetch(s).marks();
+    clock_entries_table::get_visible_clock_entries(s).into_iter()
+        .filter(|it| marks.contains(&it.id))
+        .collect()
+}
+
+pub fn bulk_actions(s: &mut Cursive) {
+    let count = marked_entries(s).len();
+    if count == 0 {
+        s.add_layer(Dialog::info("Mark entries with v, or shift and the arrow keys, first"));
+        return;
+    }
+    let mut actions = SelectView::new();
+    actions.add_all([
+        ("Mark clocked", BulkAction::Clocked),
+        ("Mark unclocked", BulkAction::Unclocked),
+        ("Delete", BulkAction::Delete),
+        ("Add or remove tags", BulkAction::Retag),
+        ("Move to another day", BulkAction::Move),
+        ("Change project", BulkAction::Project),
+        ("Export", BulkAction::Export),
+    ]);
+    actions.set_on_submit(|s, action: &BulkAction| {
+        s.pop_layer();
+        match action {
+            BulkAction::Clocked => edit_marked(s, "Mark clocked", |it| it.is_clocked = true),
+            BulkAction::Unclocked => edit_marked(s, "Mark unclocked", |it| it.is_clocked = false),
+            BulkAction::Delete => delete_marked(s),
+            BulkAction::Retag => retag_marked(s),
+            BulkAction::Move => move_marked(s),
+            BulkAction::Project => change_project_of_marked(s),
+            BulkAction::Export => export_marked(s),
+        }
+    });
+    s.add_layer(
+        Dialog::around(actions)
+            .title(format!("{} marked entries", count))
+            .button("Clear marks", |s| {
+                s.pop_layer();
+                toggle_all_marks(s);
+            })
+            .button("Cancel", |s| { s.pop_layer(); })
+    );
+}
+
+/// The edit of the entries as one step, leaving out the entries it doesn't change. None when it changes none.
+pub fn edit_batch(label: &str, entries: &[ClockEntry], edit: impl Fn(&mut ClockEntry)) -> Option<Command> {
+    let commands: Vec<Command> = entries.iter()
+        .filter_map(|before| {
+            let mut after = before.clone();
+            edit(&mut after);
+            (after != *before).then(|| {
+                after.touch();
+                Command::Edit { before: before.clone(), after }
+            })
+        })
+        .collect();
+    (!commands.is_empty()).then(|| Command::Batch { label: format!("{} on {} entries", label, commands.len()), commands })
+}
+
+/// Deleting the entries as one step. None when there are none.
+pub fn delete_batch(entries: &[ClockEntry]) -> Option<Command> {
+    (!entries.is_empty()).then(|| Command::Batch {
+        label: format!("Delete {} entries", entries.len()),
+        commands: entries.iter().cloned().map(Command::Delete).collect(),
+    })
+}
+
+fn edit_marked(s: &mut Cursive, label: &str, edit: impl Fn(&mut ClockEntry)) {
+    let batch = edit_batch(label, &marked_entries(s), edit);
+    run_batch(s, batch);
+}
+
+/// Applies the batch and records it as one step, then clears the marks. A batch failing partway is left out entirely.
+fn run_batch(s: &mut Cursive, batch: Option<Command>) {
+    if let Some(batch) = batch {
+        match history::apply(s, &batch) {
+            Ok(()) => app_context::fetch(s).record(batch),
+            Err(e) => s.add_layer(Dialog::info(format!("The marked entries were left as they were: {}", e))),
+        }
+    }
+    app_context::fetch(s).set_marks(HashSet::new());
+    stats_view::update_stats(s);
+}
+
+fn delete_marked(s: &mut Cursive) {
+    let count = marked_entries(s).len();
+    s.add_layer(
+        cursive_extras::confirm_dialog(
+            "Delete entries",
+            format!("Delete the {} marked entries?", count),
+            |s| {
+                s.pop_layer();
+                let batch = delete_batch(&marked_entries(s));
+                run_batch(s, batch);
+            }
+        ));
+}
+
+/// The tags with `added` appended and `removed` taken out.
+pub fn retagged(tags: &[String], added: &[String], removed: &[String]) -> Vec<String> {
+    tags::merge_tags(tags.to_vec(), added.to_vec()).into_iter()
+        .filter(|it| !removed.contains(it))
+        .collect()
+}
+
+fn retag_marked(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(
+            ListView::new()
+                .child("Add tags", EditView::new().with_name(ADD_TAGS).min_width(30))
+                .child("Remove tags", EditView::new().with_name(REMOVE_TAGS).min_width(30))
+        )
+            .title("Retag the marked entries")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", |s| {
+                let added = tags::parse_tag_list(&edit_view_value(s, ADD_TAGS));
+                let removed = tags::parse_tag_list(&edit_view_value(s, REMOVE_TAGS));
+                s.pop_layer();
+                edit_marked(s, "Retag", |it| it.tags = retagged(&it.tags, &added, &removed));
+            })
+    );
+}
+
+fn move_marked(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(EditView::new().content(time_picker::today().to_string()).with_name(MOVE_TO_DATE).min_width(20))
+            .title("Move the marked entries to")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", |s| {
+                let value = edit_view_value(s, MOVE_TO_DATE);
+                match time_picker::parse_date(&value) {
+                    Some(date) => {
+                        s.pop_layer();
+                        edit_marked(s, &format!("Move to {}", date), |it| it.date = date);
+                    },
+                    None => s.add_layer(Dialog::info(format!("Invalid date: {}", value))),
+                }
+            })
+    );
+}
+
+fn change_project_of_marked(s: &mut Cursive) {
+    let projects = app_context::fetch(s).projects();
+    s.add_layer(
+        Dialog::around(project_picker::project_picker_input(None, &projects))
+            .title("Change the project of the marked entries")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", |s| {
+                let project = project_picker::project_picker_value(s);
+                s.pop_layer();
+                edit_marked(s, "Change project", |it| it.project = project.clone());
+            })
+    );
+}
+
+/// Writes the marked entries as an export, which `clockking import FILE` merges into another database. The settings
+/// are left out, like in `clockking export` without `--with-settings`.
+fn export_marked(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(EditView::new().content(format!("clockking-selection-{}.json", time_picker::today())).with_name(EXPORT_FILE_NAME).min_width(40))
+            .title("Export the marked entries as")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Save", |s| {
+                let file_name = edit_view_value(s, EXPORT_FILE_NAME);
+                s.pop_layer();
+                save_export(s, file_name, false);
+            })
+    );
+}
+
+/// Saves the export, asking first when the file exists.
+fn save_export(s: &mut Cursive, file_name: String, overwrite: bool) {
+    let database = ClockKing {
+        clock_entries: marked_entries(s),
+        granularity: granularity_picker::get_granularity(s),
+        projects: app_context::fetch(s).projects(),
+        ..ClockKing::default()
+    };
+    let content = match serde_json::to_string_pretty(&backup::export(&database, None)) {
+        Ok(content) => content,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Unable to export the marked entries: {}", e)));
+            return;
+        },
+    };
+    let message = match invoice::write_file(&file_name, &content, overwrite) {
+        Ok(()) => format!("Exported {} entries to {}", database.clock_entries.len(), file_name),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            s.add_layer(cursive_extras::confirm_dialog(
+                "Export",
+                format!("{} already exists. Overwrite it?", file_name),
+                move |s| {
+                    s.pop_layer();
+                    save_export(s, file_name.clone(), true);
+                }
+            ));
+            return;
+        },
+        Err(e) => format!("Unable to export to {}: {}", file_name, e),
+    };
+    s.add_layer(Dialog::info(message));
+}
+
+fn edit_view_value(s: &mut Cursive, name: &str) -> String {
+    s.call_on_name(name, |e: &mut EditView| e.get_content().to_string())
+        .unwrap_or_else(|| panic!("{} should be defined", name))
+}
+
+#[cfg(test)]
+mod bulk_test {
+    use chrono::NaiveDate;
+
+    use crate::ClockEntry;
+    use crate::bulk::{delete_batch, edit_batch, retagged};
+    use crate::history::Command;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|it| it.to_string()).collect()
+    }
+
+    #[test]
+    fn adds_and_removes_tags() {
+        assert_eq!(retagged(&tags(&["meeting", "team"]), &tags(&["review", "team"]), &tags(&["meeting"])), tags(&["team", "review"]));
+        assert_eq!(retagged(&tags(&[]), &tags(&[]), &tags(&["meeting"])), tags(&[]));
+    }
+
+    fn entry(description: &str, tags: &[&str]) -> ClockEntry {
+        ClockEntry { description: description.to_string(), tags: self::tags(tags), ..ClockEntry::default() }
+    }
+
+    fn edited(batch: Option<Command>) -> (String, Vec<(String, ClockEntry)>) {
+        match batch {
+            Some(Command::Batch { label, commands }) => (label, commands.into_iter()
+                .map(|it| match it {
+                    Command::Edit { before, after } => (before.description, after),
+                    other => panic!("Expected an edit, got {:?}", other),
+                })
+                .collect()),
+            other => panic!("Expected a batch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn retags_only_the_entries_it_changes() {
+        let entries = vec![entry("Coding", &["review"]), entry("Standup", &["meeting"])];
+
+        let (label, edits) = edited(edit_batch("Retag", &entries, |it| it.tags = retagged(&it.tags, &tags(&["review"]), &[])));
+
+        assert_eq!(label, "Retag on 1 entries");
+        assert_eq!(edits.len(), 1);
+        assert_eq!((edits[0].0.as_str(), &edits[0].1.tags), ("Standup", &tags(&["meeting", "review"])));
+    }
+
+    #[test]
+    fn moves_the_entries_to_the_day() {
+        let date = NaiveDate::from_ymd(2022, 3, 15);
+        let entries = vec![entry("Coding", &[]), entry("Standup", &[])];
+
+        let (label, edits) = edited(edit_batch("Move to 2022-03-15", &entries, |it| it.date = date));
+
+        assert_eq!(label, "Move to 2022-03-15 on 2 entries");
+        assert!(edits.iter().all(|(_, after)| after.date == date));
+        assert!(edit_batch("Move to 2022-03-15", &edits.into_iter().map(|it| it.1).collect::<Vec<_>>(), |it| it.date = date).is_none());
+    }
+
+    #[test]
+    fn deletes_the_entries_as_one_step() {
+        let entries = vec![entry("Coding", &[]), entry("Standup", &[])];
+
+        assert_eq!(delete_batch(&entries), Some(Command::Batch {
+            label: "Delete 2 entries".to_string(),
+            commands: entries.into_iter().map(Command::Delete).collect(),
+        }));
+        assert_eq!(delete_batch(&[]), None);
+    }
+}
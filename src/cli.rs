@@ -0,0 +1,537 @@
+use std::error::Error;
+
+use std::fs;
+use std::io;
+
+use chrono::{Datelike, NaiveDate};
+use chrono::naive::{MAX_DATE, MIN_DATE};
+use uuid::Uuid;
+
+use crate::{absences, backup, billing, ClockEntry, config, db, format, Granularity, invoice, jira, Project, project_picker, quick_add, record, report, summary, targets, time_picker, timeline, validation, webhook};
+use crate::validation::Scope;
+use crate::absences::{Absence, AbsenceKind};
+use crate::backup::ImportMode;
+use crate::filter::EntryFilter;
+use crate::invoice::InvoiceFormat;
+use crate::report::{GroupBy, ReportFormat};
+
+const USAGE: &str = "Usage: clockking [COMMAND]
+
+Without a command, starts the interactive time tracker.
+
+Commands:
+  sync jira    Push unclocked entries as worklogs to the configured Jira server
+                 --from DATE --to DATE                 (default: all entries)
+                 --dry-run                             (list the entries without pushing them)
+  sync webhook Send unclocked entries to the configured webhook, retrying the outbox
+  open ID      Start the interactive time tracker with the entry open for editing
+  entry list [--date DATE]     List the entries of a day with their IDs (default: today)
+  entry show ID                Print an entry as JSON
+  add [DATE] FROM-TO DESCRIPTION [#TAG...] [@PROJECT]
+               Add an entry, e.g. `add 9:00-10:30 standup #meeting @website`
+  switch DESCRIPTION [#TAG...] [@PROJECT]
+               End the ongoing recording now and start recording the next task, e.g. `switch code review #dev`
+  check        List the overlapping entries and the gaps between entries, fails when entries overlap
+                 --date DATE | --from DATE --to DATE   (default: today)
+  report       Print a Markdown or org-mode report of a day or a date range
+                 --date DATE | --from DATE --to DATE   (YYYY-MM-DD, today, yesterday; default: today)
+                 --format markdown|org                 (default: markdown)
+                 --group-by description|project|entry  (default: description)
+                 --tag TAG                             (default: all entries)
+                 --output FILE                         (default: print to stdout)
+  summary      Print hours per day, week and month over a date range
+                 --from DATE --to DATE                 (default: this month)
+                 --tag TAG                             (default: all entries)
+  project list                 List the projects in the catalogue
+  project add NAME [--client CLIENT] [--rate RATE]
+                               Add a project, or update an existing one
+  project remove NAME          Remove a project from the catalogue
+  billing      Print the default hourly rate and currency, or change them
+                 --rate RATE --currency CURRENCY
+  targets      Print the working time targets and the flex balance, or change the targets
+                 --hours H,H,H,H,H,H,H                 (from Monday, e.g. 8,8,8,8,4)
+                 --start DATE                          (first day of the flex balance)
+                 --vacation-days DAYS                  (per year)
+                 --off                                 (no targets)
+  absence list [--year YEAR]   List the absences of a year (default: this year)
+  absence add DATE vacation|sick|holiday [--half-day] [--note NOTE]
+                               Add an absence, replacing the one on the same day
+  absence remove DATE          Remove the absence of a day
+  absence import FILE.ics      Import the all-day events of a calendar as public holidays
+  vacation [--year YEAR]       Print the used and remaining vacation days of a year
+  invoice CLIENT
+               Create an invoice of the billable entries of a client's projects, and mark them as invoiced
+                 --from DATE --to DATE                 (default: this month)
+                 --format text|markdown|html           (default: text)
+                 --output FILE                         (default: NUMBER.txt, NUMBER.md or NUMBER.html)
+                 --force                               (overwrite an existing invoice file)
+                 --dry-run                             (print it without saving or marking anything)
+  export       Export the whole database as versioned JSON
+                 --with-settings                       (include config.json, with its API tokens)
+                 --output FILE                         (default: print to stdout)
+  import FILE  Import a file created by `export`
+                 --merge | --replace                   (default: --merge)
+  help         Print this message";
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["sync", "jira", options @ ..] => sync_jira(options),
+        ["sync", "webhook"] => sync_webhook(),
+        ["entry", "list", options @ ..] => list_entries(options),
+        ["entry", "show", id] => show_entry(id),
+        ["add", words @ ..] => add_entry(words),
+        ["switch", words @ ..] => switch_task(words),
+        ["check", options @ ..] => check(options),
+        ["report", options @ ..] => print_report(options),
+        ["summary", options @ ..] => print_summary(options),
+        ["project", "list"] => list_projects(),
+        ["project", "add", name, options @ ..] => add_project(name, options),
+        ["project", "remove", name] => remove_project(name),
+        ["billing", options @ ..] => billing(options),
+        ["targets", options @ ..] => working_time_targets(options),
+        ["absence", "list", options @ ..] => list_absences(options),
+        ["absence", "add", date, kind, options @ ..] => add_absence(date, kind, options),
+        ["absence", "remove", date] => remove_absence(date),
+        ["absence", "import", file_name] => import_absences(file_name),
+        ["vacation", options @ ..] => vacation(options),
+        ["invoice", client, options @ ..] => create_invoice(client, options),
+        ["export", options @ ..] => export(options),
+        ["import", file_name, options @ ..] => import(file_name, options),
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        _ => Err(format!("Unknown command: {}\n\n{}", args.join(" "), USAGE).into()),
+    }
+}
+
+fn sync_jira(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let jira_config = config::load()?.jira.ok_or("No `jira` section found in ./.clockking/config.json")?;
+    let period = date_option(options, "--from")?.unwrap_or(MIN_DATE)..=date_option(options, "--to")?.unwrap_or(MAX_DATE);
+    if options.contains(&"--dry-run") {
+        let model = db::load_model()?;
+        let pending: Vec<&ClockEntry> = model.clock_entries.iter().filter(|it| jira::is_pending(it, &period)).collect();
+        pending.iter().for_each(|it| println!(
+            "{}  {}  {} - {}  {}",
+            jira::extract_issue_key(&it.description).as_deref().unwrap_or("-"),
+            it.date,
+            format::format_naive_time(model.granularity, it.from),
+            format::format_naive_time(model.granularity, it.to),
+            it.description
+        ));
+        println!("Would push {} entries", pending.len());
+        return Ok(());
+    }
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let results = jira::push_worklogs(&jira_config, &mut model, &period);
+    db::store_model(&model);
+
+    for result in &results {
+        let key = result.issue_key.as_deref().unwrap_or("-");
+        match &result.outcome {
+            Ok(()) => println!("[ok]     {} '{}'", key, result.description),
+            Err(e) => println!("[failed] {} '{}': {}", key, result.description, e),
+        }
+    }
+    let failed = results.iter().filter(|it| it.outcome.is_err()).count();
+    if failed > 0 {
+        Err(format!("{} of {} entries were not pushed", failed, results.len()).into())
+    } else {
+        println!("Pushed {} entries", results.len());
+        Ok(())
+    }
+}
+
+fn sync_webhook() -> Result<(), Box<dyn Error>> {
+    let webhook_config = config::load()?.webhook.ok_or("No `webhook` section found in ./.clockking/config.json")?;
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let results = webhook::sync_entries(&webhook_config, &mut model.clock_entries);
+    db::store_model(&model);
+
+    println!("{}", webhook::format_results(&results));
+    let failed = results.iter().filter(|it| it.outcome.is_err()).count();
+    if failed > 0 {
+        Err(format!("{} of {} entries were not delivered", failed, results.len()).into())
+    } else {
+        Ok(())
+    }
+}
+
+fn list_entries(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let date = date_option(options, "--date")?.unwrap_or_else(time_picker::today);
+    let (model, without_ids) = db::read_model()?;
+    if without_ids {
+        eprintln!("Some entries are from before IDs existed, start clockking once to give them IDs that stay the same");
+    }
+    let mut entries: Vec<&ClockEntry> = model.clock_entries.iter().filter(|it| it.date == date).collect();
+    entries.sort_by_key(|it| it.from);
+    entries.iter().for_each(|it| println!(
+        "{}  {} - {}  {}",
+        it.id,
+        format::format_naive_time(model.granularity, it.from),
+        format::format_naive_time(model.granularity, it.to),
+        it.description
+    ));
+    Ok(())
+}
+
+fn show_entry(id: &str) -> Result<(), Box<dyn Error>> {
+    let id = id.parse::<Uuid>().map_err(|e| format!("Invalid entry ID {}: {}", id, e))?;
+    let model = db::load_model()?;
+    let entry = model.clock_entries.iter()
+        .chain(model.recording.iter())
+        .find(|it| it.id == id)
+        .ok_or(format!("No entry with ID {}", id))?;
+    println!("{}", serde_json::to_string_pretty(entry)?);
+    Ok(())
+}
+
+fn add_entry(words: &[&str]) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let now = time_picker::now_naive_time(Granularity::Scientific);
+    let entry = quick_add::parse(&words.join(" "), &model.projects, model.granularity, time_picker::today(), now)?;
+    let rules = config::load()?.validation;
+    let issues = validation::validate(&entry, &model.clock_entries, &rules, Scope::Entry);
+    if validation::has_errors(&issues) {
+        return Err(validation::render(&issues).into());
+    }
+    if !issues.is_empty() {
+        eprintln!("{}", validation::render(&issues));
+    }
+    println!(
+        "{}  {}  {} - {}  {}",
+        entry.id,
+        entry.date,
+        format::format_naive_time(entry.granularity, entry.from),
+        format::format_naive_time(entry.granularity, entry.to),
+        entry.description
+    );
+    model.clock_entries.push(entry);
+    db::store_model(&model);
+    Ok(())
+}
+
+/// Ends the ongoing recording like stopping it in the app, split around its breaks, and starts the next one.
+fn switch_task(words: &[&str]) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let recording = model.recording.clone().ok_or("There's no recording to switch from, start one in the app first")?;
+    let at = time_picker::now_naive_time(model.granularity);
+    let next = record::parse_next_task(&words.join(" "), &model.projects, model.granularity, time_picker::today(), at)?;
+    let entries = record::switched_entries(&recording, &model.breaks, at);
+    let rules = config::load()?.validation;
+    let issues = record::switch_issues(&entries, &next, &model.clock_entries, &rules);
+    if validation::has_errors(&issues) {
+        return Err(validation::render(&issues).into());
+    }
+    if !issues.is_empty() {
+        eprintln!("{}", validation::render(&issues));
+    }
+    entries.iter().for_each(|it| println!(
+        "{}  {}  {} - {}  {}",
+        it.id,
+        it.date,
+        format::format_naive_time(model.granularity, it.from),
+        format::format_naive_time(model.granularity, it.to),
+        it.description
+    ));
+    println!("Recording '{}' since {}", next.description, format::format_naive_time(model.granularity, next.from));
+    model.clock_entries.extend(entries);
+    model.recording = Some(next);
+    model.breaks.clear();
+    db::store_model(&model);
+    Ok(())
+}
+
+fn check(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let date = date_option(options, "--date")?.unwrap_or_else(time_picker::today);
+    let from = date_option(options, "--from")?.unwrap_or(date);
+    let to = date_option(options, "--to")?.unwrap_or(from.max(date));
+    let model = db::load_model()?;
+    let issues = timeline::render_issues(&model.clock_entries, from..=to, model.granularity);
+    if issues.is_empty() {
+        println!("No overlaps or gaps.");
+    } else {
+        print!("{}", issues);
+    }
+    let entries: Vec<ClockEntry> = model.clock_entries.into_iter().filter(|it| (from..=to).contains(&it.date)).collect();
+    match timeline::overlaps(&entries).len() {
+        0 => Ok(()),
+        overlaps => Err(format!("Found {} overlapping entries", overlaps).into()),
+    }
+}
+
+fn print_report(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let date = date_option(options, "--date")?.unwrap_or_else(time_picker::today);
+    let from = date_option(options, "--from")?.unwrap_or(date);
+    let to = date_option(options, "--to")?.unwrap_or(from.max(date));
+    let report_format = match option(options, "--format") {
+        Some(value) => ReportFormat::parse(value).ok_or(format!("Unknown report format: {}", value))?,
+        None => ReportFormat::Markdown,
+    };
+    let group_by = match option(options, "--group-by") {
+        Some(value) => GroupBy::parse(value).ok_or(format!("Unknown grouping: {}", value))?,
+        None => GroupBy::Description,
+    };
+
+    let model = db::load_model()?;
+    let entries = filtered_entries(&model.clock_entries, options)?;
+    let mut content = report::render(&entries, &model.projects, &model.billing, from..=to, group_by, report_format, model.granularity);
+    if let Some(targets) = &model.targets {
+        content += &report::render_working_time(&model.clock_entries, targets, &model.absences, from..=to, report_format, model.granularity);
+    }
+    match option(options, "--output") {
+        Some(file_name) => fs::write(file_name, content)?,
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+fn print_summary(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let today = time_picker::today();
+    let from = date_option(options, "--from")?.unwrap_or_else(|| today.with_day(1).unwrap());
+    let to = date_option(options, "--to")?.unwrap_or_else(|| from.max(today));
+
+    let model = db::load_model()?;
+    let entries = filtered_entries(&model.clock_entries, options)?;
+    print!("{}", summary::render(&summary::summarize(&entries, from, to), model.granularity));
+    Ok(())
+}
+
+fn list_projects() -> Result<(), Box<dyn Error>> {
+    db::load_model()?.projects.iter().for_each(|project| println!("{}", project.label()));
+    Ok(())
+}
+
+fn add_project(name: &str, options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let rate = project_picker::parse_rate(option(options, "--rate").unwrap_or_default())?;
+    model.projects.retain(|it| it.name != name);
+    model.projects.push(Project {
+        name: name.to_string(),
+        client: option(options, "--client").map(str::to_string),
+        rate,
+    });
+    db::store_model(&model);
+    Ok(())
+}
+
+fn remove_project(name: &str) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    if !model.projects.iter().any(|it| it.name == name) {
+        return Err(format!("No project named {}", name).into());
+    }
+    model.projects.retain(|it| it.name != name);
+    db::store_model(&model);
+    Ok(())
+}
+
+fn billing(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    if let Some(rate) = option(options, "--rate") {
+        model.billing.default_rate = project_picker::parse_rate(rate)?;
+    }
+    if let Some(currency) = option(options, "--currency") {
+        model.billing.currency = currency.to_string();
+    }
+    if !options.is_empty() {
+        db::store_model(&model);
+    }
+    match model.billing.default_rate {
+        Some(rate) => println!("Default rate: {}/h", billing::format_money(rate, &model.billing.currency)),
+        None => println!("No default rate, currency: {}", model.billing.currency),
+    }
+    model.projects.iter()
+        .filter_map(|project| project.rate.map(|rate| (project, rate)))
+        .for_each(|(project, rate)| println!("{}: {}/h", project.label(), billing::format_money(rate, &model.billing.currency)));
+    Ok(())
+}
+
+fn working_time_targets(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let (mut model, _lock) = db::load_model_for_update()?;
+    if options.contains(&"--off") {
+        model.targets = None;
+    } else if !options.is_empty() {
+        let mut targets = model.targets.clone().unwrap_or_default();
+        if let Some(hours) = option(options, "--hours") {
+            targets.hours = targets::parse_hours(hours)?;
+        }
+        if let Some(start) = date_option(options, "--start")? {
+            targets.start = Some(start);
+        }
+        if let Some(days) = option(options, "--vacation-days") {
+            targets.vacation_days = Some(days.parse::<f64>().ok().filter(|it| *it >= 0.0).ok_or(format!("Invalid vacation days: {}", days))?);
+        }
+        model.targets = Some(targets);
+    }
+    if !options.is_empty() {
+        db::store_model(&model);
+    }
+
+    match &model.targets {
+        Some(targets) => {
+            let today = time_picker::today();
+            let hms = |seconds: i64| format::format_hms(model.granularity, seconds);
+            let week = targets::balance(&model.clock_entries, targets, &model.absences, targets::week(today));
+            println!("Hours from Monday: {}", targets.hours.map(|it| it.to_string()).join(", "));
+            targets.start.iter().for_each(|start| println!("Counted from: {}", start));
+            targets.vacation_days.iter().for_each(|days| println!("Vacation days per year: {}", days));
+            println!("This week: {} / {}", hms(week.worked_seconds), hms(week.target_seconds));
+            println!("Flex balance: {}", format::format_signed_hms(model.granularity, targets::flex_balance(&model.clock_entries, targets, &model.absences, today, today)));
+        },
+        None => println!("No working time targets"),
+    }
+    Ok(())
+}
+
+fn list_absences(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let year = year_option(options)?;
+    db::load_model()?.absences.iter()
+        .filter(|it| it.date.year() == year)
+        .for_each(|it| println!("{}", it.label()));
+    Ok(())
+}
+
+fn add_absence(date: &str, kind: &str, options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let absence = Absence {
+        date: time_picker::parse_date(date).ok_or(format!("Invalid date: {}", date))?,
+        kind: AbsenceKind::parse(kind).ok_or(format!("Unknown absence: {}, use vacation, sick or holiday", kind))?,
+        half_day: options.contains(&"--half-day"),
+        note: option(options, "--note").unwrap_or_default().to_string(),
+    };
+    let (mut model, _lock) = db::load_model_for_update()?;
+    absences::add(&mut model.absences, absence);
+    db::store_model(&model);
+    Ok(())
+}
+
+fn remove_absence(date: &str) -> Result<(), Box<dyn Error>> {
+    let date = time_picker::parse_date(date).ok_or(format!("Invalid date: {}", date))?;
+    let (mut model, _lock) = db::load_model_for_update()?;
+    if !model.absences.iter().any(|it| it.date == date) {
+        return Err(format!("No absence on {}", date).into());
+    }
+    model.absences.retain(|it| it.date != date);
+    db::store_model(&model);
+    Ok(())
+}
+
+fn import_absences(file_name: &str) -> Result<(), Box<dyn Error>> {
+    let imported = absences::parse_ics(&fs::read_to_string(file_name)?)?;
+    let found = imported.holidays.len();
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let added = absences::merge(&mut model.absences, imported.holidays);
+    db::store_model(&model);
+    imported.skipped.iter().for_each(|it| println!("[skipped] {}", it));
+    println!("Imported {} public holidays, {} days already had an absence", added, found - added);
+    Ok(())
+}
+
+fn vacation(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let model = db::load_model()?;
+    let summary = absences::vacation_summary(&model.absences, model.targets.as_ref(), year_option(options)?);
+    print!("{}", absences::render_vacation_summary(&summary));
+    Ok(())
+}
+
+fn year_option(options: &[&str]) -> Result<i32, Box<dyn Error>> {
+    match option(options, "--year") {
+        Some(year) => Ok(year.parse::<i32>().map_err(|_| format!("Invalid year: {}", year))?),
+        None => Ok(time_picker::today().year()),
+    }
+}
+
+fn create_invoice(client: &str, options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let today = time_picker::today();
+    let from = date_option(options, "--from")?.unwrap_or_else(|| today.with_day(1).unwrap());
+    let to = date_option(options, "--to")?.unwrap_or_else(|| from.max(today));
+    let invoice_format = match option(options, "--format") {
+        Some(value) => InvoiceFormat::parse(value).ok_or(format!("Unknown invoice format: {}", value))?,
+        None => InvoiceFormat::Text,
+    };
+    let mut settings = config::load()?;
+    let invoice_config = settings.invoice.as_mut().ok_or("No `invoice` section found in ./.clockking/config.json")?;
+    let (mut model, _lock) = db::load_model_for_update()?;
+
+    let invoice = invoice::build(&model.clock_entries, &model.projects, &model.billing, invoice_config, client, from..=to, model.granularity)?;
+    let content = invoice::render(&invoice, invoice_format, model.granularity);
+    if options.contains(&"--dry-run") {
+        print!("{}", content);
+        return Ok(());
+    }
+    let file_name = option(options, "--output")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}.{}", invoice.number, invoice_format.extension()));
+    invoice::write_file(&file_name, &content, options.contains(&"--force")).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => format!("{} already exists, use --force to overwrite it", file_name),
+        _ => format!("Unable to save the invoice to {}: {}", file_name, e),
+    })?;
+
+    // The number goes first, so a failure can't leave marked entries and a number to be billed again
+    invoice_config.next_number += 1;
+    if let Err(e) = config::store(&settings) {
+        let _ = fs::remove_file(&file_name);
+        return Err(format!("Unable to store the next invoice number, invoice {} was not created: {}", invoice.number, e).into());
+    }
+    let projects = model.projects.clone();
+    model.clock_entries.iter_mut().for_each(|it| invoice::mark_invoiced(it, &projects, client, &(from..=to), &invoice.number));
+    db::store_model(&model);
+    println!("Invoice {} over {} saved to {}", invoice.number, billing::format_money(invoice.total(), &invoice.currency), file_name);
+    Ok(())
+}
+
+fn export(options: &[&str]) -> Result<(), Box<dyn Error>> {
+    let settings = if options.contains(&"--with-settings") { Some(config::load()?) } else { None };
+    let export = backup::export(&db::load_model()?, settings.as_ref());
+    let content = serde_json::to_string_pretty(&export)?;
+    match option(options, "--output") {
+        Some(file_name) => fs::write(file_name, content)?,
+        None => println!("{}", content),
+    }
+    Ok(())
+}
+
+fn import(file_name: &str, options: &[&str]) -> Result<(), Box<dyn Error>> {
+    if file_name.starts_with("--") {
+        return Err(format!("The file name comes before the options: clockking import FILE {}", file_name).into());
+    }
+    let mode = match options {
+        [] | ["--merge"] => ImportMode::Merge,
+        ["--replace"] => ImportMode::Replace,
+        _ => return Err(format!("Unknown import options: {}", options.join(" ")).into()),
+    };
+    let export = backup::parse(&fs::read_to_string(file_name)?)?;
+    let (mut model, _lock) = db::load_model_for_update()?;
+    let mut settings = if config::exists() { Some(config::load()?) } else { None };
+
+    let report = backup::import(&mut model, &mut settings, export, mode);
+    db::store_model(&model);
+    if let Some(settings) = settings {
+        config::store(&settings)?;
+    }
+
+    println!("Imported {} entries, {} already present", report.added, report.unchanged);
+    report.conflicts.iter().for_each(|conflict| println!("[conflict] {}", conflict));
+    Ok(())
+}
+
+fn filtered_entries(entries: &[ClockEntry], options: &[&str]) -> Result<Vec<ClockEntry>, String> {
+    let filter = EntryFilter {
+        tag: option(options, "--tag").map(|tag| tag.trim_start_matches('#').to_lowercase()),
+        ..EntryFilter::default()
+    };
+    Ok(filter.partition(entries.iter().cloned())?.0)
+}
+
+fn option<'a>(options: &[&'a str], name: &str) -> Option<&'a str> {
+    options.iter()
+        .position(|it| *it == name)
+        .and_then(|index| options.get(index + 1))
+        .copied()
+}
+
+fn date_option(options: &[&str], name: &str) -> Result<Option<NaiveDate>, Box<dyn Error>> {
+    option(options, name)
+        .map(|value| time_picker::parse_date(value).ok_or_else(|| format!("Invalid date for {}: {}", name, value).into()))
+        .transpose()
+}
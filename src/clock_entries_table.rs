@@ -1,74 +1,171 @@
 use std::cmp::Ordering;
 use std::ops::Add;
 
-use chrono::Duration;
+use chrono::{Duration, NaiveDate, NaiveTime};
 use cursive::align::HAlign;
 use cursive::Cursive;
 use cursive::traits::{Nameable, Resizable};
 use cursive::views::{NamedView, ResizedView};
 use cursive_table_view::{TableView, TableViewItem};
+use uuid::Uuid;
 
-use crate::{app_context, clock_entry_form, ClockEntry, ClockKing, format, granularity_picker, input, stats_view, time_picker};
+use crate::{app_context, clock_entry_form, ClockEntry, ClockKing, format, granularity_picker, stats_view, tags};
+use crate::history::Command;
+use crate::timeline::{self, Gap};
+use crate::validation::Scope;
 
 pub const CLOCK_ENTRIES_TABLE: &str   = "clock_entries";
 
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ClockEntryColumn {
+    Date,
     From,
     To,
     Description,
+    Project,
+    Tags,
     Duration,
     IsClocked,
+    Billable,
 }
 
 impl ClockEntryColumn {
     pub(crate) fn as_str(&self) -> &str {
         match *self {
+            ClockEntryColumn::Date => "Date",
             ClockEntryColumn::From => "From",
             ClockEntryColumn::To => "To",
             ClockEntryColumn::Description => "Description",
+            ClockEntryColumn::Project => "Project",
+            ClockEntryColumn::Tags => "Tags",
             ClockEntryColumn::Duration => "Duration",
             ClockEntryColumn::IsClocked => "Clocked",
+            ClockEntryColumn::Billable => "Billable",
         }
     }
 }
 
-impl TableViewItem<ClockEntryColumn> for ClockEntry {
+/// A row of the table: an entry, or the time between two entries of a day while the table is sorted by time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Row {
+    Entry {
+        entry: ClockEntry,
+        /// Overlaps another entry of the same day
+        overlaps: bool,
+        /// Marked for a bulk action
+        marked: bool,
+    },
+    Gap(Gap),
+}
+
+impl Row {
+    pub fn entry(&self) -> Option<&ClockEntry> {
+        match self {
+            Row::Entry { entry, .. } => Some(entry),
+            Row::Gap(_) => None,
+        }
+    }
+
+    pub fn entry_mut(&mut self) -> Option<&mut ClockEntry> {
+        match self {
+            Row::Entry { entry, .. } => Some(entry),
+            Row::Gap(_) => None,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.times().0
+    }
+
+    fn times(&self) -> (NaiveDate, NaiveTime, NaiveTime) {
+        match self {
+            Row::Entry { entry, .. } => (entry.date, entry.from, entry.to),
+            Row::Gap(gap) => (gap.date, gap.from, gap.to),
+        }
+    }
+}
+
+impl From<ClockEntry> for Row {
+    fn from(entry: ClockEntry) -> Self {
+        Row::Entry { entry, overlaps: false, marked: false }
+    }
+}
+
+impl TableViewItem<ClockEntryColumn> for Row {
     fn to_column(&self, column: ClockEntryColumn) -> String {
+        let (entry, overlaps, marked) = match self {
+            Row::Entry { entry, overlaps, marked } => (entry, *overlaps, *marked),
+            Row::Gap(gap) => {
+                let granularity = gap.previous.granularity;
+                return match column {
+                    ClockEntryColumn::Date => gap.date.to_string(),
+                    ClockEntryColumn::From => format::format_naive_time(granularity, gap.from),
+                    ClockEntryColumn::To => format::format_naive_time(granularity, gap.to),
+                    ClockEntryColumn::Description => "· · · gap · · ·".to_string(),
+                    ClockEntryColumn::Duration => format::format_hms(granularity, gap.to.signed_duration_since(gap.from).num_seconds()),
+                    _ => String::new(),
+                };
+            },
+        };
         match column {
-            ClockEntryColumn::From => format::format_naive_time(self.granularity, self.from),
-            ClockEntryColumn::To => format::format_naive_time(self.granularity, self.to),
-            ClockEntryColumn::Description => self.description.to_string(),
-            ClockEntryColumn::Duration => format::format_hms(self.granularity, self.duration().num_seconds()),
-            ClockEntryColumn::IsClocked => if self.is_clocked { "[x]".to_string() } else { "[ ]".to_string() },
+            ClockEntryColumn::Date if marked => format!("● {}", entry.date),
+            ClockEntryColumn::Date => entry.date.to_string(),
+            ClockEntryColumn::From => format::format_naive_time(entry.granularity, entry.from),
+            ClockEntryColumn::To => format::format_naive_time(entry.granularity, entry.to),
+            ClockEntryColumn::Description if overlaps => format!("⚠ {}", entry.description),
+            ClockEntryColumn::Description => entry.description.to_string(),
+            ClockEntryColumn::Project => entry.project.clone().unwrap_or_default(),
+            ClockEntryColumn::Tags => tags::format_tags(&entry.tags),
+            ClockEntryColumn::Duration if entry.excluded_from_totals => format!("({})", format::format_hms(entry.granularity, entry.duration().num_seconds())),
+            ClockEntryColumn::Duration => format::format_hms(entry.granularity, entry.duration().num_seconds()),
+            ClockEntryColumn::IsClocked => if entry.is_clocked { "[x]".to_string() } else { "[ ]".to_string() },
+            ClockEntryColumn::Billable => match (entry.billable, &entry.invoice) {
+                (_, Some(_)) => "[i]".to_string(),
+                (true, None) => "[$]".to_string(),
+                (false, None) => "[ ]".to_string(),
+            },
         }
     }
 
     fn cmp(&self, other: &Self, column: ClockEntryColumn) -> Ordering where Self: Sized {
+        let (a, b) = match (self, other) {
+            (Row::Entry { entry: a, .. }, Row::Entry { entry: b, .. }) => (a, b),
+            _ => return self.times().cmp(&other.times()),
+        };
         match column {
-            ClockEntryColumn::From => self.from.cmp(&other.from),
-            ClockEntryColumn::To => self.to.cmp(&other.to),
-            ClockEntryColumn::Description => self.description.cmp(&other.description),
-            ClockEntryColumn::Duration => self.duration().cmp(&other.duration()),
-            ClockEntryColumn::IsClocked => self.is_clocked.cmp(&other.is_clocked),
+            ClockEntryColumn::Date | ClockEntryColumn::From => (a.date, a.from).cmp(&(b.date, b.from)),
+            ClockEntryColumn::To => (a.date, a.to).cmp(&(b.date, b.to)),
+            ClockEntryColumn::Description => a.description.cmp(&b.description),
+            ClockEntryColumn::Project => a.project.cmp(&b.project),
+            ClockEntryColumn::Tags => a.tags.cmp(&b.tags),
+            ClockEntryColumn::Duration => a.duration().cmp(&b.duration()),
+            ClockEntryColumn::IsClocked => a.is_clocked.cmp(&b.is_clocked),
+            ClockEntryColumn::Billable => a.billable.cmp(&b.billable),
         }
     }
 }
 
-pub fn new(model: ClockKing) -> ResizedView<NamedView<TableView<ClockEntry, ClockEntryColumn>>> {
-    let mut table: TableView<ClockEntry, ClockEntryColumn> = TableView::<ClockEntry, ClockEntryColumn>::new()
-        .column(ClockEntryColumn::From, ClockEntryColumn::From.as_str(), |c| {c.width_percent(10).align(HAlign::Center) })
-        .column(ClockEntryColumn::To, ClockEntryColumn::To.as_str(), |c| {c.width_percent(10).align(HAlign::Center)})
+pub fn new(model: ClockKing) -> ResizedView<NamedView<TableView<Row, ClockEntryColumn>>> {
+    let mut table: TableView<Row, ClockEntryColumn> = TableView::<Row, ClockEntryColumn>::new()
+        .column(ClockEntryColumn::Date, ClockEntryColumn::Date.as_str(), |c| {c.width_percent(11).align(HAlign::Center) })
+        .column(ClockEntryColumn::From, ClockEntryColumn::From.as_str(), |c| {c.width_percent(8).align(HAlign::Center) })
+        .column(ClockEntryColumn::To, ClockEntryColumn::To.as_str(), |c| {c.width_percent(8).align(HAlign::Center)})
         .column(ClockEntryColumn::Description, ClockEntryColumn::Description.as_str(), |c| {c.align(HAlign::Center)})
-        .column(ClockEntryColumn::Duration, ClockEntryColumn::Duration.as_str(), |c| {c.width_percent(12).align(HAlign::Center)})
-        .column(ClockEntryColumn::IsClocked, ClockEntryColumn::IsClocked.as_str(), |c| {c.width_percent(12).align(HAlign::Center)})
-        .items(model.clock_entries)
+        .column(ClockEntryColumn::Project, ClockEntryColumn::Project.as_str(), |c| {c.width_percent(12).align(HAlign::Center)})
+        .column(ClockEntryColumn::Tags, ClockEntryColumn::Tags.as_str(), |c| {c.width_percent(12).align(HAlign::Center)})
+        .column(ClockEntryColumn::Duration, ClockEntryColumn::Duration.as_str(), |c| {c.width_percent(10).align(HAlign::Center)})
+        .column(ClockEntryColumn::IsClocked, ClockEntryColumn::IsClocked.as_str(), |c| {c.width_percent(8).align(HAlign::Center)})
+        .column(ClockEntryColumn::Billable, ClockEntryColumn::Billable.as_str(), |c| {c.width_percent(8).align(HAlign::Center)})
+        .default_column(ClockEntryColumn::Date)
+        .items(model.clock_entries.into_iter().map(Row::from).collect())
         ;
 
 
     table.set_on_submit(move |s: &mut Cursive, _: usize, index: usize| {
         edit_entry(s,  index);
     });
+    // Gap rows only make sense between entries in order
+    table.set_on_sort(|s: &mut Cursive, _, _| timeline::update_table(s));
 
     table
         .with_name(CLOCK_ENTRIES_TABLE)
@@ -76,52 +173,73 @@ pub fn new(model: ClockKing) -> ResizedView<NamedView<TableView<ClockEntry, Cloc
 }
 
 fn edit_entry(s: &mut Cursive, index: usize) {
-    let granularity = granularity_picker::get_granularity(s);
-    let form = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        let current_entry = t.borrow_item(index).cloned();
-        clock_entry_form::new(
-            "Edit Clock Entry ⏰",
-            current_entry.as_ref(),
-            granularity,
-            move |s: &mut Cursive| submit_clock_entry(s, Some(index)))
+    let current_entry = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.borrow_item(index).cloned()
     }).unwrap();
+    match current_entry {
+        Some(Row::Entry { entry, .. }) => open_entry(s, entry),
+        Some(Row::Gap(_)) => timeline::fill_gap(s),
+        None => {},
+    }
+}
+
+pub fn open_entry(s: &mut Cursive, entry: ClockEntry) {
+    let form = clock_entry_form::new(
+        s,
+        "Edit Clock Entry ⏰",
+        Some(&entry),
+        Scope::Entry,
+        submit_clock_entry
+    );
     s.add_layer(form);
 }
 
 pub fn add_new_entry(s: &mut Cursive) {
     let granularity = granularity_picker::get_granularity(s);
-    let template_entry: Option<ClockEntry> = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        t.item().and_then(|it| t.borrow_item(it).map(|it| ClockEntry {
+    let template_entry: Option<ClockEntry> = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<Row, ClockEntryColumn>| {
+        selected_entry(t).and_then(|it| t.borrow_item(it).and_then(Row::entry).map(|it| ClockEntry {
+            date: it.date,
             from: it.to,
             to: it.to.add(Duration::minutes(60)),
-            description: String::from(""),
-            is_clocked: false,
             granularity,
+            project: it.project.clone(),
+            billable: it.billable,
+            ..ClockEntry::default()
         }))
     }).unwrap();
 
-    s.add_layer(
-        clock_entry_form::new(
-            "Add Clock Entry ⏰",
-            template_entry.as_ref(),
-            granularity,
-            move |s: &mut Cursive| submit_clock_entry(s, None)
-        )
+    match template_entry {
+        Some(template) => open_new_entry(s, template),
+        None => {
+            let form = clock_entry_form::new(s, "Add Clock Entry ⏰", None, Scope::Entry, submit_clock_entry);
+            s.add_layer(form);
+        },
+    }
+}
+
+/// Opens the form for a new entry, filled in from the template.
+pub fn open_new_entry(s: &mut Cursive, template: ClockEntry) {
+    let form = clock_entry_form::new(
+        s,
+        "Add Clock Entry ⏰",
+        Some(&template),
+        Scope::Entry,
+        submit_clock_entry
     );
+    s.add_layer(form);
 }
 
-fn submit_clock_entry(s: &mut Cursive, index: Option<usize>) {
-    let new_entry = ClockEntry {
-        from: time_picker::time_picker_value(s, ClockEntryColumn::From),
-        to: time_picker::time_picker_value(s, ClockEntryColumn::To),
-        description: input::text_area_value(s, ClockEntryColumn::Description),
-        is_clocked: input::checkbox_value(s, ClockEntryColumn::IsClocked),
-        granularity: granularity_picker::get_granularity(s)
-    };
-    s.call_on_name(CLOCK_ENTRIES_TABLE,   |table: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        index.map(|i| table.remove_item(i));
-        table.insert_item(new_entry);
+/// Replaces the entry with the same ID as the edited one, or adds it when it's new.
+fn submit_clock_entry(s: &mut Cursive, new_entry: ClockEntry) {
+    let command = s.call_on_name(CLOCK_ENTRIES_TABLE,   |table: &mut TableView<Row, ClockEntryColumn>| {
+        let before = find_index(table, new_entry.id).and_then(|index| remove_row(table, index));
+        table.insert_item(Row::from(new_entry.clone()));
+        match before {
+            Some(before) => Command::Edit { before, after: new_entry },
+            None => Command::Add(new_entry),
+        }
     }).expect("Unable to get clock entries table");
+    app_context::fetch(s).record(command);
     stats_view::update_stats(s);
     s.pop_layer();
 }
@@ -133,36 +251,159 @@ pub fn delete_current_entry(s: &mut Cursive) {
             "Are you sure?",
             |s| {
                 s.pop_layer();
-                let deleted = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-                    t.item().and_then(|index| t.remove_item(index))
+                let deleted = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<Row, ClockEntryColumn>| {
+                    selected_entry(t).and_then(|index| remove_row(t, index))
                 }).unwrap();
-                app_context::fetch(s).delete(deleted);
+                if let Some(deleted) = deleted {
+                    app_context::fetch(s).record(Command::Delete(deleted));
+                }
                 stats_view::update_stats(s)
             }
         ));
 }
 
-pub fn undo_delete(s: &mut Cursive) {
-    if let Some(deleted) = app_context::fetch(s).undo() { 
-            s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-                t.insert_item(deleted);
-            }); 
+pub fn mark_current_entry_as_clocked(s: &mut Cursive) {
+    let toggled = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<Row, ClockEntryColumn>| {
+        selected_entry(t).map(|index| {
+            let item = t.borrow_item_mut(index).and_then(Row::entry_mut).expect("No entry at current index");
+            let before = item.clone();
+            item.set_clocked(!item.is_clocked);
+            before
+        })
+    }).unwrap();
+    if let Some(before) = toggled {
+        app_context::fetch(s).record(Command::ToggleClocked(before));
     }
     stats_view::update_stats(s);
 }
 
-pub fn mark_current_entry_as_clocked(s: &mut Cursive) {
-    s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        if let Some(index) = t.item() {
-            let mut item = t.borrow_item_mut(index).expect("No entry at current index");
-            item.is_clocked = !item.is_clocked;
-        }
+pub fn mark_current_entry_as_billable(s: &mut Cursive) {
+    let edited = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<Row, ClockEntryColumn>| {
+        selected_entry(t).map(|index| {
+            let item = t.borrow_item_mut(index).and_then(Row::entry_mut).expect("No entry at current index");
+            let before = item.clone();
+            item.billable = !item.billable;
+            item.touch();
+            (before, item.clone())
+        })
     }).unwrap();
+    if let Some((before, after)) = edited {
+        app_context::fetch(s).record(Command::Edit { before, after });
+    }
     stats_view::update_stats(s);
 }
 
+/// Adds the entry to the table, or to the entries hidden by the filter when it doesn't match.
+pub fn insert_entry(s: &mut Cursive, entry: ClockEntry) {
+    if !app_context::fetch(s).filter().shows(&entry) {
+        app_context::fetch(s).hide(entry);
+        return;
+    }
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.insert_item(Row::from(entry));
+    }).expect("Unable to get clock entries table");
+}
+
+/// Removes the entry from the table, or from the entries hidden by the filter.
+pub fn remove_entry(s: &mut Cursive, id: Uuid) -> Result<ClockEntry, String> {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        find_index(t, id).and_then(|index| remove_row(t, index))
+    }).expect("Unable to get clock entries table")
+        .or_else(|| app_context::fetch(s).remove_hidden(id))
+        .ok_or_else(|| format!("no entry with ID {}", id))
+}
+
+/// Replaces the entry with the same ID, in the table or among the entries hidden by the filter, whichever the filter
+/// puts the changed entry in.
+pub fn replace_entry(s: &mut Cursive, entry: ClockEntry) -> Result<(), String> {
+    remove_entry(s, entry.id)?;
+    insert_entry(s, entry);
+    Ok(())
+}
+
+pub fn toggle_clocked(s: &mut Cursive, id: Uuid) -> Result<(), String> {
+    let mut entry = remove_entry(s, id)?;
+    entry.set_clocked(!entry.is_clocked);
+    insert_entry(s, entry);
+    Ok(())
+}
+
+/// Selects the row of the entry, returns the entry unless there's none with the ID.
+pub fn select_entry(s: &mut Cursive, id: Uuid) -> Option<ClockEntry> {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        find_index(t, id).map(|index| {
+            t.set_selected_item(index);
+            t.borrow_item(index).and_then(Row::entry).cloned().expect("The entry should exist")
+        })
+    }).expect("Unable to get clock entries table")
+}
+
+/// Selects the entry and opens it in the edit form, returns false when there's no entry with the ID.
+pub fn open_entry_by_id(s: &mut Cursive, id: Uuid) -> bool {
+    match select_entry(s, id) {
+        Some(entry) => {
+            open_entry(s, entry);
+            true
+        },
+        None => false,
+    }
+}
+
+/// The index of the selected row, unless it's a gap.
+pub fn selected_entry(table: &mut TableView<Row, ClockEntryColumn>) -> Option<usize> {
+    table.item().filter(|index| table.borrow_item(*index).and_then(Row::entry).is_some())
+}
+
+/// Removes the row of an entry, returns the entry.
+fn remove_row(table: &mut TableView<Row, ClockEntryColumn>, index: usize) -> Option<ClockEntry> {
+    match table.remove_item(index) {
+        Some(Row::Entry { entry, .. }) => Some(entry),
+        _ => None,
+    }
+}
+
+/// Edits all the entries in the table, leaving the gap rows alone.
+pub fn edit_visible_entries(s: &mut Cursive, edit: impl FnMut(&mut ClockEntry)) {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.borrow_items_mut().iter_mut().filter_map(Row::entry_mut).for_each(edit);
+    }).expect("Unable to get clock entries table");
+}
+
+/// Shows which entries are marked for a bulk action.
+pub fn update_marks(s: &mut Cursive) {
+    let marks = app_context::fetch(s).marks();
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        for row in t.borrow_items_mut() {
+            if let Row::Entry { entry, marked, .. } = row {
+                *marked = marks.contains(&entry.id);
+            }
+        }
+    }).expect("Unable to get clock entries table");
+}
+
+/// Shows the entries in the table instead of the current ones, without any gap rows until the next update.
+pub fn set_visible_entries(s: &mut Cursive, entries: Vec<ClockEntry>) {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.set_items(entries.into_iter().map(Row::from).collect());
+    }).expect("Unable to get clock entries table");
+}
+
+pub fn find_index(table: &mut TableView<Row, ClockEntryColumn>, id: Uuid) -> Option<usize> {
+    table.borrow_items().iter().position(|it| it.entry().is_some_and(|it| it.id == id))
+}
+
+/// All the entries, including the ones hidden by the active filter.
 pub fn get_clock_entries(s: &mut Cursive) -> Vec<ClockEntry> {
-    s.call_on_name(CLOCK_ENTRIES_TABLE,   |table: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        table.borrow_items().to_vec()
+    let mut entries = get_visible_clock_entries(s);
+    entries.extend(app_context::fetch(s).hidden_entries());
+    entries
+}
+
+pub fn get_visible_clock_entries(s: &mut Cursive) -> Vec<ClockEntry> {
+    s.call_on_name(CLOCK_ENTRIES_TABLE,   |table: &mut TableView<Row, ClockEntryColumn>| {
+        table.borrow_items().iter()
+            .filter_map(Row::entry)
+            .cloned()
+            .collect()
     }).expect("Clock entries table not defined")
 }
\ No newline at end of file
@@ -1,41 +1,170 @@
 use cursive::Cursive;
+use cursive::theme::{BaseColor, Color};
 use cursive::traits::Nameable;
-use cursive::views::{Dialog, ListView, NamedView};
+use cursive::utils::markup::StyledString;
+use cursive::views::{Dialog, LinearLayout, ListView, NamedView, TextView};
 
-use crate::{ClockEntry, Granularity, input, time_picker};
+use crate::{app_context, clock_entries_table, ClockEntry, config, granularity_picker, input, project_picker, tags, templates, time_picker, validation};
 use crate::clock_entries_table::ClockEntryColumn;
+use crate::config::ValidationConfig;
+use crate::validation::{Issue, Scope};
 
 const CLOCK_ENTRY_FORM: &str = "edit_clock_entry";
+const VALIDATION_ISSUES: &str = "validation_issues";
 
+/// The entry form, `on_submit` gets the entry once it passes the validation of the scope.
 pub fn new<F>(
+    s: &mut Cursive,
     prompt: &str,
     entry: Option<&ClockEntry>,
-    granularity: Granularity,
+    scope: Scope,
     on_submit: F,
 ) -> NamedView<Dialog>
 where
-    F: 'static + Fn(&mut Cursive) {
+    F: 'static + Fn(&mut Cursive, ClockEntry) {
+    let granularity = granularity_picker::get_granularity(s);
+    let projects = app_context::fetch(s).projects();
+    let entries = clock_entries_table::get_clock_entries(s);
+    let known_tags = tags::known_tags(&entries);
+    let ranked_descriptions = templates::ranked_descriptions(&entries, time_picker::today());
+    let mut fields = ListView::new();
+    if let Some(entry) = entry {
+        fields.add_child("ID", TextView::new(entry.id.to_string()));
+    }
+    let edited = entry.cloned().unwrap_or_default();
+    let time_input = config::load().map(|it| it.time_input).unwrap_or_default();
     Dialog::new()
         .title(prompt)
         .button("Cancel", |s| { s.pop_layer(); })
-        .content(
-            ListView::new()
+        .content(LinearLayout::vertical().child(
+            fields
+                .child(
+                    ClockEntryColumn::Date.as_str(),
+                    input::date_input(ClockEntryColumn::Date, entry.map(|it| it.date))
+                )
                 .child(
                     ClockEntryColumn::From.as_str(),
-                    time_picker::time_picker_input(ClockEntryColumn::From, entry.map(|it| it.from), granularity)
+                    time_picker::time_input(ClockEntryColumn::From, entry.map(|it| it.from), granularity, time_input)
                 )
                 .child(
                     ClockEntryColumn::To.as_str(),
-                    time_picker::time_picker_input(ClockEntryColumn::To, entry.map(|it|it.to), granularity)
+                    time_picker::time_input(ClockEntryColumn::To, entry.map(|it|it.to), granularity, time_input)
                 )
                 .child(
                     ClockEntryColumn::Description.as_str(),
-                    input::text_area_input(ClockEntryColumn::Description, entry.map(|it| it.description.clone()))
+                    input::description_input(ClockEntryColumn::Description, entry.map(|it| it.description.clone()), ranked_descriptions)
+                )
+                .child(
+                    ClockEntryColumn::Project.as_str(),
+                    project_picker::project_picker_input(entry.and_then(|it| it.project.clone()), &projects)
+                )
+                .child(
+                    ClockEntryColumn::Tags.as_str(),
+                    input::tags_input(ClockEntryColumn::Tags, entry.map(|it| it.tags.as_slice()).unwrap_or_default(), known_tags)
                 )
                 .child(
                     ClockEntryColumn::IsClocked.as_str(),
                     input::checkbox_input(ClockEntryColumn::IsClocked, entry.map(|it| it.is_clocked))
                 )
-        )
-        .button("Ok",on_submit).with_name(CLOCK_ENTRY_FORM)
-}
\ No newline at end of file
+                .child(
+                    ClockEntryColumn::Billable.as_str(),
+                    input::checkbox_input(ClockEntryColumn::Billable, entry.map(|it| it.billable))
+                )
+        ).child(TextView::new("").with_name(VALIDATION_ISSUES)))
+        .button("Ok", move |s| {
+            if let Some(entry) = validated_entry(s, &edited, scope) {
+                on_submit(s, entry);
+            }
+        }).with_name(CLOCK_ENTRY_FORM)
+}
+
+/// The entry in the form, or none while it has issues to show. Errors keep the form open, warnings only the first
+/// time they're shown.
+fn validated_entry(s: &mut Cursive, edited: &ClockEntry, scope: Scope) -> Option<ClockEntry> {
+    let mut issues = Vec::new();
+    for col in [ClockEntryColumn::From, ClockEntryColumn::To] {
+        if !time_picker::is_valid_time(s, col) {
+            issues.push(Issue { severity: validation::Severity::Error, message: format!("Invalid time in {}", col.as_str()) });
+        }
+    }
+    let rules = validation_rules(s)?;
+    let entries = clock_entries_table::get_clock_entries(s);
+    let entry = entry_value(s, edited);
+    match &entry {
+        Ok(entry) => issues.extend(validation::validate(entry, &entries, &rules, scope)),
+        Err(e) => issues.push(Issue { severity: validation::Severity::Error, message: e.clone() }),
+    }
+
+    if show_issues(s, &issues, VALIDATION_ISSUES) { entry.ok() } else { None }
+}
+
+/// Whether to go ahead despite the issues, otherwise they're shown in the named text view. Errors always stop, warnings
+/// only the first time they're shown.
+pub fn show_issues(s: &mut Cursive, issues: &[Issue], name: &str) -> bool {
+    let rendered = validation::render(issues);
+    let already_shown = s.call_on_name(name, |t: &mut TextView| t.get_content().source() == rendered).unwrap_or_default();
+    if issues.is_empty() || (already_shown && !validation::has_errors(issues)) {
+        return true;
+    }
+    let color = if validation::has_errors(issues) { BaseColor::Red } else { BaseColor::Yellow };
+    s.call_on_name(name, |t: &mut TextView| t.set_content(StyledString::styled(rendered, Color::Dark(color))));
+    false
+}
+
+/// The validation rules of the config, or none after showing why the config can't be read. Entries aren't saved
+/// unchecked because of a typo in the config.
+pub fn validation_rules(s: &mut Cursive) -> Option<ValidationConfig> {
+    match config::load() {
+        Ok(config) => Some(config.validation),
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Unable to read the validation rules from ./.clockking/config.json: {}", e)));
+            None
+        },
+    }
+}
+
+/// Reads the edited entry from the form fields, keeping its ID and the fields not in the form. Tags written inline
+/// as `#tag` in the description are added to the tags.
+fn entry_value(s: &mut Cursive, edited: &ClockEntry) -> Result<ClockEntry, String> {
+    let description = input::text_area_value(s, ClockEntryColumn::Description);
+    let mut entry = ClockEntry {
+        date: input::date_value(s, ClockEntryColumn::Date)?,
+        from: time_picker::time_picker_value(s, ClockEntryColumn::From),
+        to: time_picker::time_picker_value(s, ClockEntryColumn::To),
+        tags: tags::merge_tags(input::tags_value(s, ClockEntryColumn::Tags), tags::parse_inline_tags(&description)),
+        description,
+        is_clocked: input::checkbox_value(s, ClockEntryColumn::IsClocked),
+        granularity: granularity_picker::get_granularity(s),
+        project: project_picker::project_picker_value(s),
+        billable: input::checkbox_value(s, ClockEntryColumn::Billable),
+        ..edited.clone()
+    };
+    entry.touch();
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod clock_entry_form_test {
+    use cursive::Cursive;
+    use cursive::traits::Nameable;
+    use cursive::views::TextView;
+
+    use crate::clock_entry_form::show_issues;
+    use crate::validation::{Issue, Severity};
+
+    fn issue(severity: Severity) -> Issue {
+        Issue { severity, message: "Overlaps 'Coding'".to_string() }
+    }
+
+    #[test]
+    fn shows_warnings_once_and_errors_always() {
+        let mut s = Cursive::new();
+        s.add_layer(TextView::new("").with_name("issues"));
+
+        assert!(show_issues(&mut s, &[], "issues"));
+        assert!(!show_issues(&mut s, &[issue(Severity::Warning)], "issues"));
+        assert!(show_issues(&mut s, &[issue(Severity::Warning)], "issues"));
+        assert!(!show_issues(&mut s, &[issue(Severity::Error)], "issues"));
+        assert!(!show_issues(&mut s, &[issue(Severity::Error)], "issues"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const CONFIG_LOCATION: &str = "./.clockking/config.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    #[serde(default)]
+    pub jira: Option<JiraConfig>,
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+    #[serde(default)]
+    pub invoice: Option<InvoiceConfig>,
+    #[serde(default)]
+    pub validation: ValidationConfig,
+    #[serde(default)]
+    pub time_input: TimeInput,
+}
+
+/// How times are entered in the entry form.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeInput {
+    /// Typed, like `9:30` or `+15m`
+    #[default]
+    Typed,
+    /// Picked from a list of all the times of the granularity
+    Picker,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JiraConfig {
+    pub base_url: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub api_token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body_template: String,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceConfig {
+    /// Name and address of the one sending the invoice, one line per line
+    pub sender: String,
+    /// Name and address per client, the client name is used for the clients not listed here
+    #[serde(default)]
+    pub recipients: BTreeMap<String, String>,
+    #[serde(default = "default_number_prefix")]
+    pub number_prefix: String,
+    #[serde(default = "default_next_number")]
+    pub next_number: u32,
+    /// Payment terms, bank details and the like, printed at the end of the invoice
+    #[serde(default)]
+    pub footer: Option<String>,
+}
+
+/// Rules checked when an entry is saved, on top of the entry having a duration.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidationConfig {
+    #[serde(default)]
+    pub require_description: bool,
+    #[serde(default)]
+    pub max_duration_hours: Option<f64>,
+    #[serde(default = "default_warn_on_overlap")]
+    pub warn_on_overlap: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            require_description: false,
+            max_duration_hours: None,
+            warn_on_overlap: default_warn_on_overlap(),
+        }
+    }
+}
+
+fn default_warn_on_overlap() -> bool {
+    true
+}
+
+impl InvoiceConfig {
+    pub fn number(&self) -> String {
+        format!("{}{:04}", self.number_prefix, self.next_number)
+    }
+
+    pub fn recipient(&self, client: &str) -> String {
+        self.recipients.get(client).cloned().unwrap_or_else(|| client.to_string())
+    }
+}
+
+fn default_number_prefix() -> String {
+    "INV-".to_string()
+}
+
+fn default_next_number() -> u32 {
+    1
+}
+
+fn default_method() -> String {
+    "POST".to_string()
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+pub fn exists() -> bool {
+    Path::new(CONFIG_LOCATION).exists()
+}
+
+pub fn load() -> Result<Config, Box<dyn Error>> {
+    match File::open(CONFIG_LOCATION) {
+        Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+pub fn store(config: &Config) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(CONFIG_LOCATION)?);
+    serde_json::to_writer_pretty(writer, config)?;
+    Ok(())
+}
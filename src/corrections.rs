@@ -0,0 +1,299 @@
+use chrono::NaiveTime;
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView};
+use cursive_table_view::TableView;
+
+use crate::{app_context, bulk, CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, format, granularity_picker, Granularity, stats_view, tags, time_picker};
+use crate::clock_entries_table::{ClockEntryColumn, Row};
+use crate::history::Command;
+
+const SPLIT_AT: &str = "split_at";
+
+/// The part of the entry before `at`, keeping its ID, and a new entry for the rest.
+pub fn split(entry: &ClockEntry, at: NaiveTime) -> Result<(ClockEntry, ClockEntry), String> {
+    if at <= entry.from || at >= entry.to {
+        return Err(format!(
+            "Split at a time between {} and {}",
+            format::format_naive_time(entry.granularity, entry.from),
+            format::format_naive_time(entry.granularity, entry.to)
+        ));
+    }
+    let mut first = ClockEntry { to: at, ..entry.clone() };
+    first.touch();
+    let second = ClockEntry { from: at, is_clocked: entry.is_clocked, invoice: entry.invoice.clone(), ..entry.copy() };
+    Ok((first, second))
+}
+
+/// One entry spanning the given ones, which have to be of the same day, with the same description, project, billing and
+/// no gaps between them. It keeps the ID of the earliest one.
+pub fn merge(entries: &[ClockEntry]) -> Result<ClockEntry, String> {
+    let mut entries = entries.to_vec();
+    entries.sort_by_key(|it| (it.date, it.from));
+    let (first, rest) = match entries.split_first() {
+        Some((first, rest)) if !rest.is_empty() => (first, rest),
+        _ => return Err("Select two adjacent entries, or mark the entries to merge".to_string()),
+    };
+    if rest.iter().any(|it| it.date != first.date) {
+        return Err("Only entries of the same day can be merged".to_string());
+    }
+    if rest.iter().any(|it| it.description.trim() != first.description.trim()) {
+        return Err("Only entries with the same description can be merged".to_string());
+    }
+    if rest.iter().any(|it| it.project != first.project) {
+        return Err("Only entries of the same project can be merged".to_string());
+    }
+    if rest.iter().any(|it| it.invoice != first.invoice) {
+        return Err("Only entries of the same invoice can be merged".to_string());
+    }
+    if rest.iter().any(|it| it.billable != first.billable) {
+        return Err("Only entries that are all billable, or all not, can be merged".to_string());
+    }
+    if rest.iter().any(|it| it.excluded_from_totals != first.excluded_from_totals) {
+        return Err("Only entries that all count toward the totals, or all don't, can be merged".to_string());
+    }
+    let mut merged = first.clone();
+    for entry in rest {
+        if entry.from > merged.to {
+            return Err(format!(
+                "There's a gap between {} and {}",
+                format::format_naive_time(merged.granularity, merged.to),
+                format::format_naive_time(entry.granularity, entry.from)
+            ));
+        }
+        merged.to = merged.to.max(entry.to);
+        merged.tags = tags::merge_tags(merged.tags, entry.tags.clone());
+        merged.is_clocked &= entry.is_clocked;
+    }
+    merged.touch();
+    Ok(merged)
+}
+
+/// A copy of the entry right after the last entry of its day, or nothing when it doesn't fit in the day anymore.
+pub fn duplicate(entry: &ClockEntry, entries: &[ClockEntry]) -> Option<ClockEntry> {
+    let from = entries.iter()
+        .filter(|it| it.date == entry.date)
+        .map(|it| it.to)
+        .max()
+        .unwrap_or(entry.to)
+        .max(entry.to);
+    let (to, overflow) = from.overflowing_add_signed(entry.duration());
+    if overflow != 0 {
+        return None;
+    }
+    Some(ClockEntry { from, to, ..entry.copy() })
+}
+
+fn selected_entry(s: &mut Cursive) -> Option<ClockEntry> {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        clock_entries_table::selected_entry(t).and_then(|index| t.borrow_item(index)).and_then(Row::entry).cloned()
+    }).expect("Unable to get clock entries table")
+}
+
+pub fn split_entry(s: &mut Cursive) {
+    let entry = match selected_entry(s) {
+        Some(entry) => entry,
+        None => {
+            s.add_layer(Dialog::info("Select an entry to split it."));
+            return;
+        },
+    };
+    let granularity = granularity_picker::get_granularity(s);
+    let middle = granularity_picker::normalize(entry.from + entry.duration() / 2, granularity);
+    let title = format!("Split '{}' at", entry.description);
+    let entry_for_submit = entry.clone();
+    s.add_layer(
+        Dialog::around(
+            EditView::new()
+                .content(format::format_naive_time(granularity, middle))
+                .on_submit(move |s, value| submit_split(s, &entry_for_submit, value, granularity))
+                .with_name(SPLIT_AT)
+                .min_width(20)
+        )
+            .title(title)
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", move |s| {
+                let value = s.call_on_name(SPLIT_AT, |e: &mut EditView| e.get_content().to_string()).expect("Split time should be defined");
+                submit_split(s, &entry, &value, granularity);
+            })
+    );
+}
+
+/// Splits at a typed time, snapped to the granularity. Offsets like `+45m` are from the start of the entry.
+fn submit_split(s: &mut Cursive, entry: &ClockEntry, value: &str, granularity: Granularity) {
+    let result = time_picker::parse_time_input(value, granularity, entry.from, time_picker::now_naive_time(granularity))
+        .ok_or(format!("Invalid time: {}", value))
+        .and_then(|at| split(entry, at));
+    match result {
+        Ok((first, second)) => {
+            s.pop_layer();
+            clock_entries_table::replace_entry(s, first.clone()).expect("The split entry should be in the table");
+            clock_entries_table::insert_entry(s, second.clone());
+            clock_entries_table::select_entry(s, second.id);
+            app_context::fetch(s).record(Command::Batch {
+                label: format!("Split '{}'", entry.description),
+                commands: vec![Command::Edit { before: entry.clone(), after: first }, Command::Add(second)],
+            });
+            stats_view::update_stats(s);
+        },
+        Err(e) => s.add_layer(Dialog::info(e)),
+    }
+}
+
+/// Merges the marked entries, or the selected entry and the one after it when fewer are marked.
+pub fn merge_entries(s: &mut Cursive) {
+    let mut entries = bulk::marked_entries(s);
+    if entries.len() < 2 {
+        let visible = clock_entries_table::get_visible_clock_entries(s);
+        entries = selected_entry(s).into_iter()
+            .flat_map(|selected| {
+                let next = visible.iter()
+                    .filter(|it| it.date == selected.date && it.id != selected.id && it.from >= selected.from)
+                    .min_by_key(|it| (it.from, it.to))
+                    .cloned();
+                std::iter::once(selected).chain(next)
+            })
+            .collect();
+    }
+    match merge(&entries) {
+        Ok(merged) => {
+            let mut commands = Vec::new();
+            for entry in entries {
+                if entry.id == merged.id {
+                    clock_entries_table::replace_entry(s, merged.clone()).expect("The merged entry should be in the table");
+                    commands.insert(0, Command::Edit { before: entry, after: merged.clone() });
+                } else {
+                    clock_entries_table::remove_entry(s, entry.id).expect("The merged entry should be in the table");
+                    commands.push(Command::Delete(entry));
+                }
+            }
+            clock_entries_table::select_entry(s, merged.id);
+            app_context::fetch(s).record(Command::Batch {
+                label: format!("Merge {} entries '{}'", commands.len(), merged.description),
+                commands,
+            });
+            stats_view::update_stats(s);
+        },
+        Err(e) => s.add_layer(Dialog::info(e)),
+    }
+}
+
+pub fn duplicate_entry(s: &mut Cursive) {
+    let entry = match selected_entry(s) {
+        Some(entry) => entry,
+        None => {
+            s.add_layer(Dialog::info("Select an entry to duplicate it."));
+            return;
+        },
+    };
+    match duplicate(&entry, &clock_entries_table::get_clock_entries(s)) {
+        Some(copy) => {
+            clock_entries_table::insert_entry(s, copy.clone());
+            clock_entries_table::select_entry(s, copy.id);
+            app_context::fetch(s).record(Command::Add(copy));
+            stats_view::update_stats(s);
+        },
+        None => s.add_layer(Dialog::info(format!("'{}' doesn't fit after the last entry of {}.", entry.description, entry.date))),
+    }
+}
+
+#[cfg(test)]
+mod corrections_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::ClockEntry;
+    use crate::corrections::{duplicate, merge, split};
+
+    fn entry(from: (u32, u32), to: (u32, u32), description: &str) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(from.0, from.1, 0),
+            to: NaiveTime::from_hms(to.0, to.1, 0),
+            description: description.to_string(),
+            ..ClockEntry::default()
+        }
+    }
+
+    fn times(entry: &ClockEntry) -> (NaiveTime, NaiveTime) {
+        (entry.from, entry.to)
+    }
+
+    #[test]
+    fn splits_an_entry_in_two() {
+        let coding = ClockEntry { is_clocked: true, tags: vec!["dev".to_string()], ..entry((9, 0), (12, 0), "Coding") };
+
+        let (first, second) = split(&coding, NaiveTime::from_hms(10, 30, 0)).unwrap();
+
+        assert_eq!((first.id, times(&first)), (coding.id, (NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(10, 30, 0))));
+        assert_ne!(second.id, coding.id);
+        assert_eq!(times(&second), (NaiveTime::from_hms(10, 30, 0), NaiveTime::from_hms(12, 0, 0)));
+        assert_eq!((second.description.as_str(), second.is_clocked, second.tags.clone()), ("Coding", true, vec!["dev".to_string()]));
+        assert!(split(&coding, NaiveTime::from_hms(9, 0, 0)).is_err());
+        assert!(split(&coding, NaiveTime::from_hms(13, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn merges_adjacent_entries_with_the_same_description() {
+        let first = ClockEntry { is_clocked: true, ..entry((9, 0), (10, 0), "Coding") };
+        let second = ClockEntry { tags: vec!["dev".to_string()], ..entry((10, 0), (11, 30), "Coding") };
+
+        let merged = merge(&[second.clone(), first.clone()]).unwrap();
+
+        assert_eq!((merged.id, times(&merged)), (first.id, (NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(11, 30, 0))));
+        assert_eq!((merged.is_clocked, merged.tags), (false, vec!["dev".to_string()]));
+        assert!(merge(std::slice::from_ref(&first)).is_err());
+        assert_eq!(merge(&[first.clone(), entry((10, 0), (11, 0), "Review")]).unwrap_err(), "Only entries with the same description can be merged");
+        assert_eq!(merge(&[first, entry((10, 15), (11, 0), "Coding")]).unwrap_err(), "There's a gap between 10:00 and 10:15");
+    }
+
+    #[test]
+    fn merges_only_entries_of_the_same_project() {
+        let first = ClockEntry { project: Some("Website".to_string()), ..entry((9, 0), (10, 0), "Coding") };
+
+        assert_eq!(merge(&[first.clone(), entry((10, 0), (11, 0), "Coding")]).unwrap_err(), "Only entries of the same project can be merged");
+        assert!(merge(&[first.clone(), ClockEntry { project: first.project.clone(), ..entry((10, 0), (11, 0), "Coding") }]).is_ok());
+    }
+
+    #[test]
+    fn merges_only_entries_of_the_same_invoice() {
+        let invoiced = ClockEntry { invoice: Some("2022-0007".to_string()), ..entry((9, 0), (10, 0), "Coding") };
+
+        assert_eq!(merge(&[invoiced.clone(), entry((10, 0), (11, 0), "Coding")]).unwrap_err(), "Only entries of the same invoice can be merged");
+        assert_eq!(merge(&[invoiced.clone(), ClockEntry { invoice: invoiced.invoice.clone(), ..entry((10, 0), (11, 0), "Coding") }]).unwrap().invoice, invoiced.invoice);
+    }
+
+    #[test]
+    fn merges_only_entries_billed_alike() {
+        let billable = ClockEntry { billable: true, ..entry((9, 0), (10, 0), "Coding") };
+
+        assert_eq!(
+            merge(&[billable.clone(), entry((10, 0), (11, 0), "Coding")]).unwrap_err(),
+            "Only entries that are all billable, or all not, can be merged"
+        );
+        assert!(merge(&[billable, ClockEntry { billable: true, ..entry((10, 0), (11, 0), "Coding") }]).unwrap().billable);
+    }
+
+    #[test]
+    fn merges_only_entries_counted_alike() {
+        let excluded = ClockEntry { excluded_from_totals: true, ..entry((9, 0), (10, 0), "Coding") };
+
+        assert_eq!(
+            merge(&[excluded.clone(), entry((10, 0), (11, 0), "Coding")]).unwrap_err(),
+            "Only entries that all count toward the totals, or all don't, can be merged"
+        );
+        assert!(merge(&[excluded, ClockEntry { excluded_from_totals: true, ..entry((10, 0), (11, 0), "Coding") }]).unwrap().excluded_from_totals);
+    }
+
+    #[test]
+    fn duplicates_after_the_last_entry_of_the_day() {
+        let standup = ClockEntry { is_clocked: true, ..entry((9, 0), (9, 15), "Standup") };
+        let entries = vec![standup.clone(), entry((9, 15), (12, 0), "Coding")];
+
+        let copy = duplicate(&standup, &entries).unwrap();
+
+        assert_ne!(copy.id, standup.id);
+        assert_eq!(times(&copy), (NaiveTime::from_hms(12, 0, 0), NaiveTime::from_hms(12, 15, 0)));
+        assert!(!copy.is_clocked);
+        assert_eq!(duplicate(&standup, &[entry((9, 15), (23, 50), "On call")]), None);
+    }
+}
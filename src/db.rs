@@ -1,31 +1,119 @@
-use std::fs::{create_dir_all, File};
-use std::io::{BufReader, BufWriter};
+use std::fs::{self, create_dir_all, File, OpenOptions, TryLockError};
+use std::io::BufWriter;
 
+use chrono::{DateTime, Local, NaiveDate};
 use cursive::Cursive;
 
-use crate::{app_context, clock_entries_table, ClockEntry, ClockKing, GlobalContext, Granularity, granularity_picker};
+use crate::{absences, app_context, clock_entries_table, ClockKing, GlobalContext, granularity_picker, time_picker};
 
 const DB_LOCATION: &str = "./.clockking/db.json";
+const LOCK_LOCATION: &str = "./.clockking/lock";
 
-pub fn init_from_db(s: &mut Cursive) -> ClockKing {
-    create_dir_all("./.clockking").expect("Unable to create the .clockking directory");
-    let file = File::open(DB_LOCATION).or_else(|_| File::create(DB_LOCATION)).expect("Unable to create nor open a .clockking file");
-    let reader = BufReader::new(file);
-    let u: ClockKing = serde_json::from_reader(reader).unwrap_or_else(|_| ClockKing {
-        clock_entries: Vec::<ClockEntry>::default(),
-        granularity: Granularity::Detailed,
-    });
+/// Held by the app, and by the commands that change the database while they run, so neither writes back a model the
+/// other has changed in the meantime. The lock goes away with the process.
+pub struct Lock {
+    _file: File,
+}
+
+pub fn lock() -> Result<Lock, String> {
+    create_dir_all("./.clockking").map_err(|e| format!("Unable to create the .clockking directory: {}", e))?;
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(LOCK_LOCATION)
+        .map_err(|e| format!("Unable to open {}: {}", LOCK_LOCATION, e))?;
+    match file.try_lock() {
+        Ok(()) => Ok(Lock { _file: file }),
+        Err(TryLockError::WouldBlock) => Err("The database is in use, close the running Clock King first".to_string()),
+        Err(TryLockError::Error(e)) => Err(format!("Unable to lock {}: {}", LOCK_LOCATION, e)),
+    }
+}
+
+/// Loads the model for the app, which holds the lock for as long as it runs.
+pub fn init_from_db(s: &mut Cursive) -> Result<(ClockKing, Lock), String> {
+    let lock = lock()?;
+    let (u, without_ids) = read_model()?;
+    if without_ids {
+        store_model(&u);
+    }
     s.set_user_data(GlobalContext::new(&u));
 
-    u
+    Ok((u, lock))
+}
+
+pub fn load_model() -> Result<ClockKing, String> {
+    read_model().map(|(model, _)| model)
+}
+
+/// Loads the model to change it and store it again, holding the lock until it's dropped.
+pub fn load_model_for_update() -> Result<(ClockKing, Lock), String> {
+    let lock = lock()?;
+    Ok((load_model()?, lock))
+}
+
+/// The stored model, and whether it has entries from before IDs were introduced. Those get new IDs on every load until
+/// the model is stored, which the interactive tracker and the commands that change the database do. A database that
+/// can't be read is an error rather than an empty model, which would be stored over it.
+pub fn read_model() -> Result<(ClockKing, bool), String> {
+    create_dir_all("./.clockking").map_err(|e| format!("Unable to create the .clockking directory: {}", e))?;
+    let content = match fs::read_to_string(DB_LOCATION) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Unable to read {}: {}", DB_LOCATION, e)),
+    };
+    if content.trim().is_empty() {
+        return Ok((ClockKing::default(), false));
+    }
+    let saved_on = fs::metadata(DB_LOCATION).and_then(|it| it.modified()).map(|it| DateTime::<Local>::from(it).naive_local().date());
+    let unreadable = |e: serde_json::Error| format!("Unable to read {}, it's left as it is: {}", DB_LOCATION, e);
+    let mut value: serde_json::Value = serde_json::from_str(&content).map_err(unreadable)?;
+    add_missing_dates(&mut value, saved_on.unwrap_or_else(|_| time_picker::today()));
+    let mut model: ClockKing = serde_json::from_value(value.clone()).map_err(unreadable)?;
+    if let Some(targets) = model.targets.as_mut() {
+        absences::migrate_holidays(targets, &mut model.absences);
+    }
+    Ok((model, has_entries_without_id(&value)))
+}
+
+/// Entries from before dates were introduced get the day the database was last saved, the only day those versions
+/// tracked. The date stays the same until the model is stored with it.
+fn add_missing_dates(value: &mut serde_json::Value, date: NaiveDate) {
+    let add_date = |entry: &mut serde_json::Value| if let Some(entry) = entry.as_object_mut() {
+        entry.entry("date").or_insert_with(|| serde_json::Value::String(date.to_string()));
+    };
+    if let Some(entries) = value.get_mut("clock_entries").and_then(|it| it.as_array_mut()) {
+        entries.iter_mut().for_each(add_date);
+    }
+    if let Some(recording) = value.get_mut("recording") {
+        add_date(recording);
+    }
+}
+
+fn has_entries_without_id(value: &serde_json::Value) -> bool {
+    value["clock_entries"].as_array().into_iter().flatten()
+        .chain(value.get("recording").into_iter().filter(|it| !it.is_null()))
+        .any(|it| it.get("id").is_none())
 }
 
 pub fn save_to_db(s: &mut Cursive) {
     let clock_entries = clock_entries_table::get_clock_entries(s);
     let granularity = granularity_picker::get_granularity(s);
+    let recording = app_context::fetch(s).ongoing_recording();
+    let breaks = app_context::fetch(s).recording_breaks();
+    let timers = app_context::fetch(s).timers();
+    let projects = app_context::fetch(s).projects();
+    let billing = app_context::fetch(s).billing();
+    let targets = app_context::fetch(s).targets();
+    let absences = app_context::fetch(s).absences();
+    let templates = app_context::fetch(s).templates();
     let new_model = ClockKing {
         clock_entries,
         granularity,
+        recording,
+        breaks,
+        timers,
+        projects,
+        billing,
+        targets,
+        absences,
+        templates,
     };
     if app_context::fetch(s).model_changed(&new_model) {
         save_model_to_db(s, &new_model);
@@ -34,7 +122,51 @@ pub fn save_to_db(s: &mut Cursive) {
 
 fn save_model_to_db(s: &mut Cursive, clock_king: &ClockKing) {
     app_context::fetch(s).save(clock_king.clone());
+    store_model(clock_king);
+}
+
+pub fn store_model(clock_king: &ClockKing) {
     let file = File::create(DB_LOCATION).expect("Unable to open DB file");
     let writer = BufWriter::new(file);
     serde_json::to_writer_pretty(writer, &clock_king).expect("Saving to DB failed");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod db_test {
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    use crate::ClockKing;
+    use crate::db::{add_missing_dates, has_entries_without_id};
+
+    #[test]
+    fn finds_entries_from_before_ids() {
+        let mut legacy = json!({
+            "clock_entries": [{ "from": "09:00:00", "to": "10:00:00", "description": "Standup", "is_clocked": false, "granularity": "Detailed" }],
+            "granularity": "Detailed",
+            "recording": null
+        });
+        assert!(has_entries_without_id(&legacy));
+        add_missing_dates(&mut legacy, NaiveDate::from_ymd(2022, 8, 1));
+        let model: ClockKing = serde_json::from_value(legacy).unwrap();
+        assert_eq!(model.clock_entries[0].created_at, None);
+        assert!(!has_entries_without_id(&serde_json::to_value(&model).unwrap()));
+    }
+
+    #[test]
+    fn dates_entries_from_before_dates_on_the_day_they_were_saved() {
+        let mut legacy = json!({
+            "clock_entries": [
+                { "from": "09:00:00", "to": "10:00:00", "description": "Standup", "is_clocked": false, "granularity": "Detailed" },
+                { "date": "2022-07-29", "from": "11:00:00", "to": "12:00:00", "description": "Review", "is_clocked": false, "granularity": "Detailed" }
+            ],
+            "granularity": "Detailed",
+            "recording": { "from": "13:00:00", "to": "13:00:00", "description": "Coding", "is_clocked": false, "granularity": "Detailed" }
+        });
+        add_missing_dates(&mut legacy, NaiveDate::from_ymd(2022, 8, 1));
+        let model: ClockKing = serde_json::from_value(legacy).unwrap();
+        assert_eq!(model.clock_entries[0].date, NaiveDate::from_ymd(2022, 8, 1));
+        assert_eq!(model.clock_entries[1].date, NaiveDate::from_ymd(2022, 7, 29));
+        assert_eq!(model.recording.unwrap().date, NaiveDate::from_ymd(2022, 8, 1));
+    }
+}
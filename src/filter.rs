@@ -0,0 +1,310 @@
+use std::collections::BTreeSet;
+
+use cursive::Cursive;
+use cursive::theme::{BaseColor, Color};
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::utils::markup::StyledString;
+use cursive::views::{Checkbox, Dialog, EditView, LinearLayout, ListView, SelectView, TextView};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_context, CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, stats_view, tags, time_picker};
+
+const SEARCH_TEXT: &str = "search_text";
+const SEARCH_REGEX: &str = "search_regex";
+const SEARCH_ERROR: &str = "search_error";
+const FILTER_CLOCKED: &str = "filter_clocked";
+const FILTER_PROJECT: &str = "filter_project";
+const FILTER_TAG: &str = "filter_tag";
+const FILTER_MIN_DURATION: &str = "filter_min_duration";
+const FILTER_MAX_DURATION: &str = "filter_max_duration";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryFilter {
+    pub tag: Option<String>,
+    pub search: Option<Search>,
+    pub project: Option<String>,
+    pub clocked: Option<bool>,
+    pub min_seconds: Option<i64>,
+    pub max_seconds: Option<i64>,
+}
+
+/// A search in the descriptions, ignoring case.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Search {
+    pub text: String,
+    pub regex: bool,
+}
+
+impl Search {
+    pub fn pattern(&self) -> Result<Regex, String> {
+        let pattern = if self.regex { self.text.clone() } else { regex::escape(&self.text) };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| format!("Invalid regex: {}", e))
+    }
+}
+
+impl EntryFilter {
+    /// Splits the entries into the matching ones and the rest, or fails on an invalid search regex.
+    pub fn partition(&self, entries: impl IntoIterator<Item = ClockEntry>) -> Result<(Vec<ClockEntry>, Vec<ClockEntry>), String> {
+        let pattern = self.search.as_ref().map(Search::pattern).transpose()?;
+        Ok(entries.into_iter().partition(|it| self.matches(it, pattern.as_ref())))
+    }
+
+    /// Whether the entry belongs in the table while the filter is active, its search regex is always valid then.
+    pub fn shows(&self, entry: &ClockEntry) -> bool {
+        let pattern = self.search.as_ref().and_then(|it| it.pattern().ok());
+        self.matches(entry, pattern.as_ref())
+    }
+
+    fn matches(&self, entry: &ClockEntry, pattern: Option<&Regex>) -> bool {
+        let seconds = entry.duration().num_seconds();
+        self.tag.as_ref().is_none_or(|tag| entry.tags.contains(tag))
+            && self.project.as_ref().is_none_or(|project| entry.project.as_ref() == Some(project))
+            && self.clocked.is_none_or(|clocked| entry.is_clocked == clocked)
+            && self.min_seconds.is_none_or(|min| seconds >= min)
+            && self.max_seconds.is_none_or(|max| seconds <= max)
+            && pattern.is_none_or(|pattern| pattern.is_match(&entry.description))
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self != EntryFilter::default()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        match &self.search {
+            Some(search) if search.regex => parts.push(format!("/{}/", search.text)),
+            Some(search) => parts.push(format!("\"{}\"", search.text)),
+            None => {},
+        }
+        parts.extend(self.tag.iter().map(|tag| format!("#{}", tag)));
+        parts.extend(self.project.iter().map(|project| format!("@{}", project)));
+        parts.extend(self.clocked.map(|clocked| if clocked { "clocked" } else { "unclocked" }.to_string()));
+        parts.extend(self.min_seconds.map(|it| format!(">= {}", format_duration(it))));
+        parts.extend(self.max_seconds.map(|it| format!("<= {}", format_duration(it))));
+        parts.join(" ")
+    }
+}
+
+/// Formats a duration the way it's typed in the filter, like `1h30m`.
+fn format_duration(seconds: i64) -> String {
+    match (seconds / 3600, seconds % 3600 / 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h{}m", hours, minutes),
+    }
+}
+
+pub fn filter_by_tag(s: &mut Cursive) {
+    let current = app_context::fetch(s).filter();
+    let mut view: SelectView<Option<String>> = SelectView::new();
+    view.add_item("(all entries)", None);
+    tags::known_tags(&clock_entries_table::get_clock_entries(s))
+        .into_iter()
+        .for_each(|tag| view.add_item(format!("#{}", tag), Some(tag)));
+    let selected = view.iter().position(|(_, tag)| *tag == current.tag).unwrap_or(0);
+    view.set_selection(selected);
+    view.set_on_submit(move |s, tag: &Option<String>| {
+        s.pop_layer();
+        let mut filter = current.clone();
+        filter.tag = tag.clone();
+        apply_or_show_error(s, filter);
+    });
+    s.add_layer(Dialog::around(view.scrollable().max_height(20)).title("Filter by tag"));
+}
+
+/// The incremental search, the table shows the matching entries while typing.
+pub fn search(s: &mut Cursive) {
+    let search = app_context::fetch(s).filter().search.unwrap_or_default();
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(
+                    EditView::new()
+                        .content(search.text)
+                        .on_edit(|s, _, _| update_search(s))
+                        .on_submit(|s, _| close_search(s))
+                        .with_name(SEARCH_TEXT)
+                        .min_width(40)
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(Checkbox::new().with_checked(search.regex).on_change(|s, _| update_search(s)).with_name(SEARCH_REGEX))
+                        .child(TextView::new(" Regex"))
+                )
+                .child(TextView::new("").with_name(SEARCH_ERROR))
+        )
+            .title("Search descriptions")
+            .button("Clear", |s| {
+                s.call_on_name(SEARCH_TEXT, |e: &mut EditView| e.set_content(""));
+                update_search(s);
+                close_search(s);
+            })
+            .button("Close", close_search)
+    );
+}
+
+fn update_search(s: &mut Cursive) {
+    let text = s.call_on_name(SEARCH_TEXT, |e: &mut EditView| e.get_content().to_string()).expect("Search text should be defined");
+    let regex = s.call_on_name(SEARCH_REGEX, |c: &mut Checkbox| c.is_checked()).expect("Regex checkbox should be defined");
+    let mut filter = app_context::fetch(s).filter();
+    filter.search = Some(Search { text, regex }).filter(|it| !it.text.is_empty());
+    let error = apply(s, filter).err().unwrap_or_default();
+    s.call_on_name(SEARCH_ERROR, |t: &mut TextView| t.set_content(StyledString::styled(error, Color::Dark(BaseColor::Red))));
+}
+
+fn close_search(s: &mut Cursive) {
+    s.pop_layer();
+    s.focus_name(CLOCK_ENTRIES_TABLE).expect("Clock entries table should be defined");
+}
+
+/// Filters by clocked state, project, tag and duration, keeping the search.
+pub fn edit_filter(s: &mut Cursive) {
+    let current = app_context::fetch(s).filter();
+    let entries = clock_entries_table::get_clock_entries(s);
+
+    let mut clocked = SelectView::new().popup();
+    clocked.add_all([("(all)", None), ("Clocked", Some(true)), ("Unclocked", Some(false))]);
+    select(&mut clocked, &current.clocked);
+
+    let mut project = SelectView::new().popup();
+    project.add_item("(all)", None);
+    app_context::fetch(s).projects().into_iter().map(|it| it.name)
+        .chain(entries.iter().filter_map(|it| it.project.clone()))
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .for_each(|name| project.add_item(name.clone(), Some(name)));
+    select(&mut project, &current.project);
+
+    let mut tag = SelectView::new().popup();
+    tag.add_item("(all)", None);
+    tags::known_tags(&entries).into_iter().for_each(|it| tag.add_item(format!("#{}", it), Some(it)));
+    select(&mut tag, &current.tag);
+
+    s.add_layer(
+        Dialog::around(
+            ListView::new()
+                .child("Clocked", clocked.with_name(FILTER_CLOCKED))
+                .child("Project", project.with_name(FILTER_PROJECT))
+                .child("Tag", tag.with_name(FILTER_TAG))
+                .child("Min duration", EditView::new().content(current.min_seconds.map(format_duration).unwrap_or_default()).with_name(FILTER_MIN_DURATION).min_width(10))
+                .child("Max duration", EditView::new().content(current.max_seconds.map(format_duration).unwrap_or_default()).with_name(FILTER_MAX_DURATION).min_width(10))
+        )
+            .title("Filter entries")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Clear all", |s| {
+                s.pop_layer();
+                apply_or_show_error(s, EntryFilter::default());
+            })
+            .button("Ok", submit_filter)
+    );
+}
+
+fn submit_filter(s: &mut Cursive) {
+    let mut filter = app_context::fetch(s).filter();
+    filter.clocked = selection(s, FILTER_CLOCKED);
+    filter.project = selection(s, FILTER_PROJECT);
+    filter.tag = selection(s, FILTER_TAG);
+    for (name, value) in [(FILTER_MIN_DURATION, &mut filter.min_seconds), (FILTER_MAX_DURATION, &mut filter.max_seconds)] {
+        let typed = s.call_on_name(name, |e: &mut EditView| e.get_content().trim().to_string()).expect("Duration should be defined");
+        *value = match typed.as_str() {
+            "" => None,
+            typed => match time_picker::parse_duration(typed) {
+                Some(duration) => Some(duration.num_seconds()),
+                None => {
+                    s.add_layer(Dialog::info(format!("Invalid duration: {}", typed)));
+                    return;
+                },
+            },
+        };
+    }
+    s.pop_layer();
+    apply_or_show_error(s, filter);
+}
+
+fn select<T: PartialEq + 'static>(view: &mut SelectView<T>, value: &T) {
+    let index = view.iter().position(|(_, it)| it == value).unwrap_or(0);
+    view.set_selection(index);
+}
+
+fn selection<T: Clone + 'static>(s: &mut Cursive, name: &str) -> Option<T> {
+    s.call_on_name(name, |v: &mut SelectView<Option<T>>| v.selection())
+        .unwrap_or_else(|| panic!("{} should be defined", name))
+        .and_then(|it| (*it).clone())
+}
+
+fn apply_or_show_error(s: &mut Cursive, filter: EntryFilter) {
+    if let Err(e) = apply(s, filter) {
+        s.add_layer(Dialog::info(e));
+    }
+}
+
+/// Shows only the matching entries in the table, keeping the rest aside until the filter changes. The filter stays
+/// as it was when the search regex is invalid.
+pub fn apply(s: &mut Cursive, filter: EntryFilter) -> Result<(), String> {
+    let (visible, hidden) = filter.partition(clock_entries_table::get_clock_entries(s))?;
+    clock_entries_table::set_visible_entries(s, visible);
+    app_context::fetch(s).set_filter(filter, hidden);
+    stats_view::update_stats(s);
+    Ok(())
+}
+
+#[cfg(test)]
+mod filter_test {
+    use chrono::NaiveTime;
+
+    use crate::ClockEntry;
+    use crate::filter::{EntryFilter, Search};
+
+    fn entry(description: &str, minutes: u32, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            from: NaiveTime::from_hms(9, 0, 0),
+            to: NaiveTime::from_hms(9 + minutes / 60, minutes % 60, 0),
+            description: description.to_string(),
+            is_clocked,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn descriptions(filter: &EntryFilter) -> Vec<String> {
+        let entries = vec![
+            entry("Standup", 15, true),
+            entry("ABC-12 parser", 90, false),
+            ClockEntry { project: Some("Website".to_string()), ..entry("Code review of ABC-7", 30, false) },
+        ];
+        filter.partition(entries).unwrap().0.into_iter().map(|it| it.description).collect()
+    }
+
+    #[test]
+    fn searches_descriptions_as_text_or_regex() {
+        let text = EntryFilter { search: Some(Search { text: "abc-".to_string(), regex: false }), ..EntryFilter::default() };
+        assert_eq!(descriptions(&text), vec!["ABC-12 parser", "Code review of ABC-7"]);
+        let regex = EntryFilter { search: Some(Search { text: "^abc-\\d+".to_string(), regex: true }), ..EntryFilter::default() };
+        assert_eq!(descriptions(&regex), vec!["ABC-12 parser"]);
+        let invalid = EntryFilter { search: Some(Search { text: "(".to_string(), regex: true }), ..EntryFilter::default() };
+        assert!(invalid.partition(vec![]).unwrap_err().starts_with("Invalid regex"));
+    }
+
+    #[test]
+    fn filters_by_clocked_state_duration_and_project() {
+        assert_eq!(descriptions(&EntryFilter { clocked: Some(false), ..EntryFilter::default() }), vec!["ABC-12 parser", "Code review of ABC-7"]);
+        assert_eq!(descriptions(&EntryFilter { min_seconds: Some(1800), max_seconds: Some(3600), ..EntryFilter::default() }), vec!["Code review of ABC-7"]);
+        assert_eq!(descriptions(&EntryFilter { project: Some("Website".to_string()), ..EntryFilter::default() }), vec!["Code review of ABC-7"]);
+    }
+
+    #[test]
+    fn describes_the_active_filter() {
+        let filter = EntryFilter {
+            tag: Some("meeting".to_string()),
+            search: Some(Search { text: "standup".to_string(), regex: false }),
+            clocked: Some(false),
+            min_seconds: Some(5400),
+            ..EntryFilter::default()
+        };
+        assert_eq!(filter.describe(), "\"standup\" #meeting unclocked >= 1h30m");
+        assert!(!EntryFilter::default().is_active());
+    }
+}
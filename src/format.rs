@@ -21,6 +21,11 @@ pub fn format_hms(granularity: Granularity, total_seconds: i64) -> String {
     }
 }
 
+/// Formats with a sign, like `+1h 15m` or `-0h 30m`.
+pub fn format_signed_hms(granularity: Granularity, total_seconds: i64) -> String {
+    format!("{}{}", if total_seconds < 0 { "-" } else { "+" }, format_hms(granularity, total_seconds.abs()))
+}
+
 pub fn format_naive_time(granularity: Granularity, it: NaiveTime) -> String {
     format_clock(granularity, it.hour(), it.minute(), it.second())
 }
@@ -38,7 +43,7 @@ pub fn format_clock(granularity: Granularity, hours: u32, minutes: u32, seconds:
 
 #[cfg(test)]
 mod format_hms_test {
-    use crate::format::format_hms;
+    use crate::format::{format_hms, format_signed_hms};
     use crate::Granularity;
 
     #[test]
@@ -125,6 +130,12 @@ mod format_hms_test {
         });
     }
 
+    #[test]
+    fn format_signed_hms_test() {
+        assert_eq!(format_signed_hms(Granularity::Detailed, 3600 + 15 * 60), "+1h 15m");
+        assert_eq!(format_signed_hms(Granularity::Detailed, -(3600 + 15 * 60)), "-1h 15m");
+        assert_eq!(format_signed_hms(Granularity::Relaxed, 0), "+0h");
+    }
 }
 
 #[cfg(test)]
@@ -1,13 +1,12 @@
-use chrono::{NaiveTime, Timelike};
+use chrono::{Duration, NaiveTime, Timelike};
 use cursive::{Cursive, traits::Nameable, views::SelectView};
 use cursive::direction::Orientation;
 use cursive::traits::Resizable;
 use cursive::views::{LinearLayout, NamedView, TextView};
-use cursive_table_view::TableView;
 use serde::{Deserialize, Serialize};
 
 use crate::{app_context, clock_entries_table, model::ClockEntry, stats_view};
-use crate::clock_entries_table::ClockEntryColumn;
+use crate::history::{Command, Times};
 
 const GRANULARITY: &str = "Granularity";
 
@@ -43,15 +42,37 @@ fn create_view(selected_granularity: Granularity) -> NamedView<SelectView<Granul
 }
 
 fn select_granularity(s: &mut Cursive, granularity: Granularity) {
-    s.call_on_name(clock_entries_table::CLOCK_ENTRIES_TABLE, |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        for item in t.borrow_items_mut() {
-            normalize_for_granularity(item, granularity);
-        };
-    }).expect("The Clock entries table should be defined");
-    app_context::fetch(s).normalize_recording(granularity);
+    let before = app_context::fetch(s).granularity();
+    if before == granularity {
+        return;
+    }
+    let command = Command::ChangeGranularity {
+        before,
+        after: granularity,
+        entries: clock_entries_table::get_clock_entries(s).iter().map(Times::from).collect(),
+        recording: app_context::fetch(s).ongoing_recording().as_ref().map(Times::from),
+        timers: app_context::fetch(s).timers().iter().map(|it| Times::from(&it.entry)).collect(),
+    };
+    normalize_all(s, granularity);
+    app_context::fetch(s).record(command);
     stats_view::update_stats(s);
 }
 
+/// Selects the granularity and normalizes all the entries to it, without recording it in the history.
+pub fn set_granularity(s: &mut Cursive, granularity: Granularity) {
+    s.call_on_name(GRANULARITY, |view: &mut SelectView<Granularity>| {
+        view.set_selection(granularity as usize);
+    }).expect("The Granularity select should be defined");
+    normalize_all(s, granularity);
+}
+
+fn normalize_all(s: &mut Cursive, granularity: Granularity) {
+    clock_entries_table::edit_visible_entries(s, |it| normalize_for_granularity(it, granularity));
+    app_context::fetch(s).normalize_recording(granularity);
+    app_context::fetch(s).normalize_hidden(granularity);
+    app_context::fetch(s).set_granularity(granularity);
+}
+
 pub fn get_granularity(s: &mut Cursive) -> Granularity {
     s.call_on_name(GRANULARITY, |view: &mut SelectView<Granularity>|{
         *view.selection().expect("Something should be selected")
@@ -64,7 +85,19 @@ pub fn normalize_for_granularity(item: &mut ClockEntry, granularity: Granularity
     item.granularity = granularity;
 }
 
-fn normalize(it: NaiveTime, granularity: Granularity) -> NaiveTime {
+/// The time between two consecutive times of the granularity.
+pub fn step(granularity: Granularity) -> Duration {
+    match granularity {
+        Granularity::Relaxed => Duration::hours(1),
+        Granularity::Reasonable => Duration::minutes(30),
+        Granularity::Detailed => Duration::minutes(15),
+        Granularity::Paranoid => Duration::minutes(5),
+        Granularity::Ocd => Duration::minutes(1),
+        Granularity::Scientific => Duration::seconds(1),
+    }
+}
+
+pub fn normalize(it: NaiveTime, granularity: Granularity) -> NaiveTime {
     match granularity {
         Granularity::Relaxed => {
             it.with_minute(0).unwrap()
@@ -0,0 +1,524 @@
+use chrono::NaiveTime;
+use cursive::Cursive;
+use cursive::traits::{Resizable, Scrollable};
+use cursive::views::{Dialog, TextView};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{app_context, clock_entries_table, ClockEntry, Granularity, granularity_picker, record, stats_view};
+use crate::breaks::Break;
+use crate::timers::Timer;
+
+/// How many steps can be undone, the oldest ones are forgotten first.
+const MAX_STEPS: usize = 100;
+
+/// The times of an entry before a granularity change, which is all that changes.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Times {
+    pub id: Uuid,
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+    pub granularity: Granularity,
+}
+
+impl From<&ClockEntry> for Times {
+    fn from(entry: &ClockEntry) -> Self {
+        Times { id: entry.id, from: entry.from, to: entry.to, granularity: entry.granularity }
+    }
+}
+
+/// A change to the entries, kept with what's needed to revert and repeat it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Command {
+    Add(ClockEntry),
+    Edit { before: ClockEntry, after: ClockEntry },
+    Delete(ClockEntry),
+    /// The entry as it was before toggling
+    ToggleClocked(ClockEntry),
+    /// The times of all the entries, the recording and the timers before the change, with their full precision
+    ChangeGranularity { before: Granularity, after: Granularity, entries: Vec<Times>, recording: Option<Times>, timers: Vec<Times> },
+    /// The recording with its breaks, and the entries it was stopped as
+    StopRecording { recording: ClockEntry, breaks: Vec<Break>, entries: Vec<ClockEntry> },
+    /// A stopped recording, like `StopRecording`, and the recording started right after it
+    SwitchRecording { recording: ClockEntry, breaks: Vec<Break>, entries: Vec<ClockEntry>, next: ClockEntry },
+    /// The timer as it was running, and the entry it was stopped as
+    StopTimer { timer: Timer, entry: ClockEntry },
+    /// Commands undone and redone together, like a bulk action on the marked entries
+    Batch { label: String, commands: Vec<Command> },
+}
+
+impl Command {
+    pub fn label(&self) -> String {
+        match self {
+            Command::Add(entry) => format!("Add '{}'", entry.description),
+            Command::Edit { before, .. } => format!("Edit '{}'", before.description),
+            Command::Delete(entry) => format!("Delete '{}'", entry.description),
+            Command::ToggleClocked(entry) => format!("Toggle clocked '{}'", entry.description),
+            Command::ChangeGranularity { before, after, .. } => format!("Change granularity from {:?} to {:?}", before, after),
+            Command::StopRecording { recording, entries, .. } => format!("Stop recording '{}'", entries.first().unwrap_or(recording).description),
+            Command::SwitchRecording { recording, next, .. } => format!("Switch from '{}' to '{}'", recording.description, next.description),
+            Command::StopTimer { entry, .. } => format!("Stop timer '{}'", entry.description),
+            Command::Batch { label, .. } => label.clone(),
+        }
+    }
+}
+
+/// The undo and redo stacks of the session.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct History {
+    done: Vec<Command>,
+    undone: Vec<Command>,
+}
+
+impl History {
+    /// Adds a new command, which drops whatever could be redone.
+    pub fn record(&mut self, command: Command) {
+        self.done.push(command);
+        if self.done.len() > MAX_STEPS {
+            self.done.remove(0);
+        }
+        self.undone.clear();
+    }
+
+    pub fn undo(&mut self) -> Option<Command> {
+        let command = self.done.pop()?;
+        self.undone.push(command.clone());
+        Some(command)
+    }
+
+    pub fn redo(&mut self) -> Option<Command> {
+        let command = self.undone.pop()?;
+        self.done.push(command.clone());
+        Some(command)
+    }
+
+    /// Forgets the command just undone, when it couldn't be, so the steps before it can still be undone.
+    pub fn forget_undone(&mut self) {
+        self.undone.pop();
+    }
+
+    /// Forgets the command just redone, when it couldn't be.
+    pub fn forget_redone(&mut self) {
+        self.done.pop();
+    }
+
+    pub fn done(&self) -> &[Command] {
+        &self.done
+    }
+
+    pub fn undone(&self) -> &[Command] {
+        &self.undone
+    }
+}
+
+pub fn undo(s: &mut Cursive) {
+    if let Some(command) = app_context::fetch(s).undo() {
+        if let Err(e) = revert(s, &command) {
+            app_context::fetch(s).forget_undone();
+            s.add_layer(Dialog::info(format!("Can't undo {}: {}. It's left out of the history.", command.label(), e)));
+        }
+        stats_view::update_stats(s);
+    }
+}
+
+pub fn redo(s: &mut Cursive) {
+    if let Some(command) = app_context::fetch(s).redo() {
+        if let Err(e) = apply(s, &command) {
+            app_context::fetch(s).forget_redone();
+            s.add_layer(Dialog::info(format!("Can't redo {}: {}. It's left out of the history.", command.label(), e)));
+        }
+        stats_view::update_stats(s);
+    }
+}
+
+/// Applies the command, a batch entirely or not at all.
+pub fn apply(s: &mut Cursive, command: &Command) -> Result<(), String> {
+    match command {
+        Command::Add(entry) => clock_entries_table::insert_entry(s, entry.clone()),
+        Command::Edit { before, after } => change_fields(s, before, after)?,
+        Command::Delete(entry) => { clock_entries_table::remove_entry(s, entry.id)?; },
+        Command::ToggleClocked(entry) => clock_entries_table::toggle_clocked(s, entry.id)?,
+        Command::ChangeGranularity { after, .. } => granularity_picker::set_granularity(s, *after),
+        Command::StopRecording { recording, entries, .. } => {
+            if app_context::fetch(s).ongoing_recording().is_none_or(|it| it.id != recording.id) {
+                return Err("that recording is no longer in progress".to_string());
+            }
+            app_context::fetch(s).stop_recording();
+            entries.iter().for_each(|it| clock_entries_table::insert_entry(s, it.clone()));
+            record::set_record_button(s, false);
+        },
+        Command::SwitchRecording { recording, entries, next, .. } => {
+            if app_context::fetch(s).ongoing_recording().is_none_or(|it| it.id != recording.id) {
+                return Err("that recording is no longer in progress".to_string());
+            }
+            app_context::fetch(s).stop_recording();
+            entries.iter().for_each(|it| clock_entries_table::insert_entry(s, it.clone()));
+            app_context::fetch(s).start_recording(next.clone());
+        },
+        Command::StopTimer { timer, entry } => {
+            let mut timers = app_context::fetch(s).timers();
+            let index = timers.iter().position(|it| it.entry.id == timer.entry.id).ok_or("that timer is no longer running")?;
+            timers.remove(index);
+            app_context::fetch(s).set_timers(timers);
+            clock_entries_table::insert_entry(s, entry.clone());
+        },
+        Command::Batch { commands, .. } => {
+            for (index, command) in commands.iter().enumerate() {
+                if let Err(e) = apply(s, command) {
+                    // Rather left as it was than half done
+                    commands[..index].iter().rev().for_each(|it| { let _ = revert(s, it); });
+                    return Err(e);
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+fn revert(s: &mut Cursive, command: &Command) -> Result<(), String> {
+    match command {
+        Command::Add(entry) => { clock_entries_table::remove_entry(s, entry.id)?; },
+        Command::Edit { before, after } => change_fields(s, after, before)?,
+        Command::Delete(entry) => clock_entries_table::insert_entry(s, entry.clone()),
+        Command::ToggleClocked(entry) => clock_entries_table::toggle_clocked(s, entry.id)?,
+        Command::ChangeGranularity { before, entries, recording, timers, .. } => {
+            let current = clock_entries_table::get_clock_entries(s);
+            let deleted = entries.iter().filter(|it| current.iter().all(|current| current.id != it.id)).count();
+            if deleted > 0 {
+                return Err(format!("{} of the entries were deleted since", deleted));
+            }
+            granularity_picker::set_granularity(s, *before);
+            // Only the times go back, later changes like clocking or invoicing stay
+            let restore_times = |entry: &mut ClockEntry| {
+                if let Some(saved) = entries.iter().chain(recording.iter()).chain(timers.iter()).find(|it| it.id == entry.id) {
+                    entry.from = saved.from;
+                    entry.to = saved.to;
+                    entry.granularity = saved.granularity;
+                }
+            };
+            clock_entries_table::edit_visible_entries(s, restore_times);
+            app_context::fetch(s).update_hidden(restore_times);
+            if let Some(mut ongoing) = app_context::fetch(s).ongoing_recording() {
+                restore_times(&mut ongoing);
+                app_context::fetch(s).start_recording(ongoing);
+            }
+            let mut running = app_context::fetch(s).timers();
+            running.iter_mut().for_each(|it| restore_times(&mut it.entry));
+            app_context::fetch(s).set_timers(running);
+        },
+        Command::StopRecording { recording, breaks, entries } => {
+            if app_context::fetch(s).is_recording() {
+                return Err("stop the ongoing recording first".to_string());
+            }
+            entries.iter().try_for_each(|it| clock_entries_table::remove_entry(s, it.id).map(|_| ()))?;
+            app_context::fetch(s).start_recording(recording.clone());
+            app_context::fetch(s).set_recording_breaks(breaks.clone());
+            record::set_record_button(s, true);
+        },
+        Command::SwitchRecording { recording, breaks, entries, next } => {
+            if app_context::fetch(s).ongoing_recording().is_none_or(|it| it.id != next.id) {
+                return Err(format!("the recording of '{}' is no longer in progress", next.description));
+            }
+            app_context::fetch(s).stop_recording();
+            entries.iter().try_for_each(|it| clock_entries_table::remove_entry(s, it.id).map(|_| ()))?;
+            app_context::fetch(s).start_recording(recording.clone());
+            app_context::fetch(s).set_recording_breaks(breaks.clone());
+        },
+        Command::StopTimer { timer, entry } => {
+            clock_entries_table::remove_entry(s, entry.id)?;
+            let mut timers = app_context::fetch(s).timers();
+            timers.push(timer.clone());
+            app_context::fetch(s).set_timers(timers);
+        },
+        Command::Batch { commands, .. } => {
+            for (index, command) in commands.iter().enumerate().rev() {
+                if let Err(e) = revert(s, command) {
+                    commands[index + 1..].iter().for_each(|it| { let _ = apply(s, it); });
+                    return Err(e);
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Changes the fields of the entry that differ between `old` and `new` to the `new` ones. The other fields keep what
+/// they are now, as they may have changed since without a step in the history, like the invoice or a sync clocking it.
+fn change_fields(s: &mut Cursive, old: &ClockEntry, new: &ClockEntry) -> Result<(), String> {
+    let current = clock_entries_table::get_clock_entries(s).into_iter()
+        .find(|it| it.id == new.id)
+        .ok_or_else(|| format!("no entry with ID {}", new.id))?;
+    clock_entries_table::replace_entry(s, with_changes(&current, old, new))
+}
+
+fn with_changes(current: &ClockEntry, old: &ClockEntry, new: &ClockEntry) -> ClockEntry {
+    let mut entry = current.clone();
+    if old.date != new.date { entry.date = new.date; }
+    if old.from != new.from { entry.from = new.from; }
+    if old.to != new.to { entry.to = new.to; }
+    if old.description != new.description { entry.description = new.description.clone(); }
+    if old.is_clocked != new.is_clocked { entry.is_clocked = new.is_clocked; }
+    if old.granularity != new.granularity { entry.granularity = new.granularity; }
+    if old.project != new.project { entry.project = new.project.clone(); }
+    if old.tags != new.tags { entry.tags = new.tags.clone(); }
+    if old.billable != new.billable { entry.billable = new.billable; }
+    if old.invoice != new.invoice { entry.invoice = new.invoice.clone(); }
+    if old.excluded_from_totals != new.excluded_from_totals { entry.excluded_from_totals = new.excluded_from_totals; }
+    entry.touch();
+    entry
+}
+
+pub fn show_history(s: &mut Cursive) {
+    let history = app_context::fetch(s).history();
+    let mut lines = vec!["Undo (u):".to_string()];
+    lines.extend(history.done().iter().rev().map(|it| format!("  {}", it.label())));
+    lines.push(String::new());
+    lines.push("Redo (Ctrl-r):".to_string());
+    lines.extend(history.undone().iter().rev().map(|it| format!("  {}", it.label())));
+    s.add_layer(
+        Dialog::around(TextView::new(lines.join("\n")).scrollable().max_height(20))
+            .title("History")
+            .button("Ok", |s| { s.pop_layer(); })
+    );
+}
+
+#[cfg(test)]
+mod history_test {
+    use chrono::NaiveTime;
+    use cursive::Cursive;
+    use cursive::views::LinearLayout;
+
+    use crate::{app_context, clock_entries_table, ClockEntry, ClockKing, filter, GlobalContext, Granularity, granularity_picker, history, invoice, Project};
+    use crate::filter::EntryFilter;
+    use crate::history::{apply, Command, History, MAX_STEPS, revert, Times};
+    use crate::timers::Timer;
+
+    fn entry(description: &str) -> ClockEntry {
+        ClockEntry { description: description.to_string(), ..ClockEntry::default() }
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms(hour, minute, 0)
+    }
+
+    #[test]
+    fn undoes_and_redoes_in_order() {
+        let mut history = History::default();
+        history.record(Command::Add(entry("Coding")));
+        history.record(Command::Delete(entry("Review")));
+
+        assert_eq!(history.undo().map(|it| it.label()), Some("Delete 'Review'".to_string()));
+        assert_eq!(history.undo().map(|it| it.label()), Some("Add 'Coding'".to_string()));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo().map(|it| it.label()), Some("Add 'Coding'".to_string()));
+        assert_eq!(history.done().len(), 1);
+        assert_eq!(history.undone().len(), 1);
+    }
+
+    #[test]
+    fn recording_drops_the_redo_stack() {
+        let mut history = History::default();
+        let review = entry("Review");
+        history.record(Command::Add(entry("Coding")));
+        history.undo();
+        history.record(Command::ToggleClocked(review.clone()));
+
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.done(), &[Command::ToggleClocked(review)][..]);
+    }
+
+    #[test]
+    fn undoes_a_batch_as_one_step() {
+        let mut history = History::default();
+        let commands = vec![Command::Delete(entry("Coding")), Command::Delete(entry("Review"))];
+        history.record(Command::Batch { label: "Delete 2 entries".to_string(), commands });
+
+        assert_eq!(history.undo().map(|it| it.label()), Some("Delete 2 entries".to_string()));
+        assert_eq!(history.undo(), None);
+    }
+
+    /// A headless session with only the granularity and the table of the entries.
+    fn session(entries: &[ClockEntry]) -> Cursive {
+        let model = ClockKing { clock_entries: entries.to_vec(), ..ClockKing::default() };
+        let mut s = Cursive::new();
+        s.set_user_data(GlobalContext::new(&model));
+        s.add_layer(LinearLayout::vertical()
+            .child(granularity_picker::new(model.granularity))
+            .child(clock_entries_table::new(model)));
+        s
+    }
+
+    fn descriptions(s: &mut Cursive) -> Vec<String> {
+        let mut descriptions: Vec<String> = clock_entries_table::get_clock_entries(s).into_iter().map(|it| it.description).collect();
+        descriptions.sort();
+        descriptions
+    }
+
+    #[test]
+    fn reverts_a_batch_delete() {
+        let entries = vec![entry("Coding"), entry("Review")];
+        let mut s = session(&entries);
+        let batch = Command::Batch { label: "Delete 2 entries".to_string(), commands: entries.into_iter().map(Command::Delete).collect() };
+
+        apply(&mut s, &batch).unwrap();
+        assert!(descriptions(&mut s).is_empty());
+        revert(&mut s, &batch).unwrap();
+        assert_eq!(descriptions(&mut s), vec!["Coding", "Review"]);
+    }
+
+    #[test]
+    fn reverts_a_batch_marking_clocked() {
+        let entries = vec![entry("Coding"), entry("Review")];
+        let mut s = session(&entries);
+        let commands = entries.iter()
+            .map(|it| Command::Edit { before: it.clone(), after: ClockEntry { is_clocked: true, ..it.clone() } })
+            .collect();
+        let batch = Command::Batch { label: "Mark clocked on 2 entries".to_string(), commands };
+
+        apply(&mut s, &batch).unwrap();
+        assert!(clock_entries_table::get_clock_entries(&mut s).iter().all(|it| it.is_clocked));
+        revert(&mut s, &batch).unwrap();
+        let mut reverted = clock_entries_table::get_clock_entries(&mut s);
+        reverted.sort_by(|a, b| a.description.cmp(&b.description));
+        assert_eq!(reverted, entries.into_iter().zip(&reverted).map(|(it, r)| ClockEntry { updated_at: r.updated_at, ..it }).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn reverts_only_the_times_of_a_granularity_change() {
+        let coding = ClockEntry { from: time(9, 7), to: time(10, 13), ..entry("Coding") };
+        let mut s = session(std::slice::from_ref(&coding));
+        let change = Command::ChangeGranularity { before: Granularity::Detailed, after: Granularity::Relaxed, entries: vec![Times::from(&coding)], recording: None, timers: vec![] };
+
+        apply(&mut s, &change).unwrap();
+        let changed = clock_entries_table::get_clock_entries(&mut s).remove(0);
+        assert_eq!((changed.from, changed.to), (time(9, 0), time(10, 0)));
+        clock_entries_table::toggle_clocked(&mut s, coding.id).unwrap();
+        revert(&mut s, &change).unwrap();
+
+        let reverted = clock_entries_table::get_clock_entries(&mut s).remove(0);
+        assert_eq!((reverted.from, reverted.to, reverted.granularity), (time(9, 7), time(10, 13), Granularity::Detailed));
+        assert!(reverted.is_clocked);
+        assert_eq!(granularity_picker::get_granularity(&mut s), Granularity::Detailed);
+    }
+
+    #[test]
+    fn leaves_a_granularity_change_of_deleted_entries_as_it_is() {
+        let entries = vec![ClockEntry { from: time(9, 7), ..entry("Coding") }, entry("Review")];
+        let mut s = session(&entries);
+        let change = Command::ChangeGranularity { before: Granularity::Detailed, after: Granularity::Relaxed, entries: entries.iter().map(Times::from).collect(), recording: None, timers: vec![] };
+
+        apply(&mut s, &change).unwrap();
+        clock_entries_table::remove_entry(&mut s, entries[1].id).unwrap();
+
+        assert_eq!(revert(&mut s, &change), Err("1 of the entries were deleted since".to_string()));
+        assert_eq!(clock_entries_table::get_clock_entries(&mut s)[0].from, time(9, 0));
+        assert_eq!(granularity_picker::get_granularity(&mut s), Granularity::Relaxed);
+    }
+
+    #[test]
+    fn keeps_entries_the_filter_hides_out_of_the_table() {
+        let coding = entry("Coding");
+        let review = ClockEntry { is_clocked: true, ..entry("Review") };
+        let mut s = session(&[coding.clone(), review.clone()]);
+        filter::apply(&mut s, EntryFilter { clocked: Some(false), ..EntryFilter::default() }).unwrap();
+
+        clock_entries_table::remove_entry(&mut s, review.id).unwrap();
+        revert(&mut s, &Command::Delete(review)).unwrap();
+        clock_entries_table::toggle_clocked(&mut s, coding.id).unwrap();
+
+        assert!(clock_entries_table::get_visible_clock_entries(&mut s).is_empty());
+        assert_eq!(descriptions(&mut s), vec!["Coding", "Review"]);
+    }
+
+    #[test]
+    fn hides_an_edited_entry_the_filter_no_longer_shows() {
+        let coding = entry("Coding");
+        let mut s = session(std::slice::from_ref(&coding));
+        filter::apply(&mut s, EntryFilter { clocked: Some(false), ..EntryFilter::default() }).unwrap();
+        let edit = Command::Edit { before: coding.clone(), after: ClockEntry { is_clocked: true, ..coding.clone() } };
+
+        apply(&mut s, &edit).unwrap();
+        assert!(clock_entries_table::get_visible_clock_entries(&mut s).is_empty());
+        revert(&mut s, &edit).unwrap();
+        assert_eq!(clock_entries_table::get_visible_clock_entries(&mut s).len(), 1);
+    }
+
+    #[test]
+    fn changes_the_granularity_of_running_timers() {
+        let mut s = session(&[]);
+        let build = ClockEntry { from: time(9, 7), ..entry("Build") };
+        app_context::fetch(&mut s).set_timers(vec![Timer { entry: build.clone(), counts_toward_total: false }]);
+        let change = Command::ChangeGranularity { before: Granularity::Detailed, after: Granularity::Relaxed, entries: vec![], recording: None, timers: vec![Times::from(&build)] };
+        let timer_start = |s: &mut Cursive| app_context::fetch(s).timers()[0].entry.from;
+
+        apply(&mut s, &change).unwrap();
+        assert_eq!(timer_start(&mut s), time(9, 0));
+        revert(&mut s, &change).unwrap();
+        assert_eq!(timer_start(&mut s), time(9, 7));
+    }
+
+    #[test]
+    fn forgets_the_oldest_steps() {
+        let mut history = History::default();
+        (0..MAX_STEPS + 1).for_each(|it| history.record(Command::Add(entry(&it.to_string()))));
+
+        assert_eq!(history.done().len(), MAX_STEPS);
+        assert_eq!(history.done()[0].label(), "Add '1'");
+    }
+
+    #[test]
+    fn leaves_a_batch_as_it_was_when_it_fails_partway() {
+        let coding = entry("Coding");
+        let mut s = session(std::slice::from_ref(&coding));
+        let batch = Command::Batch { label: "Delete 2 entries".to_string(), commands: vec![Command::Delete(coding), Command::Delete(entry("Gone"))] };
+
+        assert!(apply(&mut s, &batch).is_err());
+        assert_eq!(descriptions(&mut s), vec!["Coding"]);
+    }
+
+    #[test]
+    fn undoes_an_edit_keeping_the_fields_changed_since() {
+        let coding = entry("Coding");
+        let mut s = session(std::slice::from_ref(&coding));
+        let edit = Command::Edit { before: coding.clone(), after: ClockEntry { description: "Coding ABC-1".to_string(), ..coding.clone() } };
+
+        apply(&mut s, &edit).unwrap();
+        let invoiced = ClockEntry { invoice: Some("2022-0007".to_string()), ..clock_entries_table::get_clock_entries(&mut s).remove(0) };
+        clock_entries_table::replace_entry(&mut s, invoiced).unwrap();
+        revert(&mut s, &edit).unwrap();
+
+        let reverted = clock_entries_table::get_clock_entries(&mut s).remove(0);
+        assert_eq!((reverted.description.as_str(), reverted.invoice.as_deref()), ("Coding", Some("2022-0007")));
+    }
+
+    #[test]
+    fn keeps_the_invoice_of_entries_invoiced_after_an_edit() {
+        let coding = ClockEntry { billable: true, project: Some("Website".to_string()), ..entry("Coding") };
+        let mut s = session(std::slice::from_ref(&coding));
+        let projects = vec![Project { name: "Website".to_string(), client: Some("ACME".to_string()), rate: None }];
+        let edit = Command::Edit { before: coding.clone(), after: ClockEntry { description: "Coding ABC-1".to_string(), ..coding.clone() } };
+        apply(&mut s, &edit).unwrap();
+        app_context::fetch(&mut s).record(edit);
+
+        clock_entries_table::edit_visible_entries(&mut s, |it| invoice::mark_invoiced(it, &projects, "ACME", &(coding.date..=coding.date), "2022-0007"));
+        history::undo(&mut s);
+
+        let undone = clock_entries_table::get_clock_entries(&mut s).remove(0);
+        assert_eq!((undone.description.as_str(), undone.invoice.as_deref()), ("Coding", Some("2022-0007")));
+    }
+
+    #[test]
+    fn skips_a_step_that_cant_be_undone() {
+        let coding = entry("Coding");
+        let mut s = session(std::slice::from_ref(&coding));
+        app_context::fetch(&mut s).record(Command::Add(coding));
+        app_context::fetch(&mut s).record(Command::Add(entry("Deleted without a step")));
+
+        history::undo(&mut s);
+        history::undo(&mut s);
+
+        assert!(descriptions(&mut s).is_empty());
+        assert!(app_context::fetch(&mut s).history().done().is_empty());
+        assert_eq!(app_context::fetch(&mut s).history().undone().len(), 1);
+    }
+}
@@ -1,7 +1,14 @@
 use cursive::Cursive;
+use cursive::event::{Event, EventResult, EventTrigger};
 use cursive::traits::Nameable;
-use cursive::views::{Checkbox, NamedView, TextArea};
+use chrono::NaiveDate;
+use cursive::view::View;
+use cursive::views::{Checkbox, EditView, LinearLayout, NamedView, OnEventView, TextArea, TextView};
 use crate::clock_entries_table::ClockEntryColumn;
+use crate::{tags, templates, time_picker};
+
+const TAG_SUGGESTIONS: &str = "tag_suggestions";
+const DESCRIPTION_SUGGESTIONS: &str = "description_suggestions";
 
 pub fn text_area_input(col: ClockEntryColumn, value:Option<String>) -> NamedView<TextArea> {
     TextArea::new()
@@ -9,6 +16,40 @@ pub fn text_area_input(col: ClockEntryColumn, value:Option<String>) -> NamedView
         .with_name(col.as_str())
 }
 
+/// A text area suggesting the `ranked` descriptions that complete the typed one, Ctrl-n takes the first.
+pub fn description_input(col: ClockEntryColumn, value: Option<String>, ranked: Vec<String>) -> LinearLayout {
+    LinearLayout::vertical()
+        .child(
+            OnEventView::new(text_area_input(col, value))
+                .on_pre_event_inner(EventTrigger::any(), move |e, event| {
+                    let completion = templates::suggestions(&ranked, e.get_mut().get_content()).into_iter().next();
+                    let result = match completion {
+                        Some(completed) if *event == Event::CtrlChar('n') => {
+                            e.get_mut().set_content(completed);
+                            EventResult::Consumed(None)
+                        },
+                        _ => e.on_event(event.clone()),
+                    };
+                    if !result.is_consumed() {
+                        return Some(result);
+                    }
+                    let hint = format_description_suggestions(&templates::suggestions(&ranked, e.get_mut().get_content()));
+                    Some(result.and(EventResult::with_cb(move |s| {
+                        s.call_on_name(DESCRIPTION_SUGGESTIONS, |t: &mut TextView| t.set_content(hint.clone()));
+                    })))
+                })
+        )
+        .child(TextView::new("").with_name(DESCRIPTION_SUGGESTIONS))
+}
+
+fn format_description_suggestions(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!("Ctrl-n completes: {}", suggestions.iter().take(3).cloned().collect::<Vec<String>>().join(" | "))
+    }
+}
+
 pub fn text_area_value(s: &mut Cursive, col: ClockEntryColumn) -> String {
     s.call_on_name(col.as_str(), |e: &mut TextArea| {
         e.get_content().to_string()
@@ -23,4 +64,53 @@ pub fn checkbox_value(s: &mut Cursive, col: ClockEntryColumn) -> bool {
     s.call_on_name(col.as_str(), |e: &mut Checkbox| {
         e.is_checked()
     }).unwrap_or_else(|| panic!("{} should be defined", col.as_str()))
-}
\ No newline at end of file
+}
+
+pub fn date_input(col: ClockEntryColumn, value: Option<NaiveDate>) -> NamedView<EditView> {
+    EditView::new()
+        .content(value.unwrap_or_else(time_picker::today).to_string())
+        .with_name(col.as_str())
+}
+
+pub fn date_value(s: &mut Cursive, col: ClockEntryColumn) -> Result<NaiveDate, String> {
+    s.call_on_name(col.as_str(), |e: &mut EditView| {
+        let content = e.get_content();
+        time_picker::parse_date(&content).ok_or(format!("Invalid date: {}", content))
+    }).unwrap_or_else(|| panic!("{} should be defined", col.as_str()))
+}
+
+pub fn tags_input(col: ClockEntryColumn, value: &[String], known_tags: Vec<String>) -> LinearLayout {
+    let known_tags_for_submit = known_tags.clone();
+    LinearLayout::vertical()
+        .child(
+            EditView::new()
+                .content(value.join(", "))
+                .on_edit(move |s, content, _| {
+                    let hint = format_tag_suggestions(&tags::suggestions(&known_tags, content));
+                    s.call_on_name(TAG_SUGGESTIONS, |t: &mut TextView| t.set_content(hint));
+                })
+                .on_submit(move |s, content| {
+                    if let Some(completed) = tags::complete(&known_tags_for_submit, content) {
+                        let hint = format_tag_suggestions(&tags::suggestions(&known_tags_for_submit, &completed));
+                        s.call_on_name(col.as_str(), |e: &mut EditView| e.set_content(completed));
+                        s.call_on_name(TAG_SUGGESTIONS, |t: &mut TextView| t.set_content(hint));
+                    }
+                })
+                .with_name(col.as_str())
+        )
+        .child(TextView::new("").with_name(TAG_SUGGESTIONS))
+}
+
+fn format_tag_suggestions(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!("Enter completes: {}", suggestions.iter().take(5).cloned().collect::<Vec<String>>().join(", "))
+    }
+}
+
+pub fn tags_value(s: &mut Cursive, col: ClockEntryColumn) -> Vec<String> {
+    s.call_on_name(col.as_str(), |e: &mut EditView| {
+        tags::parse_tag_list(&e.get_content())
+    }).unwrap_or_else(|| panic!("{} should be defined", col.as_str()))
+}
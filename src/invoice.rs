@@ -0,0 +1,513 @@
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, NaiveDate};
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, LinearLayout, SelectView, TextView};
+
+use crate::{app_context, billing, clock_entries_table, ClockEntry, config, db, format, Granularity, granularity_picker, Project, stats_view, time_picker};
+use crate::billing::Billing;
+use crate::config::InvoiceConfig;
+
+const INVOICE_CLIENT: &str = "invoice_client";
+const INVOICE_FROM: &str = "invoice_from";
+const INVOICE_TO: &str = "invoice_to";
+const INVOICE_FORMAT: &str = "invoice_format";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvoiceFormat {
+    Text,
+    Markdown,
+    Html,
+}
+
+impl InvoiceFormat {
+    pub fn parse(value: &str) -> Option<InvoiceFormat> {
+        match value {
+            "text" | "txt" => Some(InvoiceFormat::Text),
+            "markdown" | "md" => Some(InvoiceFormat::Markdown),
+            "html" => Some(InvoiceFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            InvoiceFormat::Text => "txt",
+            InvoiceFormat::Markdown => "md",
+            InvoiceFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvoiceLine {
+    pub project: String,
+    pub description: String,
+    pub seconds: i64,
+    pub rate: f64,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invoice {
+    pub number: String,
+    pub date: NaiveDate,
+    pub client: String,
+    pub period: RangeInclusive<NaiveDate>,
+    pub sender: String,
+    pub recipient: String,
+    pub footer: Option<String>,
+    pub currency: String,
+    pub lines: Vec<InvoiceLine>,
+}
+
+impl Invoice {
+    pub fn total(&self) -> f64 {
+        self.lines.iter().map(|it| it.amount).sum()
+    }
+}
+
+pub fn invoice(s: &mut Cursive) {
+    let clients = clients(&app_context::fetch(s).projects());
+    if clients.is_empty() {
+        s.add_layer(Dialog::info("No project has a client yet, add one with (P)rojects."));
+        return;
+    }
+    let today = time_picker::today();
+    let mut client_picker = SelectView::new().popup();
+    clients.into_iter().for_each(|client| client_picker.add_item(client.clone(), client));
+    let mut format_picker = SelectView::new().popup();
+    format_picker.add_item("Plain text", InvoiceFormat::Text);
+    format_picker.add_item("Markdown", InvoiceFormat::Markdown);
+    format_picker.add_item("HTML", InvoiceFormat::Html);
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new("Client"))
+                .child(client_picker.with_name(INVOICE_CLIENT))
+                .child(TextView::new("From"))
+                .child(EditView::new().content(today.with_day(1).unwrap().to_string()).with_name(INVOICE_FROM).min_width(30))
+                .child(TextView::new("To"))
+                .child(EditView::new().content(today.to_string()).with_name(INVOICE_TO).min_width(30))
+                .child(TextView::new("Format"))
+                .child(format_picker.with_name(INVOICE_FORMAT))
+        )
+            .title("Create invoice")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Create", create_invoice)
+    );
+}
+
+fn create_invoice(s: &mut Cursive) {
+    let client = s.call_on_name(INVOICE_CLIENT, |v: &mut SelectView<String>| v.selection())
+        .flatten()
+        .map(|it| (*it).clone())
+        .expect("A client should be selected");
+    let invoice_format = s.call_on_name(INVOICE_FORMAT, |v: &mut SelectView<InvoiceFormat>| v.selection())
+        .flatten()
+        .map(|it| *it)
+        .expect("A format should be selected");
+    let from = s.call_on_name(INVOICE_FROM, |e: &mut EditView| time_picker::parse_date(&e.get_content())).flatten();
+    let to = s.call_on_name(INVOICE_TO, |e: &mut EditView| time_picker::parse_date(&e.get_content())).flatten();
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        _ => {
+            s.add_layer(Dialog::info("Invalid date, use YYYY-MM-DD, today or yesterday."));
+            return;
+        },
+    };
+
+    save_invoice(s, client, invoice_format, from..=to, false);
+}
+
+/// Saves the invoice as `NUMBER.EXT`, asking first when the file exists. The next number is stored before the billed
+/// entries are marked and saved, so a failure leaves neither the file nor the marks behind.
+fn save_invoice(s: &mut Cursive, client: String, invoice_format: InvoiceFormat, period: RangeInclusive<NaiveDate>, overwrite: bool) {
+    let mut settings = match config::load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Unable to read ./.clockking/config.json: {}", e)));
+            return;
+        },
+    };
+    let invoice_config = match settings.invoice.as_mut() {
+        Some(invoice_config) => invoice_config,
+        None => {
+            s.add_layer(Dialog::info("No `invoice` section found in ./.clockking/config.json"));
+            return;
+        },
+    };
+
+    let granularity = granularity_picker::get_granularity(s);
+    let projects = app_context::fetch(s).projects();
+    let billing = app_context::fetch(s).billing();
+    let entries = clock_entries_table::get_clock_entries(s);
+    let invoice = match build(&entries, &projects, &billing, invoice_config, &client, period.clone(), granularity) {
+        Ok(invoice) => invoice,
+        Err(e) => {
+            s.add_layer(Dialog::info(e));
+            return;
+        },
+    };
+    let file_name = format!("{}.{}", invoice.number, invoice_format.extension());
+    match write_file(&file_name, &render(&invoice, invoice_format, granularity), overwrite) {
+        Ok(()) => {},
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            s.add_layer(cursive_extras::confirm_dialog(
+                "Create invoice",
+                format!("{} already exists. Overwrite it?", file_name),
+                move |s| {
+                    s.pop_layer();
+                    save_invoice(s, client.clone(), invoice_format, period.clone(), true);
+                }
+            ));
+            return;
+        },
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Unable to save the invoice to {}: {}", file_name, e)));
+            return;
+        },
+    }
+    invoice_config.next_number += 1;
+    if let Err(e) = config::store(&settings) {
+        let _ = fs::remove_file(&file_name);
+        s.add_layer(Dialog::info(format!("Unable to store the next invoice number, invoice {} was not created: {}", invoice.number, e)));
+        return;
+    }
+    let mark = |entry: &mut ClockEntry| mark_invoiced(entry, &projects, &client, &period, &invoice.number);
+    clock_entries_table::edit_visible_entries(s, mark);
+    app_context::fetch(s).update_hidden(mark);
+    db::save_to_db(s);
+    s.pop_layer();
+    s.add_layer(Dialog::info(format!("Invoice {} saved to {}", invoice.number, file_name)));
+    stats_view::update_stats(s);
+}
+
+/// Writes the invoice, an existing file is only overwritten when asked to.
+pub fn write_file(file_name: &str, content: &str, overwrite: bool) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    if overwrite {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.write(true).create_new(true);
+    }
+    options.open(file_name)?.write_all(content.as_bytes())
+}
+
+/// The clients of the projects in the catalogue, sorted.
+pub fn clients(projects: &[Project]) -> Vec<String> {
+    projects.iter()
+        .filter_map(|it| it.client.clone())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// Billable entries of the client's projects in the period that weren't invoiced yet.
+pub fn is_invoiceable(entry: &ClockEntry, projects: &[Project], client: &str, period: &RangeInclusive<NaiveDate>) -> bool {
+    entry.billable
+        && entry.invoice.is_none()
+        && period.contains(&entry.date)
+        && entry.project.as_ref()
+            .and_then(|name| projects.iter().find(|it| it.name == *name))
+            .and_then(|it| it.client.as_deref())
+            == Some(client)
+}
+
+pub fn mark_invoiced(entry: &mut ClockEntry, projects: &[Project], client: &str, period: &RangeInclusive<NaiveDate>, number: &str) {
+    if is_invoiceable(entry, projects, client, period) {
+        entry.invoice = Some(number.to_string());
+        entry.touch();
+    }
+}
+
+/// Groups the invoiceable entries per project and description, with the hours of each line rounded to the granularity.
+pub fn build(
+    entries: &[ClockEntry],
+    projects: &[Project],
+    billing: &Billing,
+    invoice_config: &InvoiceConfig,
+    client: &str,
+    period: RangeInclusive<NaiveDate>,
+    granularity: Granularity,
+) -> Result<Invoice, String> {
+    let mut entries: Vec<&ClockEntry> = entries.iter()
+        .filter(|it| is_invoiceable(it, projects, client, &period))
+        .collect();
+    if entries.is_empty() {
+        return Err(format!("No billable entries of {} left to invoice between {} and {}", client, period.start(), period.end()));
+    }
+    entries.sort_by_key(|it| (it.date, it.from));
+
+    let mut groups: Vec<(&Option<String>, &str)> = Vec::new();
+    entries.iter().for_each(|it| {
+        if !groups.contains(&(&it.project, it.description.as_str())) {
+            groups.push((&it.project, &it.description));
+        }
+    });
+    groups.sort_by_key(|(project, _)| *project);
+
+    let lines = groups.into_iter().map(|(project, description)| {
+        let rate = billing::rate(project, projects, billing)
+            .ok_or_else(|| format!("No hourly rate for {}, set one in the project or the default rate", stats_view::project_label(project, projects)))?;
+        let seconds = billing::rounded_seconds(
+            granularity,
+            stats_view::totals(entries.iter().copied().filter(|it| it.project == *project && it.description == description)).total_seconds
+        );
+        Ok(InvoiceLine {
+            project: stats_view::project_label(project, projects),
+            description: description.to_string(),
+            seconds,
+            rate,
+            amount: seconds as f64 / 3600.0 * rate,
+        })
+    }).collect::<Result<Vec<InvoiceLine>, String>>()?;
+
+    Ok(Invoice {
+        number: invoice_config.number(),
+        date: time_picker::today(),
+        client: client.to_string(),
+        period,
+        sender: invoice_config.sender.clone(),
+        recipient: invoice_config.recipient(client),
+        footer: invoice_config.footer.clone(),
+        currency: billing.currency.clone(),
+        lines,
+    })
+}
+
+pub fn render(invoice: &Invoice, invoice_format: InvoiceFormat, granularity: Granularity) -> String {
+    let money = |amount: f64| billing::format_money(amount, &invoice.currency);
+    let rows: Vec<[String; 5]> = invoice.lines.iter().map(|it| [
+        it.project.clone(),
+        it.description.clone(),
+        format::format_hms(granularity, it.seconds),
+        money(it.rate),
+        money(it.amount),
+    ]).collect();
+    let header = ["Project", "Description", "Hours", "Rate", "Amount"];
+    let title = format!("Invoice {}", invoice.number);
+    let details = [
+        format!("Date: {}", invoice.date),
+        format!("Period: {} - {}", invoice.period.start(), invoice.period.end()),
+    ];
+    let total = format!("Total: {}", money(invoice.total()));
+
+    match invoice_format {
+        InvoiceFormat::Text => {
+            let widths: Vec<usize> = (0..header.len())
+                .map(|i| rows.iter().map(|row| row[i].chars().count()).chain([header[i].len()]).max().unwrap())
+                .collect();
+            let line = |cells: Vec<&str>| cells.iter().zip(&widths)
+                .map(|(cell, width)| format!("{}{}", cell, " ".repeat(width - cell.chars().count())))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string();
+            let mut table = vec![line(header.to_vec()), "-".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1))];
+            rows.iter().for_each(|row| table.push(line(row.iter().map(String::as_str).collect())));
+            let mut parts = vec![
+                format!("{}\n{}", title, "=".repeat(title.chars().count())),
+                format!("From:\n{}", invoice.sender),
+                format!("To:\n{}", invoice.recipient),
+                details.join("\n"),
+                table.join("\n"),
+                total,
+            ];
+            parts.extend(invoice.footer.clone());
+            parts.join("\n\n") + "\n"
+        },
+        InvoiceFormat::Markdown => {
+            let escape = |cell: &str| cell.replace('|', "\\|");
+            let address = |text: &str| text.lines().collect::<Vec<&str>>().join("  \n");
+            let mut table = vec![format!("| {} |", header.join(" | ")), "|---|---|---|---:|---:|".to_string()];
+            rows.iter().for_each(|row| table.push(format!("| {} |", row.iter().map(|it| escape(it)).collect::<Vec<String>>().join(" | "))));
+            let mut parts = vec![
+                format!("# {}", title),
+                format!("**From:**  \n{}", address(&invoice.sender)),
+                format!("**To:**  \n{}", address(&invoice.recipient)),
+                details.join("  \n"),
+                table.join("\n"),
+                format!("**{}**", total),
+            ];
+            parts.extend(invoice.footer.clone());
+            parts.join("\n\n") + "\n"
+        },
+        InvoiceFormat::Html => {
+            let address = |text: &str| text.lines().map(escape_html).collect::<Vec<String>>().join("<br>\n");
+            let cells = |tag: &str, row: Vec<&str>| row.iter()
+                .map(|cell| format!("<{0}>{1}</{0}>", tag, escape_html(cell)))
+                .collect::<String>();
+            let mut html = vec![
+                "<!DOCTYPE html>".to_string(),
+                format!("<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>", escape_html(&title)),
+                format!("<h1>{}</h1>", escape_html(&title)),
+                format!("<p><strong>From:</strong><br>\n{}</p>", address(&invoice.sender)),
+                format!("<p><strong>To:</strong><br>\n{}</p>", address(&invoice.recipient)),
+                format!("<p>{}</p>", details.iter().map(|it| escape_html(it)).collect::<Vec<String>>().join("<br>\n")),
+                "<table>".to_string(),
+                format!("<tr>{}</tr>", cells("th", header.to_vec())),
+            ];
+            rows.iter().for_each(|row| html.push(format!("<tr>{}</tr>", cells("td", row.iter().map(String::as_str).collect()))));
+            html.push("</table>".to_string());
+            html.push(format!("<p><strong>{}</strong></p>", escape_html(&total)));
+            html.extend(invoice.footer.as_deref().map(|footer| format!("<p>{}</p>", address(footer))));
+            html.push("</body>\n</html>".to_string());
+            html.join("\n") + "\n"
+        },
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod invoice_test {
+    use std::collections::BTreeMap;
+
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity, Project};
+    use crate::billing::Billing;
+    use crate::config::InvoiceConfig;
+    use crate::invoice::{build, InvoiceFormat, mark_invoiced, render, write_file};
+
+    fn entry(day: u32, from: u32, to: u32, project: &str, description: &str) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, day),
+            from: NaiveTime::from_hms(from, 0, 0),
+            to: NaiveTime::from_hms(to, 20, 0),
+            description: description.to_string(),
+            project: Some(project.to_string()),
+            billable: true,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn projects() -> Vec<Project> {
+        vec![
+            Project { name: "Website".to_string(), client: Some("ACME".to_string()), rate: Some(100.0) },
+            Project { name: "Shop".to_string(), client: Some("ACME".to_string()), rate: None },
+            Project { name: "Other".to_string(), client: Some("Globex".to_string()), rate: Some(10.0) },
+        ]
+    }
+
+    fn invoice_config() -> InvoiceConfig {
+        InvoiceConfig {
+            sender: "Jane Doe\nMain Street 1".to_string(),
+            recipients: BTreeMap::from([("ACME".to_string(), "ACME Inc.\nHarbour 2".to_string())]),
+            number_prefix: "2022-".to_string(),
+            next_number: 7,
+            footer: Some("Payable within 30 days".to_string()),
+        }
+    }
+
+    fn entries() -> Vec<ClockEntry> {
+        let mut invoiced = entry(14, 8, 9, "Website", "Design");
+        invoiced.invoice = Some("2022-0006".to_string());
+        let mut unbillable = entry(14, 12, 13, "Website", "Design");
+        unbillable.billable = false;
+        vec![
+            entry(14, 9, 10, "Website", "Design"),
+            entry(15, 9, 10, "Website", "Design"),
+            entry(15, 10, 11, "Shop", "Checkout"),
+            entry(15, 11, 12, "Other", "Support"),
+            entry(20, 9, 10, "Website", "Design"),
+            invoiced,
+            unbillable,
+        ]
+    }
+
+    #[test]
+    fn groups_the_invoiceable_entries_of_the_client() {
+        let period = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 15);
+        let billing = Billing { default_rate: Some(50.0), ..Billing::default() };
+        let invoice = build(&entries(), &projects(), &billing, &invoice_config(), "ACME", period, Granularity::Detailed).unwrap();
+
+        assert_eq!(invoice.number, "2022-0007");
+        assert_eq!(invoice.recipient, "ACME Inc.\nHarbour 2");
+        assert_eq!(invoice.lines.len(), 2);
+        assert_eq!(invoice.lines[0].project, "Shop (ACME)");
+        assert_eq!(invoice.lines[0].seconds, 3600 + 15 * 60);
+        assert_eq!(invoice.lines[0].amount, 62.5);
+        assert_eq!(invoice.lines[1].project, "Website (ACME)");
+        assert_eq!(invoice.lines[1].seconds, 2 * 3600 + 30 * 60);
+        assert_eq!(invoice.lines[1].amount, 250.0);
+        assert_eq!(invoice.total(), 312.5);
+    }
+
+    #[test]
+    fn requires_a_rate_and_something_to_invoice() {
+        let period = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 15);
+        assert!(build(&entries(), &projects(), &Billing::default(), &invoice_config(), "ACME", period.clone(), Granularity::Detailed)
+            .unwrap_err().contains("No hourly rate for Shop (ACME)"));
+        assert!(build(&entries(), &projects(), &Billing::default(), &invoice_config(), "Initech", period, Granularity::Detailed)
+            .unwrap_err().contains("No billable entries of Initech"));
+    }
+
+    #[test]
+    fn marks_only_the_invoiced_entries() {
+        let period = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 15);
+        let mut entries = entries();
+        entries.iter_mut().for_each(|it| mark_invoiced(it, &projects(), "ACME", &period, "2022-0007"));
+        let numbers: Vec<Option<&str>> = entries.iter().map(|it| it.invoice.as_deref()).collect();
+        assert_eq!(numbers, vec![Some("2022-0007"), Some("2022-0007"), Some("2022-0007"), None, None, Some("2022-0006"), None]);
+    }
+
+    #[test]
+    fn renders_plain_text_markdown_and_html() {
+        let period = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 31);
+        let mut entries = entries();
+        entries[4].description = "Design <b>".to_string();
+        let mut invoice = build(&entries[4..5], &projects(), &Billing::default(), &invoice_config(), "ACME", period, Granularity::Relaxed).unwrap();
+        invoice.date = NaiveDate::from_ymd(2022, 3, 31);
+
+        assert_eq!(
+            render(&invoice, InvoiceFormat::Text, Granularity::Relaxed),
+            "Invoice 2022-0007
+=================
+
+From:
+Jane Doe
+Main Street 1
+
+To:
+ACME Inc.
+Harbour 2
+
+Date: 2022-03-31
+Period: 2022-03-01 - 2022-03-31
+
+Project         Description  Hours  Rate        Amount
+----------------------------------------------------------
+Website (ACME)  Design <b>   1h     100.00 EUR  100.00 EUR
+
+Total: 100.00 EUR
+
+Payable within 30 days
+"
+        );
+        assert!(render(&invoice, InvoiceFormat::Markdown, Granularity::Relaxed)
+            .contains("| Website (ACME) | Design <b> | 1h | 100.00 EUR | 100.00 EUR |\n\n**Total: 100.00 EUR**"));
+        assert!(render(&invoice, InvoiceFormat::Html, Granularity::Relaxed)
+            .contains("<tr><td>Website (ACME)</td><td>Design &lt;b&gt;</td><td>1h</td><td>100.00 EUR</td><td>100.00 EUR</td></tr>"));
+    }
+
+    #[test]
+    fn overwrites_an_invoice_file_only_when_asked_to() {
+        let file_name = std::env::temp_dir().join(format!("clockking-invoice-{}.txt", std::process::id()));
+        let file_name = file_name.to_str().unwrap();
+
+        write_file(file_name, "first", false).unwrap();
+        assert_eq!(write_file(file_name, "second", false).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(file_name).unwrap(), "first");
+        write_file(file_name, "second", true).unwrap();
+        assert_eq!(std::fs::read_to_string(file_name).unwrap(), "second");
+        std::fs::remove_file(file_name).unwrap();
+    }
+}
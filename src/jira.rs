@@ -0,0 +1,195 @@
+use std::ops::RangeInclusive;
+
+use chrono::{Duration, Local, NaiveDate, TimeZone};
+use serde_json::json;
+
+use crate::{ClockEntry, ClockKing};
+use crate::config::JiraConfig;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorklogResult {
+    pub description: String,
+    pub issue_key: Option<String>,
+    pub outcome: Result<(), String>,
+}
+
+/// Entries of the period not pushed yet. Entries left out of the totals, like timers that ran alongside, and entries
+/// without a duration aren't worked time.
+pub fn is_pending(entry: &ClockEntry, period: &RangeInclusive<NaiveDate>) -> bool {
+    !entry.is_clocked && !entry.excluded_from_totals && entry.duration() > Duration::zero() && period.contains(&entry.date)
+}
+
+pub fn push_worklogs(config: &JiraConfig, model: &mut ClockKing, period: &RangeInclusive<NaiveDate>) -> Vec<WorklogResult> {
+    model.clock_entries.iter_mut()
+        .filter(|it| is_pending(it, period))
+        .map(|entry| {
+            let issue_key = extract_issue_key(&entry.description);
+            let outcome = match &issue_key {
+                Some(key) => push_worklog(config, key, entry),
+                None => Err("No issue key found in the description".to_string()),
+            };
+            if outcome.is_ok() {
+                entry.set_clocked(true);
+            }
+            WorklogResult {
+                description: entry.description.clone(),
+                issue_key,
+                outcome,
+            }
+        })
+        .collect()
+}
+
+fn push_worklog(config: &JiraConfig, issue_key: &str, entry: &ClockEntry) -> Result<(), String> {
+    let url = format!("{}/rest/api/2/issue/{}/worklog", config.base_url.trim_end_matches('/'), issue_key);
+    let started = Local.from_local_datetime(&entry.date.and_time(entry.from))
+        .earliest()
+        .ok_or_else(|| "Unable to resolve the local start time".to_string())?;
+    let body = json!({
+        "started": started.format("%Y-%m-%dT%H:%M:%S.000%z").to_string(),
+        "timeSpentSeconds": entry.duration().num_seconds(),
+        "comment": entry.description,
+    });
+    ureq::post(&url)
+        .set("Authorization", &authorization(config))
+        .send_json(body)
+        .map(|_| ())
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => format!(
+                "Jira responded with {}: {}",
+                code,
+                response.into_string().unwrap_or_default()
+            ),
+            ureq::Error::Transport(transport) => transport.to_string(),
+        })
+}
+
+fn authorization(config: &JiraConfig) -> String {
+    match &config.email {
+        Some(email) => format!("Basic {}", base64::encode(format!("{}:{}", email, config.api_token))),
+        None => format!("Bearer {}", config.api_token),
+    }
+}
+
+/// Finds the first Jira-style issue key (e.g. `ABC-123`) in the description.
+pub fn extract_issue_key(description: &str) -> Option<String> {
+    description
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .flat_map(|word| {
+            let parts: Vec<&str> = word.split('-').collect();
+            parts.windows(2).map(|pair| (pair[0], pair[1])).collect::<Vec<(&str, &str)>>()
+        })
+        .find(|(project, number)| is_project_key(project) && is_issue_number(number))
+        .map(|(project, number)| format!("{}-{}", project, number))
+}
+
+fn is_project_key(value: &str) -> bool {
+    value.len() > 1
+        && value.starts_with(|c: char| c.is_ascii_uppercase())
+        && value.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+fn is_issue_number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod extract_issue_key_test {
+    use crate::jira::extract_issue_key;
+
+    #[test]
+    fn finds_key_anywhere_in_description() {
+        assert_eq!(extract_issue_key("ABC-123"), Some("ABC-123".to_string()));
+        assert_eq!(extract_issue_key("Reviewing ABC-123 with the team"), Some("ABC-123".to_string()));
+        assert_eq!(extract_issue_key("[PROJ2-7] fix"), Some("PROJ2-7".to_string()));
+        assert_eq!(extract_issue_key("ABC-123: first, XYZ-1 second"), Some("ABC-123".to_string()));
+    }
+
+    #[test]
+    fn ignores_things_that_are_not_keys() {
+        assert_eq!(extract_issue_key("Coffee"), None);
+        assert_eq!(extract_issue_key("abc-123"), None);
+        assert_eq!(extract_issue_key("A-1"), None);
+        assert_eq!(extract_issue_key("ABC-"), None);
+        assert_eq!(extract_issue_key("follow-up"), None);
+    }
+}
+
+#[cfg(test)]
+mod push_worklogs_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, ClockKing, Granularity};
+    use crate::config::JiraConfig;
+    use crate::jira::{is_pending, push_worklogs};
+    use crate::test_server::TestServer;
+
+    fn entry(description: &str, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(9, 0, 0),
+            to: NaiveTime::from_hms(10, 30, 0),
+            description: description.to_string(),
+            is_clocked,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    #[test]
+    fn pushes_unclocked_entries_and_marks_them_clocked() {
+        let server = TestServer::start(|request| {
+            if request.path.contains("FAIL-1") { (404, "Issue does not exist".to_string()) } else { (201, "{}".to_string()) }
+        });
+        let config = JiraConfig {
+            base_url: server.url(),
+            email: None,
+            api_token: "secret".to_string(),
+        };
+        let mut model = ClockKing {
+            clock_entries: vec![
+                entry("ABC-1 implementation", false),
+                entry("ABC-2 already clocked", true),
+                entry("FAIL-1 missing issue", false),
+                entry("Coffee", false),
+            ],
+            ..ClockKing::default()
+        };
+
+        let results = push_worklogs(&config, &mut model, &(NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 31)));
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].outcome.is_ok());
+        assert!(results[1].outcome.as_ref().unwrap_err().contains("404"));
+        assert_eq!(results[2].issue_key, None);
+        assert!(results[2].outcome.is_err());
+        assert_eq!(
+            model.clock_entries.iter().map(|it| it.is_clocked).collect::<Vec<bool>>(),
+            vec![true, true, false, false]
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/rest/api/2/issue/ABC-1/worklog");
+        assert_eq!(requests[0].header("authorization"), Some("Bearer secret".to_string()));
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["timeSpentSeconds"], 5400);
+        assert_eq!(body["comment"], "ABC-1 implementation");
+        assert!(body["started"].as_str().unwrap().starts_with("2022-03-14T09:00:00.000"));
+    }
+
+    #[test]
+    fn leaves_out_entries_that_are_not_worked_time() {
+        let march = NaiveDate::from_ymd(2022, 3, 1)..=NaiveDate::from_ymd(2022, 3, 31);
+        let timer = ClockEntry { excluded_from_totals: true, ..entry("ABC-1 deploy", false) };
+        let empty = ClockEntry { to: NaiveTime::from_hms(9, 0, 0), ..entry("ABC-1 started", false) };
+        let april = ClockEntry { date: NaiveDate::from_ymd(2022, 4, 1), ..entry("ABC-1 later", false) };
+
+        assert!(is_pending(&entry("ABC-1 implementation", false), &march));
+        assert!(!is_pending(&entry("ABC-1 implementation", true), &march));
+        assert!(!is_pending(&timer, &march));
+        assert!(!is_pending(&empty, &march));
+        assert!(!is_pending(&april, &march));
+    }
+}
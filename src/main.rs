@@ -4,6 +4,8 @@ use std::error::Error;
 
 use cursive::{Cursive, CursiveExt};
 use cursive::event::{Event, Key};
+use cursive::views::Dialog;
+use uuid::Uuid;
 
 use granularity_picker::Granularity;
 
@@ -17,17 +19,68 @@ mod format;
 mod time_picker;
 mod input;
 mod granularity_picker;
+mod project_picker;
 mod clock_entry_form;
 mod autosave;
+mod ticker;
 mod clock_entries_table;
 mod stats_view;
 mod main_dialog;
 mod record;
+mod breaks;
+mod timers;
+mod tags;
+mod templates;
+mod history;
+mod bulk;
+mod timeline;
+mod corrections;
+mod validation;
+mod quick_add;
+mod billing;
+mod targets;
+mod absences;
+mod filter;
+mod config;
+mod jira;
+mod webhook;
+mod report;
+mod invoice;
+mod backup;
+mod summary;
+mod cli;
+#[cfg(test)]
+mod test_server;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let open = match args.as_slice() {
+        [] => None,
+        [command, id] if command == "open" => match id.parse::<Uuid>() {
+            Ok(id) => Some(id),
+            Err(e) => {
+                eprintln!("Invalid entry ID {}: {}", id, e);
+                std::process::exit(1);
+            },
+        },
+        _ => {
+            if let Err(e) = cli::run(&args) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        },
+    };
+
     let mut siv = Cursive::default();
 
-    let initial_clock_king = db::init_from_db(&mut siv);
+    let (initial_clock_king, _lock) = match db::init_from_db(&mut siv) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        },
+    };
 
     siv.add_layer(
         main_dialog::new(initial_clock_king)
@@ -41,7 +94,13 @@ fn main() -> Result<(), Box<dyn Error>> {
     stats_view::update_stats(&mut siv);
 
     siv.focus_name(CLOCK_ENTRIES_TABLE)?;
-    autosave::start_autosave_loop(&siv);
+    if let Some(id) = open {
+        if !clock_entries_table::open_entry_by_id(&mut siv, id) {
+            siv.add_layer(Dialog::info(format!("No entry with ID {}", id)));
+        }
+    }
+    let _autosave = autosave::start_autosave_loop(&siv);
+    let _ticker = ticker::start_ticker_loop(&siv);
     siv.run();
     Ok(())
 }
@@ -1,14 +1,17 @@
 use cursive::Cursive;
 use cursive::direction::Orientation;
-use cursive::event::Key;
+use cursive::event::{Event, Key};
 use cursive::traits::{Nameable, Resizable};
-use cursive::views::{Button, Dialog, DummyView, LinearLayout, OnEventView};
+use cursive::views::{Button, Dialog, DummyView, LinearLayout, NamedView, OnEventView};
 
-use crate::{clock_entries_table, ClockKing, db, granularity_picker, record, stats_view};
+use crate::{absences, bulk, clock_entries_table, corrections, ClockKing, db, filter, granularity_picker, history, invoice, project_picker, quick_add, record, report, stats_view, summary, targets, templates, timeline, timers, webhook};
 
 pub const RECORD_BUTTON: &str = "RECORD_BUTTON";
+const MAIN_DIALOG: &str = "MAIN_DIALOG";
+const TITLE: &str = "Clock King 👑";
 
-pub fn new(initial_clock_king: ClockKing) -> Dialog {
+pub fn new(initial_clock_king: ClockKing) -> NamedView<Dialog> {
+    let record_label = if initial_clock_king.recording.is_some() { "Stop (r)ecording" } else { "Start (r)ecording" };
     Dialog::around(
         LinearLayout::new(Orientation::Vertical)
             .child(granularity_picker::new(initial_clock_king.granularity))
@@ -16,27 +19,70 @@ pub fn new(initial_clock_king: ClockKing) -> Dialog {
                 OnEventView::new(clock_entries_table::new(initial_clock_king))
                     .on_event(Key::Del, clock_entries_table::delete_current_entry)
                     .on_event('d', clock_entries_table::delete_current_entry)
-                    .on_event('u', clock_entries_table::undo_delete)
+                    .on_event('u', history::undo)
+                    .on_event(Event::CtrlChar('r'), history::redo)
+                    .on_event('h', history::show_history)
                     .on_event(' ', clock_entries_table::mark_current_entry_as_clocked)
+                    .on_event('b', clock_entries_table::mark_current_entry_as_billable)
+                    .on_event('v', bulk::toggle_mark)
+                    .on_event('V', bulk::toggle_all_marks)
+                    .on_pre_event(Event::Shift(Key::Up), |s| bulk::extend_marks(s, -1))
+                    .on_pre_event(Event::Shift(Key::Down), |s| bulk::extend_marks(s, 1))
+                    .on_event('m', bulk::bulk_actions)
                     .on_event('a', clock_entries_table::add_new_entry)
                     .on_event('r', record::record)
+                    .on_event('R', record::pause_or_resume)
+                    .on_event('n', record::switch_task)
+                    .on_event('w', timers::timers)
+                    .on_event('s', webhook::sync)
+                    .on_event('p', report::report)
+                    .on_event('o', summary::summary)
+                    .on_event('P', project_picker::manage_projects)
+                    .on_event('t', filter::filter_by_tag)
+                    .on_event('/', filter::search)
+                    .on_event('l', filter::edit_filter)
+                    .on_event('i', invoice::invoice)
+                    .on_event('T', targets::edit_targets)
+                    .on_event('A', absences::manage_absences)
+                    .on_event('f', timeline::fill_gap)
+                    .on_event('e', timeline::extend_previous)
+                    .on_event('x', timeline::trim_overlap)
+                    .on_event('S', corrections::split_entry)
+                    .on_event('M', corrections::merge_entries)
+                    .on_event('c', corrections::duplicate_entry)
+                    .on_event(':', quick_add::focus)
+                    .on_event('F', templates::templates)
             )
             .child(
-                stats_view::new()
+                quick_add::new()
+            )
+            .child(
+                LinearLayout::new(Orientation::Horizontal)
+                    .child(stats_view::new().full_width())
+                    .child(timers::new())
             )
             .child(
                 LinearLayout::new(Orientation::Horizontal)
                     .child(Button::new("(A)dd", clock_entries_table::add_new_entry))
                     .child(DummyView.fixed_width(20))
-                    .child(Button::new("Start (r)ecording", record::record).with_name(RECORD_BUTTON))
+                    .child(Button::new(record_label, record::record).with_name(RECORD_BUTTON))
                     .child(DummyView.fixed_width(20))
                     .child(Button::new("(D)elete", clock_entries_table::delete_current_entry))
                     .child(DummyView.fixed_width(20))
-                    .child(Button::new("(U)ndo Delete", clock_entries_table::undo_delete))
+                    .child(Button::new("(U)ndo", history::undo))
                     .child(DummyView.fixed_width(20))
                     .child(Button::new("(Q)uit", quit))
             )
-    ).title("Clock King 👑")
+    ).title(TITLE).with_name(MAIN_DIALOG)
+}
+
+pub fn set_title_suffix(s: &mut Cursive, suffix: Option<String>) {
+    s.call_on_name(MAIN_DIALOG, |d: &mut Dialog| {
+        d.set_title(match suffix {
+            Some(suffix) => format!("{} - {}", TITLE, suffix),
+            None => TITLE.to_string(),
+        })
+    });
 }
 
 pub fn quit(s: &mut Cursive) {
@@ -1,39 +1,89 @@
-use std::collections::VecDeque;
+use std::collections::HashSet;
 
-use chrono::{Duration, NaiveTime};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{Granularity, granularity_picker};
-
-const UNDO_BUFFER_SIZE: usize = 20;
+use crate::{breaks, Granularity, granularity_picker, time_picker};
+use crate::absences::Absence;
+use crate::breaks::Break;
+use crate::billing::Billing;
+use crate::targets::Targets;
+use crate::templates::Template;
+use crate::timers::Timer;
+use crate::filter::EntryFilter;
+use crate::history::{Command, History};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GlobalContext {
-    deleted: VecDeque<ClockEntry>,
+    history: History,
+    granularity: Granularity,
     last_saved: ClockKing,
     recording: Option<ClockEntry>,
+    breaks: Vec<Break>,
+    timers: Vec<Timer>,
+    projects: Vec<Project>,
+    billing: Billing,
+    targets: Option<Targets>,
+    absences: Vec<Absence>,
+    filter: EntryFilter,
+    hidden: Vec<ClockEntry>,
+    templates: Vec<Template>,
+    /// IDs of the entries marked for a bulk action
+    marks: HashSet<Uuid>,
 }
 
 impl GlobalContext {
     pub(crate) fn new(model: &ClockKing) -> GlobalContext {
         GlobalContext {
-            deleted: VecDeque::<ClockEntry>::default(),
+            history: History::default(),
+            granularity: model.granularity,
             last_saved: model.clone(),
-            recording: None,
+            recording: model.recording.clone(),
+            breaks: model.breaks.clone(),
+            timers: model.timers.clone(),
+            projects: model.projects.clone(),
+            billing: model.billing.clone(),
+            targets: model.targets.clone(),
+            absences: model.absences.clone(),
+            filter: EntryFilter::default(),
+            hidden: Vec::default(),
+            templates: model.templates.clone(),
+            marks: HashSet::default(),
         }
     }
 
-    pub(crate) fn delete(&mut self, clock_entry: Option<ClockEntry>) {
-        if let Some(it) = clock_entry {
-            if self.deleted.len() >= UNDO_BUFFER_SIZE {
-                self.deleted.pop_front();
-            }
-            self.deleted.push_back(it);
-        }
+    pub(crate) fn record(&mut self, command: Command) {
+        self.history.record(command);
+    }
+
+    pub(crate) fn undo(&mut self) -> Option<Command> {
+        self.history.undo()
+    }
+
+    pub(crate) fn redo(&mut self) -> Option<Command> {
+        self.history.redo()
+    }
+
+    pub(crate) fn forget_undone(&mut self) {
+        self.history.forget_undone();
     }
 
-    pub(crate) fn undo(&mut self) -> Option<ClockEntry> {
-        self.deleted.pop_back()
+    pub(crate) fn forget_redone(&mut self) {
+        self.history.forget_redone();
+    }
+
+    pub(crate) fn history(&self) -> History {
+        self.history.clone()
+    }
+
+    /// The granularity the entries are currently normalized to.
+    pub(crate) fn granularity(&self) -> Granularity {
+        self.granularity
+    }
+
+    pub(crate) fn set_granularity(&mut self, granularity: Granularity) {
+        self.granularity = granularity;
     }
 
     pub(crate) fn save(&mut self, clock_king: ClockKing) {
@@ -48,18 +98,57 @@ impl GlobalContext {
         self.recording = Some(new_entry);
     }
 
+    /// Stops the recording, its breaks are gone with it.
     pub(crate) fn stop_recording(&mut self) -> ClockEntry {
         let result = self.recording.clone().expect("Recording should be in progress");
         self.recording = None;
+        self.breaks.clear();
         result
     }
 
+    pub(crate) fn recording_breaks(&self) -> Vec<Break> {
+        self.breaks.clone()
+    }
+
+    pub(crate) fn set_recording_breaks(&mut self, breaks: Vec<Break>) {
+        self.breaks = breaks;
+    }
+
+    pub(crate) fn timers(&self) -> Vec<Timer> {
+        self.timers.clone()
+    }
+
+    pub(crate) fn set_timers(&mut self, timers: Vec<Timer>) {
+        self.timers = timers;
+    }
+
+    pub(crate) fn marks(&self) -> HashSet<Uuid> {
+        self.marks.clone()
+    }
+
+    pub(crate) fn set_marks(&mut self, marks: HashSet<Uuid>) {
+        self.marks = marks;
+    }
+
+    pub(crate) fn toggle_mark(&mut self, id: Uuid) {
+        if !self.marks.remove(&id) {
+            self.marks.insert(id);
+        }
+    }
+
+    pub(crate) fn mark(&mut self, id: Uuid) {
+        self.marks.insert(id);
+    }
+
+    /// Normalizes the recording with its breaks, and the running timers.
     pub(crate) fn normalize_recording(&mut self, granularity: Granularity) {
         if self.is_recording() {
             let mut value = self.recording.clone().unwrap();
             granularity_picker::normalize_for_granularity(&mut value, granularity);
             self.recording = Some(value);
+            breaks::normalize(&mut self.breaks, granularity);
         }
+        self.timers.iter_mut().for_each(|it| granularity_picker::normalize_for_granularity(&mut it.entry, granularity));
     }
 
     pub(crate) fn is_recording(&self) -> bool {
@@ -69,25 +158,217 @@ impl GlobalContext {
     pub(crate) fn ongoing_recording(&self) -> Option<ClockEntry> {
         self.recording.clone()
     }
+
+    pub(crate) fn projects(&self) -> Vec<Project> {
+        self.projects.clone()
+    }
+
+    pub(crate) fn set_projects(&mut self, projects: Vec<Project>) {
+        self.projects = projects;
+    }
+
+    pub(crate) fn billing(&self) -> Billing {
+        self.billing.clone()
+    }
+
+    pub(crate) fn set_billing(&mut self, billing: Billing) {
+        self.billing = billing;
+    }
+
+    pub(crate) fn targets(&self) -> Option<Targets> {
+        self.targets.clone()
+    }
+
+    pub(crate) fn set_targets(&mut self, targets: Option<Targets>) {
+        self.targets = targets;
+    }
+
+    pub(crate) fn absences(&self) -> Vec<Absence> {
+        self.absences.clone()
+    }
+
+    pub(crate) fn set_absences(&mut self, absences: Vec<Absence>) {
+        self.absences = absences;
+    }
+
+    pub(crate) fn templates(&self) -> Vec<Template> {
+        self.templates.clone()
+    }
+
+    pub(crate) fn set_templates(&mut self, templates: Vec<Template>) {
+        self.templates = templates;
+    }
+
+    pub(crate) fn filter(&self) -> EntryFilter {
+        self.filter.clone()
+    }
+
+    pub(crate) fn set_filter(&mut self, filter: EntryFilter, hidden: Vec<ClockEntry>) {
+        self.filter = filter;
+        self.hidden = hidden;
+    }
+
+    pub(crate) fn hidden_entries(&self) -> Vec<ClockEntry> {
+        self.hidden.clone()
+    }
+
+    pub(crate) fn hide(&mut self, entry: ClockEntry) {
+        self.hidden.push(entry);
+    }
+
+    pub(crate) fn update_hidden(&mut self, update: impl FnMut(&mut ClockEntry)) {
+        self.hidden.iter_mut().for_each(update);
+    }
+
+    pub(crate) fn remove_hidden(&mut self, id: Uuid) -> Option<ClockEntry> {
+        let index = self.hidden.iter().position(|it| it.id == id)?;
+        Some(self.hidden.remove(index))
+    }
+
+    pub(crate) fn normalize_hidden(&mut self, granularity: Granularity) {
+        self.hidden.iter_mut().for_each(|it| granularity_picker::normalize_for_granularity(it, granularity));
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ClockKing {
     pub clock_entries: Vec<ClockEntry>,
     pub granularity: Granularity,
+    #[serde(default)]
+    pub recording: Option<ClockEntry>,
+    /// Pauses of the ongoing recording
+    #[serde(default)]
+    pub breaks: Vec<Break>,
+    /// Named timers running next to the recording
+    #[serde(default)]
+    pub timers: Vec<Timer>,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    #[serde(default)]
+    pub billing: Billing,
+    #[serde(default)]
+    pub targets: Option<Targets>,
+    #[serde(default)]
+    pub absences: Vec<Absence>,
+    #[serde(default)]
+    pub templates: Vec<Template>,
+}
+
+impl Default for ClockKing {
+    fn default() -> Self {
+        ClockKing {
+            clock_entries: Vec::default(),
+            granularity: Granularity::Detailed,
+            recording: None,
+            breaks: Vec::default(),
+            timers: Vec::default(),
+            projects: Vec::default(),
+            billing: Billing::default(),
+            targets: None,
+            absences: Vec::default(),
+            templates: Vec::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Project {
+    pub name: String,
+    #[serde(default)]
+    pub client: Option<String>,
+    /// Hourly rate, in the currency of the billing settings
+    #[serde(default)]
+    pub rate: Option<f64>,
+}
+
+impl Project {
+    pub fn label(&self) -> String {
+        match &self.client {
+            Some(client) => format!("{} ({})", self.name, client),
+            None => self.name.clone(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ClockEntry {
+    /// Entries written before IDs existed get one when they're loaded
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    /// Unknown for entries written before the timestamps existed
+    #[serde(default)]
+    pub created_at: Option<NaiveDateTime>,
+    #[serde(default = "now")]
+    pub updated_at: NaiveDateTime,
+    pub date: NaiveDate,
     pub from: NaiveTime,
     pub to: NaiveTime,
     pub description: String,
     pub is_clocked: bool,
     pub granularity: Granularity,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub billable: bool,
+    /// Number of the invoice that billed this entry
+    #[serde(default)]
+    pub invoice: Option<String>,
+    /// Left out of the totals and the overlap checks, like a timer that ran next to the recording
+    #[serde(default)]
+    pub excluded_from_totals: bool,
+}
+
+impl Default for ClockEntry {
+    fn default() -> Self {
+        ClockEntry {
+            id: Uuid::new_v4(),
+            created_at: Some(now()),
+            updated_at: now(),
+            date: time_picker::today(),
+            from: NaiveTime::from_hms(0, 0, 0),
+            to: NaiveTime::from_hms(0, 0, 0),
+            description: String::default(),
+            is_clocked: false,
+            granularity: Granularity::Detailed,
+            project: None,
+            tags: Vec::default(),
+            billable: false,
+            invoice: None,
+            excluded_from_totals: false,
+        }
+    }
 }
 
 impl ClockEntry {
     pub fn duration(&self) -> Duration {
         self.to.signed_duration_since(self.from)
     }
+
+    pub fn set_clocked(&mut self, is_clocked: bool) {
+        self.is_clocked = is_clocked;
+        self.touch();
+    }
+
+    pub fn touch(&mut self) {
+        self.updated_at = now();
+    }
+
+    /// A new entry with the same times, description, project and tags, neither clocked nor invoiced yet.
+    pub fn copy(&self) -> ClockEntry {
+        let new = ClockEntry::default();
+        ClockEntry {
+            id: new.id,
+            created_at: new.created_at,
+            updated_at: new.updated_at,
+            is_clocked: false,
+            invoice: None,
+            ..self.clone()
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, LinearLayout, NamedView, SelectView, TextView};
+
+use crate::{app_context, billing, Project, stats_view};
+use crate::billing::Billing;
+use crate::clock_entries_table::ClockEntryColumn;
+
+const NO_PROJECT: &str = "(none)";
+const PROJECTS_LIST: &str = "projects_list";
+const PROJECT_NAME: &str = "project_name";
+const PROJECT_CLIENT: &str = "project_client";
+const PROJECT_RATE: &str = "project_rate";
+const DEFAULT_RATE: &str = "default_rate";
+const CURRENCY: &str = "currency";
+
+pub fn project_picker_input(value: Option<String>, projects: &[Project]) -> NamedView<SelectView<Option<String>>> {
+    let mut view = SelectView::new().popup();
+    view.add_item(NO_PROJECT, None);
+    projects.iter().for_each(|project| view.add_item(project.label(), Some(project.name.clone())));
+    // An entry can still refer to a project that was since removed from the catalogue
+    if let Some(name) = value.as_ref().filter(|name| !projects.iter().any(|it| it.name == **name)) {
+        view.add_item(name.clone(), Some(name.clone()));
+    }
+    let selected = view.iter().position(|(_, it)| *it == value).unwrap_or(0);
+    view.selected(selected).with_name(ClockEntryColumn::Project.as_str())
+}
+
+pub fn project_picker_value(s: &mut Cursive) -> Option<String> {
+    s.call_on_name(ClockEntryColumn::Project.as_str(), |e: &mut SelectView<Option<String>>| {
+        e.selection().and_then(|it| (*it).clone())
+    }).unwrap_or_else(|| panic!("{} should be defined", ClockEntryColumn::Project.as_str()))
+}
+
+pub fn manage_projects(s: &mut Cursive) {
+    let projects = app_context::fetch(s).projects();
+    let billing = app_context::fetch(s).billing();
+    let mut list = SelectView::new();
+    projects.iter().for_each(|project| list.add_item(rate_label(project, &billing), project.name.clone()));
+    s.add_layer(
+        Dialog::around(list.with_name(PROJECTS_LIST).min_size((40, 5)))
+            .title("Projects")
+            .button("Add", add_project)
+            .button("Remove", remove_project)
+            .button("Billing", edit_billing)
+            .button("Close", |s| { s.pop_layer(); })
+    );
+}
+
+fn add_project(s: &mut Cursive) {
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new("Name"))
+                .child(EditView::new().with_name(PROJECT_NAME).min_width(30))
+                .child(TextView::new("Client (optional)"))
+                .child(EditView::new().with_name(PROJECT_CLIENT).min_width(30))
+                .child(TextView::new("Hourly rate (optional)"))
+                .child(EditView::new().with_name(PROJECT_RATE).min_width(30))
+        )
+            .title("Add project")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", submit_project)
+    );
+}
+
+fn submit_project(s: &mut Cursive) {
+    let name = edit_view_value(s, PROJECT_NAME);
+    let client = Some(edit_view_value(s, PROJECT_CLIENT)).filter(|it| !it.is_empty());
+    if name.is_empty() {
+        return;
+    }
+    let rate = match parse_rate(&edit_view_value(s, PROJECT_RATE)) {
+        Ok(rate) => rate,
+        Err(e) => {
+            s.add_layer(Dialog::info(e));
+            return;
+        },
+    };
+    let mut projects = app_context::fetch(s).projects();
+    projects.retain(|it| it.name != name);
+    projects.push(Project { name, client, rate });
+    app_context::fetch(s).set_projects(projects);
+    s.pop_layer();
+    s.pop_layer();
+    manage_projects(s);
+    stats_view::update_stats(s);
+}
+
+fn remove_project(s: &mut Cursive) {
+    let selected = s.call_on_name(PROJECTS_LIST, |l: &mut SelectView<String>| l.selection())
+        .expect("The projects list should be defined");
+    if let Some(name) = selected {
+        let mut projects = app_context::fetch(s).projects();
+        projects.retain(|it| it.name != *name);
+        app_context::fetch(s).set_projects(projects);
+        s.pop_layer();
+        manage_projects(s);
+        stats_view::update_stats(s);
+    }
+}
+
+fn edit_billing(s: &mut Cursive) {
+    let billing = app_context::fetch(s).billing();
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(TextView::new("Default hourly rate (optional)"))
+                .child(EditView::new().content(billing.default_rate.map(|it| it.to_string()).unwrap_or_default()).with_name(DEFAULT_RATE).min_width(30))
+                .child(TextView::new("Currency"))
+                .child(EditView::new().content(billing.currency).with_name(CURRENCY).min_width(30))
+        )
+            .title("Billing")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", submit_billing)
+    );
+}
+
+fn submit_billing(s: &mut Cursive) {
+    let default_rate = match parse_rate(&edit_view_value(s, DEFAULT_RATE)) {
+        Ok(rate) => rate,
+        Err(e) => {
+            s.add_layer(Dialog::info(e));
+            return;
+        },
+    };
+    let currency = Some(edit_view_value(s, CURRENCY)).filter(|it| !it.is_empty()).unwrap_or_else(|| Billing::default().currency);
+    app_context::fetch(s).set_billing(Billing { default_rate, currency });
+    s.pop_layer();
+    s.pop_layer();
+    manage_projects(s);
+    stats_view::update_stats(s);
+}
+
+fn rate_label(project: &Project, billing: &Billing) -> String {
+    match project.rate {
+        Some(rate) => format!("{} - {}/h", project.label(), billing::format_money(rate, &billing.currency)),
+        None => project.label(),
+    }
+}
+
+/// An empty rate is no rate, anything else has to be a non-negative number.
+pub fn parse_rate(value: &str) -> Result<Option<f64>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    value.trim().replace(',', ".").parse::<f64>().ok()
+        .filter(|it| it.is_finite() && *it >= 0.0)
+        .map(Some)
+        .ok_or_else(|| format!("Invalid hourly rate: {}", value))
+}
+
+fn edit_view_value(s: &mut Cursive, name: &str) -> String {
+    s.call_on_name(name, |e: &mut EditView| e.get_content().trim().to_string())
+        .unwrap_or_else(|| panic!("{} should be defined", name))
+}
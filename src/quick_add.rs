@@ -0,0 +1,159 @@
+use chrono::{NaiveDate, NaiveTime};
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{EditView, LinearLayout, TextView};
+
+use crate::{app_context, CLOCK_ENTRIES_TABLE, clock_entries_table, clock_entry_form, ClockEntry, Granularity, granularity_picker, Project, stats_view, tags, time_picker, validation};
+use crate::history::Command;
+use crate::validation::Scope;
+
+pub const QUICK_ADD: &str = "quick_add";
+const QUICK_ADD_ISSUES: &str = "quick_add_issues";
+
+/// Parses a one-line entry like `9:00-10:30 standup #meeting @projectX`, see the "Quick add" section of the README.
+/// The line can start with a date, otherwise the entry is for `today`. `#tags` stay in the description, like in the
+/// entry form, and `@project` has to be in the catalogue.
+pub fn parse(line: &str, projects: &[Project], granularity: Granularity, today: NaiveDate, now: NaiveTime) -> Result<ClockEntry, String> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let date = match words.first().and_then(|it| time_picker::parse_date(it)) {
+        Some(date) => {
+            words.remove(0);
+            date
+        },
+        None => today,
+    };
+    let (from, to) = match words.first() {
+        Some(range) => parse_range(range, granularity, now).ok_or(format!("Invalid time range: {}", range))?,
+        None => return Err("Start with a time range, like 9:00-10:30".to_string()),
+    };
+    let (description, project) = parse_description(&words[1..], projects)?;
+    Ok(ClockEntry {
+        date,
+        from,
+        to,
+        tags: tags::parse_inline_tags(&description),
+        description,
+        granularity,
+        project,
+        ..ClockEntry::default()
+    })
+}
+
+/// Parses the description of an entry and its `@project`, which has to be in the catalogue.
+pub fn parse_description(words: &[&str], projects: &[Project]) -> Result<(String, Option<String>), String> {
+    let mut project = None;
+    let mut description = Vec::new();
+    for word in words {
+        match word.strip_prefix('@') {
+            Some(name) if project.is_some() => return Err(format!("More than one project: @{}", name)),
+            Some(name) => project = Some(
+                projects.iter()
+                    .find(|it| it.name.eq_ignore_ascii_case(name))
+                    .map(|it| it.name.clone())
+                    .ok_or(format!("Unknown project: {}", name))?
+            ),
+            None => description.push(*word),
+        }
+    }
+    Ok((description.join(" "), project))
+}
+
+/// Splits `from-to` at the dash that gives two valid times, as times like `now-10m` have a dash too.
+fn parse_range(range: &str, granularity: Granularity, now: NaiveTime) -> Option<(NaiveTime, NaiveTime)> {
+    range.match_indices('-').find_map(|(i, _)| {
+        let from = time_picker::parse_time_input(&range[..i], granularity, now, now)?;
+        let to = time_picker::parse_time_input(&range[i + 1..], granularity, from, now)?;
+        Some((from, to))
+    })
+}
+
+pub fn new() -> LinearLayout {
+    LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Quick add (:)").min_width(20))
+                .child(EditView::new().on_submit(submit).with_name(QUICK_ADD).full_width())
+        )
+        .child(TextView::new("").with_name(QUICK_ADD_ISSUES))
+}
+
+pub fn focus(s: &mut Cursive) {
+    s.focus_name(QUICK_ADD).expect("Quick add should be defined");
+}
+
+/// Adds the typed entry, or shows why it can't.
+fn submit(s: &mut Cursive, line: &str) {
+    let granularity = granularity_picker::get_granularity(s);
+    let projects = app_context::fetch(s).projects();
+    let entry = parse(line, &projects, granularity, time_picker::today(), time_picker::now_naive_time(Granularity::Scientific));
+    let issues = match &entry {
+        Ok(entry) => {
+            let rules = match clock_entry_form::validation_rules(s) {
+                Some(rules) => rules,
+                None => return,
+            };
+            validation::validate(entry, &clock_entries_table::get_clock_entries(s), &rules, Scope::Entry)
+        },
+        Err(e) => vec![validation::Issue { severity: validation::Severity::Error, message: e.clone() }],
+    };
+    if !clock_entry_form::show_issues(s, &issues, QUICK_ADD_ISSUES) {
+        return;
+    }
+    let entry = entry.expect("An entry without errors should be parsed");
+    clock_entries_table::insert_entry(s, entry.clone());
+    app_context::fetch(s).record(Command::Add(entry));
+    s.call_on_name(QUICK_ADD, |e: &mut EditView| e.set_content(""));
+    s.call_on_name(QUICK_ADD_ISSUES, |t: &mut TextView| t.set_content(""));
+    stats_view::update_stats(s);
+    s.focus_name(CLOCK_ENTRIES_TABLE).expect("Clock entries table should be defined");
+}
+
+#[cfg(test)]
+mod quick_add_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{Granularity, Project};
+    use crate::quick_add::parse;
+
+    fn projects() -> Vec<Project> {
+        vec![Project { name: "ProjectX".to_string(), client: None, rate: None }]
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd(2022, 3, 14)
+    }
+
+    fn now() -> NaiveTime {
+        NaiveTime::from_hms(16, 20, 0)
+    }
+
+    #[test]
+    fn parses_times_description_tags_and_project() {
+        let entry = parse("9:00-10:30 standup #meeting @projectx", &projects(), Granularity::Detailed, today(), now()).unwrap();
+        assert_eq!(entry.date, today());
+        assert_eq!((entry.from, entry.to), (NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(10, 30, 0)));
+        assert_eq!(entry.description, "standup #meeting");
+        assert_eq!(entry.tags, vec!["meeting"]);
+        assert_eq!(entry.project, Some("ProjectX".to_string()));
+        assert_eq!(entry.granularity, Granularity::Detailed);
+    }
+
+    #[test]
+    fn parses_dates_and_relative_times() {
+        let entry = parse("2022-03-11 now-45m-now  Review", &projects(), Granularity::Detailed, today(), now()).unwrap();
+        assert_eq!(entry.date, NaiveDate::from_ymd(2022, 3, 11));
+        assert_eq!((entry.from, entry.to), (NaiveTime::from_hms(15, 30, 0), NaiveTime::from_hms(16, 15, 0)));
+        assert_eq!(entry.description, "Review");
+
+        let entry = parse("930-+1h Coding", &projects(), Granularity::Detailed, today(), now()).unwrap();
+        assert_eq!((entry.from, entry.to), (NaiveTime::from_hms(9, 30, 0), NaiveTime::from_hms(10, 30, 0)));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_parse() {
+        assert!(parse("", &projects(), Granularity::Detailed, today(), now()).unwrap_err().contains("time range"));
+        assert!(parse("standup 9-10", &projects(), Granularity::Detailed, today(), now()).unwrap_err().contains("Invalid time range: standup"));
+        assert!(parse("9-10 standup @website", &projects(), Granularity::Detailed, today(), now()).unwrap_err().contains("Unknown project: website"));
+        assert!(parse("9-10 @projectx @projectx", &projects(), Granularity::Detailed, today(), now()).is_err());
+    }
+}
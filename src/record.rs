@@ -1,10 +1,18 @@
-use cursive::views::Button;
-use cursive_table_view::TableView;
+use chrono::{NaiveDate, NaiveTime};
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Button, Dialog, EditView, LinearLayout, SelectView, TextView};
 
-use crate::{app_context, CLOCK_ENTRIES_TABLE, clock_entry_form, ClockEntry, Cursive, granularity_picker, input, stats_view, time_picker};
-use crate::clock_entries_table::ClockEntryColumn;
+use crate::{app_context, breaks, clock_entries_table, clock_entry_form, ClockEntry, Cursive, format, Granularity, granularity_picker, Project, quick_add, stats_view, tags, time_picker, validation};
+use crate::breaks::{Break, StopMode};
+use crate::config::ValidationConfig;
+use crate::history::Command;
+use crate::templates::Template;
+use crate::validation::{Issue, Scope};
 use crate::main_dialog::RECORD_BUTTON;
 
+const NEXT_TASK: &str = "next_task";
+const SWITCH_ISSUES: &str = "switch_issues";
+
 pub fn record(s: &mut Cursive) {
     if app_context::fetch(s).is_recording() {
         stop_recording(s)
@@ -16,67 +24,254 @@ pub fn record(s: &mut Cursive) {
 fn start_recording(s: &mut Cursive) {
     let granularity = granularity_picker::get_granularity(s);
     let new_entry = ClockEntry {
+        date: time_picker::today(),
         from: time_picker::now_naive_time(granularity),
         to: time_picker::now_naive_time(granularity),
         description: String::from(""),
         is_clocked: false,
-        granularity
+        granularity,
+        ..ClockEntry::default()
     };
-    s.add_layer(
-        clock_entry_form::new(
-            "Start recording",
-            Some(&new_entry),
-            granularity,
-            submit_recording_entry
-        )
-    );
+    let form = clock_entry_form::new(s, "Start recording", Some(&new_entry), Scope::RecordingStart, submit_recording_entry);
+    s.add_layer(form);
 }
 
-fn submit_recording_entry(s: &mut Cursive) {
-    let new_entry = ClockEntry {
-        from: time_picker::time_picker_value(s, ClockEntryColumn::From),
-        to: time_picker::time_picker_value(s, ClockEntryColumn::To),
-        description: input::text_area_value(s, ClockEntryColumn::Description),
-        is_clocked: input::checkbox_value(s, ClockEntryColumn::IsClocked),
-        granularity: granularity_picker::get_granularity(s),    
-    };
-    app_context::fetch(s).start_recording(new_entry);
-    s.pop_layer();
+/// Starts recording right away, with the description, project and tags of the template.
+pub fn start_recording_from(s: &mut Cursive, template: &Template) {
+    if app_context::fetch(s).is_recording() {
+        s.add_layer(Dialog::info("Stop the ongoing recording first"));
+        return;
+    }
+    let granularity = granularity_picker::get_granularity(s);
+    let now = time_picker::now_naive_time(granularity);
+    let recording = ClockEntry { to: now, ..template.entry(time_picker::today(), now, granularity) };
+    app_context::fetch(s).start_recording(recording);
+    set_record_button(s, true);
+    stats_view::update_stats(s);
+}
+
+pub fn set_record_button(s: &mut Cursive, is_recording: bool) {
     s.call_on_name(RECORD_BUTTON, |b: &mut Button |{
-        b.set_label("Stop (r)ecording")
+        b.set_label(if is_recording { "Stop (r)ecording" } else { "Start (r)ecording" })
     });
+}
+
+fn submit_recording_entry(s: &mut Cursive, new_entry: ClockEntry) {
+    app_context::fetch(s).start_recording(new_entry);
+    s.pop_layer();
+    set_record_button(s, true);
     stats_view::update_stats(s);
 }
 
+/// Pauses the ongoing recording, or resumes it when it's paused.
+pub fn pause_or_resume(s: &mut Cursive) {
+    if !app_context::fetch(s).is_recording() {
+        s.add_layer(Dialog::info("There's no recording to pause"));
+        return;
+    }
+    let now = time_picker::now_naive_time(granularity_picker::get_granularity(s));
+    let mut breaks = app_context::fetch(s).recording_breaks();
+    if !breaks::resume(&mut breaks, now) {
+        breaks::pause(&mut breaks, now);
+    }
+    app_context::fetch(s).set_recording_breaks(breaks);
+    stats_view::update_stats(s);
+}
+
+/// Stops the recording, asking first whether to leave out its breaks or split it around them.
 fn stop_recording(s: &mut Cursive) {
+    let recording = app_context::fetch(s).ongoing_recording().expect("Recording should be in progress");
+    let breaks = app_context::fetch(s).recording_breaks();
+    if breaks.is_empty() {
+        open_stop_form(s, recording, breaks, StopMode::WithoutBreaks);
+        return;
+    }
     let granularity = granularity_picker::get_granularity(s);
-    let mut new_entry = app_context::fetch(s).stop_recording();
-    new_entry.to = time_picker::now_naive_time(granularity);
+    let stopped = ClockEntry { to: time_picker::now_naive_time(granularity), ..recording.clone() };
+    let paused = breaks::total(&breaks, stopped.to);
+    let shortened = breaks::without_breaks(&stopped, &breaks).duration();
+    let mut modes = SelectView::new();
+    modes.add_item(
+        format!("One entry of {} (breaks: {})", format::format_hms(granularity, shortened.num_seconds()), format::format_hms(granularity, paused.num_seconds())),
+        StopMode::WithoutBreaks
+    );
+    modes.add_item(format!("Split around the breaks ({} entries)", breaks::split_around(&stopped, &breaks).len()), StopMode::SplitAroundBreaks);
+    modes.set_on_submit(move |s, mode: &StopMode| {
+        s.pop_layer();
+        open_stop_form(s, recording.clone(), breaks.clone(), *mode);
+    });
     s.add_layer(
-        clock_entry_form::new(
-            "Stop recording",
-            Some(&new_entry),
-            granularity,
-            add_recording_entry
-        )
+        Dialog::around(modes)
+            .title(format!("Stop recording '{}'", stopped.description))
+            .button("Cancel", |s| { s.pop_layer(); })
     );
 }
 
-fn add_recording_entry(s: &mut Cursive) {
-    let new_entry = ClockEntry {
-        from: time_picker::time_picker_value(s, ClockEntryColumn::From),
-        to: time_picker::time_picker_value(s, ClockEntryColumn::To),
-        description: input::text_area_value(s, ClockEntryColumn::Description),
-        is_clocked: input::checkbox_value(s, ClockEntryColumn::IsClocked) ,
-        granularity: granularity_picker::get_granularity(s),
+fn open_stop_form(s: &mut Cursive, recording: ClockEntry, breaks: Vec<Break>, mode: StopMode) {
+    let granularity = granularity_picker::get_granularity(s);
+    let stopped = ClockEntry { to: time_picker::now_naive_time(granularity), ..recording.clone() };
+    let new_entry = match mode {
+        StopMode::WithoutBreaks => breaks::without_breaks(&stopped, &breaks),
+        StopMode::SplitAroundBreaks => stopped,
     };
-    s.call_on_name(CLOCK_ENTRIES_TABLE, |table: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        table.insert_item(new_entry);
-    }).expect("Unable to get clock entries table");
+    let form = clock_entry_form::new(s, "Stop recording", Some(&new_entry), Scope::Entry, move |s, new_entry| add_recording_entry(s, new_entry, &recording, &breaks, mode));
+    s.add_layer(form);
+}
+
+/// Adds the stopped recording as entries, undoing it resumes the recording with its breaks.
+fn add_recording_entry(s: &mut Cursive, new_entry: ClockEntry, recording: &ClockEntry, breaks: &[Break], mode: StopMode) {
+    let entries = match mode {
+        StopMode::WithoutBreaks => vec![new_entry],
+        StopMode::SplitAroundBreaks => breaks::split_around(&new_entry, breaks),
+    };
+    end_recording(s, &entries);
+    app_context::fetch(s).record(Command::StopRecording { recording: recording.clone(), breaks: breaks.to_vec(), entries });
+    s.pop_layer();
+    set_record_button(s, false);
     stats_view::update_stats(s);
+}
+
+/// Stops the ongoing recording and adds the entries it was stopped as to the table.
+fn end_recording(s: &mut Cursive, entries: &[ClockEntry]) {
+    app_context::fetch(s).stop_recording();
+    entries.iter().for_each(|it| clock_entries_table::insert_entry(s, it.clone()));
+}
+
+/// The next recording from a line like `code review #dev @website`, starting at `at`.
+pub fn parse_next_task(line: &str, projects: &[Project], granularity: Granularity, today: NaiveDate, at: NaiveTime) -> Result<ClockEntry, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (description, project) = quick_add::parse_description(&words, projects)?;
+    Ok(ClockEntry {
+        date: today,
+        from: at,
+        to: at,
+        tags: tags::parse_inline_tags(&description),
+        description,
+        project,
+        granularity,
+        ..ClockEntry::default()
+    })
+}
+
+/// The entries of the recording ended at `at`, split around its breaks. Nothing is left when it ends where it started.
+pub fn switched_entries(recording: &ClockEntry, breaks: &[Break], at: NaiveTime) -> Vec<ClockEntry> {
+    let mut stopped = ClockEntry { to: at.max(recording.from), ..recording.clone() };
+    stopped.touch();
+    breaks::split_around(&stopped, breaks)
+}
+
+/// The issues of the entries the recording ends as and of the next recording, like the stop and start forms check.
+/// `entries` are the other entries.
+pub fn switch_issues(ended: &[ClockEntry], next: &ClockEntry, entries: &[ClockEntry], rules: &ValidationConfig) -> Vec<Issue> {
+    let ended_issues = ended.iter()
+        .flat_map(|it| validation::validate(it, entries, rules, Scope::Entry))
+        .map(|it| Issue { message: format!("Ended recording: {}", it.message), ..it });
+    let next_issues = validation::validate(next, entries, rules, Scope::RecordingStart).into_iter()
+        .map(|it| Issue { message: format!("Next task: {}", it.message), ..it });
+    ended_issues.chain(next_issues).collect()
+}
+
+/// Ends the ongoing recording now and starts recording the next task, without the stop and start forms.
+pub fn switch_task(s: &mut Cursive) {
+    let description = match app_context::fetch(s).ongoing_recording() {
+        Some(recording) => recording.description,
+        None => {
+            s.add_layer(Dialog::info("There's no recording to switch from"));
+            return;
+        },
+    };
+    s.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+                .child(EditView::new().on_submit(submit_switch).with_name(NEXT_TASK).min_width(40))
+                .child(TextView::new("").with_name(SWITCH_ISSUES))
+        )
+            .title(format!("Switch from '{}' to", description))
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Ok", |s| {
+                let line = s.call_on_name(NEXT_TASK, |e: &mut EditView| e.get_content().to_string()).expect("Next task should be defined");
+                submit_switch(s, &line);
+            })
+    );
+}
+
+/// Switches to the typed task, or shows why it can't.
+fn submit_switch(s: &mut Cursive, line: &str) {
+    let granularity = granularity_picker::get_granularity(s);
+    let at = time_picker::now_naive_time(granularity);
+    let projects = app_context::fetch(s).projects();
+    let next = match parse_next_task(line, &projects, granularity, time_picker::today(), at) {
+        Ok(next) => next,
+        Err(e) => {
+            s.add_layer(Dialog::info(e));
+            return;
+        },
+    };
+    let rules = match clock_entry_form::validation_rules(s) {
+        Some(rules) => rules,
+        None => return,
+    };
+    let recording = app_context::fetch(s).ongoing_recording().expect("Recording should be in progress");
+    let breaks = app_context::fetch(s).recording_breaks();
+    let entries = switched_entries(&recording, &breaks, at);
+    let issues = switch_issues(&entries, &next, &clock_entries_table::get_clock_entries(s), &rules);
+    if !clock_entry_form::show_issues(s, &issues, SWITCH_ISSUES) {
+        return;
+    }
     s.pop_layer();
-    s.call_on_name(RECORD_BUTTON, |b: &mut Button |{
-        b.set_label("Start (r)ecording")
-    });
+    end_recording(s, &entries);
+    app_context::fetch(s).start_recording(next.clone());
+    app_context::fetch(s).record(Command::SwitchRecording { recording, breaks, entries, next });
     stats_view::update_stats(s);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod record_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity, Project};
+    use crate::breaks::Break;
+    use crate::config::ValidationConfig;
+    use crate::record::{parse_next_task, switch_issues, switched_entries};
+
+    fn time(hours: u32, minutes: u32) -> NaiveTime {
+        NaiveTime::from_hms(hours, minutes, 0)
+    }
+
+    #[test]
+    fn parses_the_next_task() {
+        let projects = vec![Project { name: "Website".to_string(), client: None, rate: None }];
+        let today = NaiveDate::from_ymd(2022, 3, 14);
+
+        let next = parse_next_task("code review #dev @website", &projects, Granularity::Detailed, today, time(10, 15)).unwrap();
+
+        assert_eq!((next.date, next.from, next.to), (today, time(10, 15), time(10, 15)));
+        assert_eq!(next.description, "code review #dev");
+        assert_eq!(next.tags, vec!["dev".to_string()]);
+        assert_eq!(next.project, Some("Website".to_string()));
+        assert!(parse_next_task("deploy @unknown", &projects, Granularity::Detailed, today, time(10, 15)).is_err());
+    }
+
+    #[test]
+    fn ends_the_recording_around_its_breaks() {
+        let recording = ClockEntry { from: time(9, 0), to: time(9, 0), description: "Coding".to_string(), ..ClockEntry::default() };
+        let breaks = vec![Break { from: time(12, 0), to: Some(time(13, 0)) }];
+
+        let entries = switched_entries(&recording, &breaks, time(14, 30));
+
+        assert_eq!(entries.iter().map(|it| (it.from, it.to)).collect::<Vec<_>>(), vec![(time(9, 0), time(12, 0)), (time(13, 0), time(14, 30))]);
+        assert_eq!(entries[0].id, recording.id);
+        assert_eq!(switched_entries(&recording, &[], time(9, 0)), vec![]);
+    }
+
+    #[test]
+    fn checks_the_ended_entries_and_the_next_recording() {
+        let rules = ValidationConfig { require_description: true, max_duration_hours: Some(4.0), ..ValidationConfig::default() };
+        let ended = vec![ClockEntry { from: time(8, 0), to: time(13, 0), description: "Coding".to_string(), ..ClockEntry::default() }];
+        let next = ClockEntry { from: time(13, 0), to: time(13, 0), ..ClockEntry::default() };
+
+        let messages: Vec<String> = switch_issues(&ended, &next, &[], &rules).into_iter().map(|it| it.message).collect();
+
+        assert_eq!(messages, vec!["Ended recording: The entry is longer than 4 hours", "Next task: The description is required"]);
+    }
+}
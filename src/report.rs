@@ -0,0 +1,472 @@
+use std::fs;
+use std::ops::RangeInclusive;
+
+use chrono::NaiveDate;
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable, Scrollable};
+use cursive::views::{Dialog, EditView, TextView};
+use cursive_table_view::TableView;
+
+use crate::{app_context, billing, CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, format, Granularity, granularity_picker, Project, stats_view, targets, time_picker};
+use crate::absences::Absence;
+use crate::billing::{BILLABLE_AMOUNT, Billing};
+use crate::targets::{FLEX_BALANCE, Targets};
+use crate::clock_entries_table::{ClockEntryColumn, Row};
+use crate::stats_view::{TOTAL_HOURS, TOTAL_HOURS_CLOCKED, TOTAL_HOURS_REMAINING};
+
+const REPORT_FILE_NAME: &str = "report_file_name";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Org,
+}
+
+impl ReportFormat {
+    pub fn parse(value: &str) -> Option<ReportFormat> {
+        match value {
+            "markdown" | "md" => Some(ReportFormat::Markdown),
+            "org" => Some(ReportFormat::Org),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Org => "org",
+        }
+    }
+
+    fn toggle(&self) -> ReportFormat {
+        match self {
+            ReportFormat::Markdown => ReportFormat::Org,
+            ReportFormat::Org => ReportFormat::Markdown,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    Entry,
+    Description,
+    Project,
+}
+
+impl GroupBy {
+    pub fn parse(value: &str) -> Option<GroupBy> {
+        match value {
+            "entry" => Some(GroupBy::Entry),
+            "description" => Some(GroupBy::Description),
+            "project" => Some(GroupBy::Project),
+            _ => None,
+        }
+    }
+
+    fn toggle(&self) -> GroupBy {
+        match self {
+            GroupBy::Entry => GroupBy::Description,
+            GroupBy::Description => GroupBy::Project,
+            GroupBy::Project => GroupBy::Entry,
+        }
+    }
+}
+
+pub fn report(s: &mut Cursive) {
+    show_report(s, ReportFormat::Markdown, GroupBy::Description);
+}
+
+fn show_report(s: &mut Cursive, report_format: ReportFormat, group_by: GroupBy) {
+    let granularity = granularity_picker::get_granularity(s);
+    let date = s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.item()
+            .and_then(|index| t.borrow_item(index))
+            .map(Row::date)
+            .unwrap_or_else(time_picker::today)
+    }).expect("Unable to get clock entries table");
+    let entries = clock_entries_table::get_visible_clock_entries(s);
+    let projects = app_context::fetch(s).projects();
+    let billing = app_context::fetch(s).billing();
+    let mut content = render(&entries, &projects, &billing, date..=date, group_by, report_format, granularity);
+    if let Some(targets) = app_context::fetch(s).targets() {
+        let absences = app_context::fetch(s).absences();
+        content += &render_working_time(&clock_entries_table::get_clock_entries(s), &targets, &absences, date..=date, report_format, granularity);
+    }
+
+    s.add_layer(
+        Dialog::around(TextView::new(content.clone()).scrollable().max_height(30))
+            .title(format!("Report for {}", date))
+            .button(
+                match report_format { ReportFormat::Markdown => "As org", ReportFormat::Org => "As markdown" },
+                move |s| {
+                    s.pop_layer();
+                    show_report(s, report_format.toggle(), group_by);
+                }
+            )
+            .button(
+                match group_by.toggle() {
+                    GroupBy::Entry => "By entry",
+                    GroupBy::Description => "By description",
+                    GroupBy::Project => "By project",
+                },
+                move |s| {
+                    s.pop_layer();
+                    show_report(s, report_format, group_by.toggle());
+                }
+            )
+            .button("Save", move |s| {
+                save_report(s, format!("clockking-report-{}.{}", date, report_format.extension()), content.clone())
+            })
+            .button("Close", |s| { s.pop_layer(); })
+    );
+}
+
+fn save_report(s: &mut Cursive, file_name: String, content: String) {
+    s.add_layer(
+        Dialog::around(EditView::new().content(file_name).with_name(REPORT_FILE_NAME).min_width(40))
+            .title("Save report as")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Save", move |s| {
+                let file_name = s.call_on_name(REPORT_FILE_NAME, |e: &mut EditView| e.get_content().to_string())
+                    .expect("The report file name should be defined");
+                s.pop_layer();
+                let message = match fs::write(&file_name, &content) {
+                    Ok(()) => format!("Report saved to {}", file_name),
+                    Err(e) => format!("Unable to save the report to {}: {}", file_name, e),
+                };
+                s.add_layer(Dialog::info(message));
+            })
+    );
+}
+
+/// Renders the entries in the period as a table, followed by the totals and the revenue of the billable entries.
+pub fn render(
+    entries: &[ClockEntry],
+    projects: &[Project],
+    billing: &Billing,
+    period: RangeInclusive<NaiveDate>,
+    group_by: GroupBy,
+    report_format: ReportFormat,
+    granularity: Granularity,
+) -> String {
+    let (from, to) = (*period.start(), *period.end());
+    let mut entries: Vec<&ClockEntry> = entries.iter()
+        .filter(|it| period.contains(&it.date))
+        .collect();
+    entries.sort_by_key(|it| (it.date, it.from));
+    let multiple_days = from != to;
+
+    let (header, rows): (Vec<&str>, Vec<Vec<String>>) = match group_by {
+        GroupBy::Entry => {
+            let header = if multiple_days {
+                vec!["Date", "From", "To", "Description", "Duration", "Clocked"]
+            } else {
+                vec!["From", "To", "Description", "Duration", "Clocked"]
+            };
+            let rows = entries.iter().map(|it| {
+                let mut row = vec![
+                    format::format_naive_time(granularity, it.from),
+                    format::format_naive_time(granularity, it.to),
+                    it.description.clone(),
+                    format::format_hms(granularity, it.duration().num_seconds()),
+                    if it.is_clocked { "[x]".to_string() } else { "[ ]".to_string() },
+                ];
+                if multiple_days {
+                    row.insert(0, it.date.to_string());
+                }
+                row
+            }).collect();
+            (header, rows)
+        },
+        GroupBy::Description => {
+            let mut descriptions: Vec<&str> = Vec::new();
+            entries.iter().for_each(|it| {
+                if !descriptions.contains(&it.description.as_str()) {
+                    descriptions.push(&it.description);
+                }
+            });
+            let rows = descriptions.iter().map(|description| {
+                let totals = stats_view::totals(entries.iter().copied().filter(|it| it.description == *description));
+                vec![
+                    description.to_string(),
+                    format::format_hms(granularity, totals.total_seconds),
+                    format::format_hms(granularity, totals.clocked_seconds),
+                ]
+            }).collect();
+            (vec!["Description", "Duration", "Clocked"], rows)
+        },
+        GroupBy::Project => {
+            let rows = stats_view::project_totals(entries.iter().copied()).iter().map(|(project, totals)| {
+                vec![
+                    stats_view::project_label(project, projects),
+                    format::format_hms(granularity, totals.total_seconds),
+                    format::format_hms(granularity, totals.clocked_seconds),
+                ]
+            }).collect();
+            (vec!["Project", "Duration", "Clocked"], rows)
+        },
+    };
+
+    let title = if multiple_days {
+        format!("Clock King report {} - {}", from, to)
+    } else {
+        format!("Clock King report {}", from)
+    };
+    let totals = stats_view::totals(entries.iter().copied());
+    let totals_list = [
+        (TOTAL_HOURS, totals.total_seconds),
+        (TOTAL_HOURS_CLOCKED, totals.clocked_seconds),
+        (TOTAL_HOURS_REMAINING, totals.remaining_seconds()),
+    ].iter()
+        .map(|(prompt, seconds)| format!("- {}: {}", prompt, format::format_hms(granularity, *seconds)))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let report = match report_format {
+        ReportFormat::Markdown => format!("# {}\n\n{}\n{}\n", title, render_table(&header, &rows, report_format), totals_list),
+        ReportFormat::Org => format!("* {}\n\n{}\n{}\n", title, render_table(&header, &rows, report_format), totals_list),
+    };
+    if entries.iter().any(|it| it.billable) {
+        report + &render_revenue(&entries, projects, billing, report_format, granularity)
+    } else {
+        report
+    }
+}
+
+fn render_revenue(
+    entries: &[&ClockEntry],
+    projects: &[Project],
+    billing: &Billing,
+    report_format: ReportFormat,
+    granularity: Granularity,
+) -> String {
+    let revenue = billing::revenue(entries.iter().copied(), projects, billing, granularity);
+    let rows: Vec<Vec<String>> = revenue.iter().map(|it| vec![
+        stats_view::project_label(&it.project, projects),
+        format::format_hms(granularity, it.billable_seconds),
+        it.rate.map(|rate| format!("{}/h", billing::format_money(rate, &billing.currency))).unwrap_or_else(|| "-".to_string()),
+        billing::format_money(it.amount, &billing.currency),
+    ]).collect();
+    let table = render_table(&["Project", "Billable", "Rate", "Amount"], &rows, report_format);
+    let total = format!("- {}: {}", BILLABLE_AMOUNT, billing::format_money(billing::total_amount(&revenue), &billing.currency));
+    match report_format {
+        ReportFormat::Markdown => format!("\n## Revenue\n\n{}\n{}\n", table, total),
+        ReportFormat::Org => format!("\n** Revenue\n\n{}\n{}\n", table, total),
+    }
+}
+
+/// The target and the worked hours in the period, the absences in it, and the flex balance at its end, over all the
+/// given entries.
+pub fn render_working_time(
+    entries: &[ClockEntry],
+    targets: &Targets,
+    absences: &[Absence],
+    period: RangeInclusive<NaiveDate>,
+    report_format: ReportFormat,
+    granularity: Granularity,
+) -> String {
+    let end = *period.end();
+    let absent: Vec<String> = absences.iter()
+        .filter(|it| period.contains(&it.date))
+        .map(|it| format!("- {}", it.label()))
+        .collect();
+    let balance = targets::balance(entries, targets, absences, period);
+    let table = render_table(
+        &["Target", "Worked", "Difference"],
+        &[vec![
+            format::format_hms(granularity, balance.target_seconds),
+            format::format_hms(granularity, balance.worked_seconds),
+            format::format_signed_hms(granularity, balance.difference()),
+        ]],
+        report_format
+    );
+    let flex = format!(
+        "- {} on {}: {}",
+        FLEX_BALANCE,
+        end,
+        format::format_signed_hms(granularity, targets::flex_balance(entries, targets, absences, end, time_picker::today()))
+    );
+    let details = absent.into_iter().chain([flex]).collect::<Vec<String>>().join("\n");
+    match report_format {
+        ReportFormat::Markdown => format!("\n## Working time\n\n{}\n{}\n", table, details),
+        ReportFormat::Org => format!("\n** Working time\n\n{}\n{}\n", table, details),
+    }
+}
+
+fn render_table(header: &[&str], rows: &[Vec<String>], report_format: ReportFormat) -> String {
+    let escape = |cell: &str| match report_format {
+        ReportFormat::Markdown => cell.replace('|', "\\|"),
+        ReportFormat::Org => cell.replace('|', "\\vert{}"),
+    };
+    let rows: Vec<Vec<String>> = rows.iter()
+        .map(|row| row.iter().map(|cell| escape(cell)).collect())
+        .collect();
+    let widths: Vec<usize> = header.iter().enumerate()
+        .map(|(i, title)| rows.iter().map(|row| row[i].chars().count()).chain([title.chars().count(), 3]).max().unwrap())
+        .collect();
+
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let pad = |cell: &str, width: usize| format!("{}{}", cell, " ".repeat(width - cell.chars().count()));
+    let separator = widths.iter().map(|width| "-".repeat(width + 2)).collect::<Vec<String>>();
+
+    let mut table = line(header.iter().zip(&widths).map(|(title, width)| pad(title, *width)).collect());
+    table += &match report_format {
+        ReportFormat::Markdown => format!("|{}|\n", separator.join("|")),
+        ReportFormat::Org => format!("|{}|\n", separator.join("+")),
+    };
+    rows.iter().for_each(|row| {
+        table += &line(row.iter().zip(&widths).map(|(cell, width)| pad(cell, *width)).collect());
+    });
+    table
+}
+
+#[cfg(test)]
+mod render_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity, Project};
+    use crate::billing::Billing;
+    use crate::report::{GroupBy, render, render_working_time, ReportFormat};
+    use crate::targets::Targets;
+
+    fn entry(day: u32, from: (u32, u32), to: (u32, u32), description: &str, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, day),
+            from: NaiveTime::from_hms(from.0, from.1, 0),
+            to: NaiveTime::from_hms(to.0, to.1, 0),
+            description: description.to_string(),
+            is_clocked,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn entries() -> Vec<ClockEntry> {
+        vec![
+            entry(14, (11, 0), (12, 30), "Coding", false),
+            entry(14, (9, 0), (9, 15), "Standup", true),
+            entry(14, (9, 15), (11, 0), "Coding", true),
+            entry(15, (9, 0), (9, 15), "Standup", false),
+        ]
+    }
+
+    #[test]
+    fn renders_a_day_grouped_by_description_as_markdown() {
+        let day = NaiveDate::from_ymd(2022, 3, 14);
+        assert_eq!(
+            render(&entries(), &[], &Billing::default(), day..=day, GroupBy::Description, ReportFormat::Markdown, Granularity::Detailed),
+            "# Clock King report 2022-03-14
+
+| Description | Duration | Clocked |
+|-------------|----------|---------|
+| Standup     | 0h 15m   | 0h 15m  |
+| Coding      | 3h 15m   | 1h 45m  |
+
+- Total hours: 3h 30m
+- Total clocked: 2h 00m
+- Left to clock: 1h 30m
+"
+        );
+    }
+
+    #[test]
+    fn renders_a_range_of_entries_as_an_org_table() {
+        assert_eq!(
+            render(
+                &entries(),
+                &[],
+                &Billing::default(),
+                NaiveDate::from_ymd(2022, 3, 14)..=NaiveDate::from_ymd(2022, 3, 15),
+                GroupBy::Entry,
+                ReportFormat::Org,
+                Granularity::Detailed
+            ),
+            "* Clock King report 2022-03-14 - 2022-03-15
+
+| Date       | From  | To    | Description | Duration | Clocked |
+|------------+-------+-------+-------------+----------+---------|
+| 2022-03-14 | 09:00 | 09:15 | Standup     | 0h 15m   | [x]     |
+| 2022-03-14 | 09:15 | 11:00 | Coding      | 1h 45m   | [x]     |
+| 2022-03-14 | 11:00 | 12:30 | Coding      | 1h 30m   | [ ]     |
+| 2022-03-15 | 09:00 | 09:15 | Standup     | 0h 15m   | [ ]     |
+
+- Total hours: 3h 45m
+- Total clocked: 2h 00m
+- Left to clock: 1h 45m
+"
+        );
+    }
+
+    #[test]
+    fn escapes_table_separators() {
+        let day = NaiveDate::from_ymd(2022, 3, 15);
+        let entries = vec![entry(15, (9, 0), (10, 0), "a | b", false)];
+        assert!(render(&entries, &[], &Billing::default(), day..=day, GroupBy::Description, ReportFormat::Markdown, Granularity::Detailed).contains("| a \\| b "));
+        assert!(render(&entries, &[], &Billing::default(), day..=day, GroupBy::Description, ReportFormat::Org, Granularity::Detailed).contains("| a \\vert{} b "));
+    }
+
+    #[test]
+    fn renders_project_subtotals() {
+        let day = NaiveDate::from_ymd(2022, 3, 14);
+        let mut entries = entries();
+        entries[0].project = Some("Website".to_string());
+        entries[2].project = Some("Website".to_string());
+        let projects = vec![Project { name: "Website".to_string(), client: Some("ACME".to_string()), rate: None }];
+        assert_eq!(
+            render(&entries, &projects, &Billing::default(), day..=day, GroupBy::Project, ReportFormat::Markdown, Granularity::Detailed),
+            "# Clock King report 2022-03-14
+
+| Project        | Duration | Clocked |
+|----------------|----------|---------|
+| Website (ACME) | 3h 15m   | 1h 45m  |
+| No project     | 0h 15m   | 0h 15m  |
+
+- Total hours: 3h 30m
+- Total clocked: 2h 00m
+- Left to clock: 1h 30m
+"
+        );
+    }
+
+    #[test]
+    fn renders_revenue_per_project() {
+        let day = NaiveDate::from_ymd(2022, 3, 14);
+        let mut entries = entries();
+        entries[0].project = Some("Website".to_string());
+        entries[0].billable = true;
+        entries[2].billable = true;
+        let projects = vec![Project { name: "Website".to_string(), client: None, rate: Some(100.0) }];
+        let billing = Billing { default_rate: Some(60.0), currency: "USD".to_string() };
+        assert!(
+            render(&entries, &projects, &billing, day..=day, GroupBy::Description, ReportFormat::Markdown, Granularity::Relaxed)
+                .ends_with("
+## Revenue
+
+| Project    | Billable | Rate         | Amount     |
+|------------|----------|--------------|------------|
+| Website    | 1h       | 100.00 USD/h | 100.00 USD |
+| No project | 1h       | 60.00 USD/h  | 60.00 USD  |
+
+- Billable amount: 160.00 USD
+")
+        );
+    }
+
+    #[test]
+    fn renders_working_time() {
+        let targets = Targets { hours: [2.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0], ..Targets::default() };
+        let period = NaiveDate::from_ymd(2022, 3, 14)..=NaiveDate::from_ymd(2022, 3, 15);
+        assert_eq!(
+            render_working_time(&entries(), &targets, &[], period, ReportFormat::Markdown, Granularity::Detailed),
+            "
+## Working time
+
+| Target | Worked | Difference |
+|--------|--------|------------|
+| 4h 00m | 3h 45m | -0h 15m    |
+
+- Flex balance on 2022-03-15: -0h 15m
+"
+        );
+    }
+}
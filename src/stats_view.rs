@@ -1,48 +1,200 @@
+use std::collections::BTreeMap;
+
+use chrono::Local;
 use cursive::Cursive;
 use cursive::direction::Orientation;
 use cursive::traits::Nameable;
 use cursive::views::{LinearLayout, TextView};
-use cursive_table_view::TableView;
 
-use crate::{app_context, CLOCK_ENTRIES_TABLE, ClockEntry, format, granularity_picker};
-use crate::clock_entries_table::ClockEntryColumn;
+use crate::{app_context, billing, breaks, clock_entries_table, ClockEntry, format, Granularity, granularity_picker, main_dialog, Project, targets, time_picker, timeline, timers};
+use crate::absences::Absence;
+use crate::billing::{BILLABLE_AMOUNT, BILLABLE_HOURS, NON_BILLABLE_HOURS};
+use crate::targets::{FLEX_BALANCE, LEFT_TODAY, OVERTIME_TODAY, THIS_WEEK, Targets};
+use crate::timers::Timer;
 
 pub const TOTAL_HOURS_CLOCKED: &str   = "Total clocked";
 pub const TOTAL_HOURS_REMAINING: &str = "Left to clock";
 pub const TOTAL_HOURS: &str           = "Total hours";
 pub const RECORDING_STATUS: &str      = "RECORDING_STATUS";
+pub const PROJECT_TOTALS: &str        = "PROJECT_TOTALS";
+pub const BILLING_TOTALS: &str        = "BILLING_TOTALS";
+pub const TARGET_TOTALS: &str         = "TARGET_TOTALS";
+pub const NO_PROJECT: &str            = "No project";
+pub const FILTER_STATUS: &str         = "FILTER_STATUS";
 
 pub fn new() -> LinearLayout {
     LinearLayout::new(Orientation::Vertical)
+        .child(TextView::new("").with_name(FILTER_STATUS))
         .child(TextView::new(TOTAL_HOURS).with_name(TOTAL_HOURS))
         .child(TextView::new(TOTAL_HOURS_CLOCKED).with_name(TOTAL_HOURS_CLOCKED))
         .child(TextView::new(TOTAL_HOURS_REMAINING).with_name(TOTAL_HOURS_REMAINING))
+        .child(TextView::new("").with_name(BILLING_TOTALS))
+        .child(TextView::new("").with_name(TARGET_TOTALS))
+        .child(TextView::new("").with_name(PROJECT_TOTALS))
         .child(TextView::new("No recording in progress.").with_name(RECORDING_STATUS))
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Totals {
+    pub total_seconds: i64,
+    pub clocked_seconds: i64,
+}
+
+impl Totals {
+    pub fn remaining_seconds(&self) -> i64 {
+        self.total_seconds - self.clocked_seconds
+    }
+}
+
+pub fn totals<'a>(entries: impl IntoIterator<Item = &'a ClockEntry>) -> Totals {
+    entries.into_iter().filter(|it| !it.excluded_from_totals).fold(Totals::default(), |totals, it| {
+        let seconds = it.duration().num_seconds();
+        Totals {
+            total_seconds: totals.total_seconds + seconds,
+            clocked_seconds: totals.clocked_seconds + if it.is_clocked { seconds } else { 0 },
+        }
+    })
+}
+
+/// Totals per project name, ordered by name, with the entries without a project last.
+pub fn project_totals<'a>(entries: impl IntoIterator<Item = &'a ClockEntry>) -> Vec<(Option<String>, Totals)> {
+    let mut groups: BTreeMap<(bool, Option<String>), Vec<&ClockEntry>> = BTreeMap::new();
+    entries.into_iter().for_each(|it| groups.entry((it.project.is_none(), it.project.clone())).or_default().push(it));
+    groups.into_iter()
+        .map(|((_, project), entries)| (project, totals(entries)))
+        .collect()
+}
+
+pub fn project_label(project: &Option<String>, projects: &[Project]) -> String {
+    match project {
+        Some(name) => projects.iter()
+            .find(|it| it.name == *name)
+            .map(|it| it.label())
+            .unwrap_or_else(|| name.clone()),
+        None => NO_PROJECT.to_string(),
+    }
+}
+
+/// Billable and non-billable hours and the billable amount, or nothing when no entry is billable.
+fn billing_lines(entries: &[ClockEntry], projects: &[Project], billing: &billing::Billing, granularity: Granularity) -> String {
+    if !entries.iter().any(|it| it.billable) {
+        return String::new();
+    }
+    let billable_seconds = billing::billable_seconds(entries);
+    let revenue = billing::revenue(entries, projects, billing, granularity);
+    [
+        format::format_hms_with_prompt(granularity, BILLABLE_HOURS, billable_seconds),
+        format::format_hms_with_prompt(granularity, NON_BILLABLE_HOURS, totals(entries).total_seconds - billable_seconds),
+        format!("{}:\t{}", BILLABLE_AMOUNT, billing::format_money(billing::total_amount(&revenue), &billing.currency)),
+    ].join("\n")
+}
+
+/// Progress towards today's and this week's targets, over all the entries regardless of the filter.
+fn target_lines(entries: &[ClockEntry], targets: &Targets, absences: &[Absence], granularity: Granularity) -> String {
+    let today = time_picker::today();
+    let day = targets::balance(entries, targets, absences, today..=today);
+    let week = targets::balance(entries, targets, absences, targets::week(today));
+    [
+        format::format_hms_with_prompt(granularity, LEFT_TODAY, day.remaining()),
+        format::format_hms_with_prompt(granularity, OVERTIME_TODAY, day.overtime()),
+        format!(
+            "{}:\t{} / {}",
+            THIS_WEEK,
+            format::format_hms(granularity, week.worked_seconds),
+            format::format_hms(granularity, week.target_seconds)
+        ),
+        format!("{}:\t{}", FLEX_BALANCE, format::format_signed_hms(granularity, targets::flex_balance(entries, targets, absences, today, today))),
+    ].join("\n")
+}
+
+/// Refreshes the totals, and the overlaps, gaps and marks in the table, after the entries changed.
 pub fn update_stats(s: &mut Cursive) {
+    timeline::update_table(s);
+    clock_entries_table::update_marks(s);
     let granularity = granularity_picker::get_granularity(s);
-    let (total_seconds, total_seconds_clocked) = s.call_on_name(CLOCK_ENTRIES_TABLE, move |t: &mut TableView<ClockEntry, ClockEntryColumn>| {
-        let items = t.borrow_items();
-        (items.iter().map(|it| it.duration().num_seconds()).sum(),
-         items.iter().filter(|it|it.is_clocked).map(|it| it.duration().num_seconds()).sum())
-    }).unwrap();
-    s.call_on_name(TOTAL_HOURS, move |t: &mut TextView| {
-        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS, total_seconds));
+    let projects = app_context::fetch(s).projects();
+    let billing = app_context::fetch(s).billing();
+    let entries = clock_entries_table::get_visible_clock_entries(s);
+    let (totals, project_totals, billing_lines) = (totals(&entries), project_totals(&entries), billing_lines(&entries, &projects, &billing, granularity));
+    let project_lines = if project_totals.iter().any(|(project, _)| project.is_some()) {
+        project_totals.iter()
+            .map(|(project, totals)| format::format_hms_with_prompt(granularity, &format!("  {}", project_label(project, &projects)), totals.total_seconds))
+            .collect::<Vec<String>>()
+            .join("\n")
+    } else {
+        String::new()
+    };
+    let hidden = app_context::fetch(s).hidden_entries().len();
+    let filter_status = if app_context::fetch(s).filter().is_active() {
+        format!("Totals of the {} filtered entries, {} hidden", entries.len(), hidden)
+    } else {
+        String::new()
+    };
+    s.call_on_name(FILTER_STATUS, move |t: &mut TextView| {
+        t.set_content(filter_status);
+    });
+    s.call_on_name(PROJECT_TOTALS, move |t: &mut TextView| {
+        t.set_content(project_lines);
+    });
+    s.call_on_name(BILLING_TOTALS, move |t: &mut TextView| {
+        t.set_content(billing_lines);
+    });
+    let absences = app_context::fetch(s).absences();
+    let target_lines = match app_context::fetch(s).targets() {
+        Some(targets) => target_lines(&clock_entries_table::get_clock_entries(s), &targets, &absences, granularity),
+        None => String::new(),
+    };
+    s.call_on_name(TARGET_TOTALS, move |t: &mut TextView| {
+        t.set_content(target_lines);
     });
     s.call_on_name(TOTAL_HOURS_CLOCKED, move |t: &mut TextView| {
-        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS_CLOCKED, total_seconds_clocked));
+        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS_CLOCKED, totals.clocked_seconds));
+    });
+    update_recording_status(s);
+}
+
+/// Refreshes what changes while recording: the totals with the time recorded so far, the recording status, the title
+/// and the timers. The ticker calls it every second.
+pub fn update_recording_status(s: &mut Cursive) {
+    let granularity = granularity_picker::get_granularity(s);
+    let now = Local::now().naive_local();
+    let filter = app_context::fetch(s).filter();
+    let context = app_context::fetch(s).ongoing_recording();
+    let breaks = app_context::fetch(s).recording_breaks();
+    let elapsed = context.as_ref().map(|it| breaks::elapsed(it, &breaks, now).num_seconds()).unwrap_or_default();
+    let is_counted = |entry: &ClockEntry| filter.partition([ClockEntry { to: now.time(), ..entry.clone() }])
+        .is_ok_and(|(visible, _)| !visible.is_empty());
+    let counted_timers = app_context::fetch(s).timers().into_iter()
+        .filter(|it| is_counted(&it.entry))
+        .collect::<Vec<Timer>>();
+    let mut totals = totals(&clock_entries_table::get_visible_clock_entries(s));
+    totals.total_seconds += timers::counted(&counted_timers, now).num_seconds();
+    if context.as_ref().is_some_and(is_counted) {
+        totals.total_seconds += elapsed;
+    }
+    s.call_on_name(TOTAL_HOURS, move |t: &mut TextView| {
+        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS, totals.total_seconds));
     });
     s.call_on_name(TOTAL_HOURS_REMAINING, move |t: &mut TextView| {
-        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS_REMAINING, total_seconds - total_seconds_clocked));
+        t.set_content(format::format_hms_with_prompt(granularity, TOTAL_HOURS_REMAINING, totals.remaining_seconds()));
     });
 
-    let context = app_context::fetch(s).ongoing_recording();
+    let running = context.as_ref().map(|recording| {
+        let status = if breaks::is_paused(&breaks) { "Paused" } else { "Recording" };
+        format!("{} '{}' {}", status, recording.description, format::format_hms(Granularity::Scientific, elapsed))
+    });
     let recording_status = if let Some(recording) = context {
+        let status = match breaks.last().filter(|_| breaks::is_paused(&breaks)) {
+            Some(ongoing) => format!("Paused '{}' since {}", recording.description, format::format_naive_time(granularity, ongoing.from)),
+            None => format!("Recording '{}'", recording.description),
+        };
+        let paused = breaks::total(&breaks, now.time()).num_seconds();
         format!(
-            "Recording '{}' ({} - ...)",
-            recording.description,
-            format::format_naive_time(granularity, recording.from)
+            "{} ({} - ..., {}{})",
+            status,
+            format::format_naive_time(granularity, recording.from),
+            format::format_hms(Granularity::Scientific, elapsed),
+            if breaks.is_empty() { String::new() } else { format!(", {} of breaks", format::format_hms(granularity, paused)) }
         )
     } else {
         "No recording in progress.".to_string()
@@ -50,4 +202,10 @@ pub fn update_stats(s: &mut Cursive) {
     s.call_on_name(RECORDING_STATUS, move |t: &mut TextView| {
         t.set_content(recording_status);
     });
+
+    let suffix = [Some(filter.describe()).filter(|_| filter.is_active()), running].into_iter()
+        .flatten()
+        .collect::<Vec<String>>();
+    main_dialog::set_title_suffix(s, Some(suffix.join(" - ")).filter(|it| !it.is_empty()));
+    timers::update_panel(s);
 }
\ No newline at end of file
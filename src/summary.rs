@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use cursive::Cursive;
+use cursive::traits::{Resizable, Scrollable};
+use cursive::views::{Dialog, TextView};
+
+use crate::{clock_entries_table, ClockEntry, format, Granularity, granularity_picker, stats_view, time_picker};
+use crate::stats_view::Totals;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeriodTotals {
+    pub label: String,
+    pub totals: Totals,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub days: Vec<PeriodTotals>,
+    pub weeks: Vec<PeriodTotals>,
+    pub months: Vec<PeriodTotals>,
+    pub overall: Totals,
+}
+
+pub fn summary(s: &mut Cursive) {
+    let today = time_picker::today();
+    show_summary(s, today - Duration::days(today.weekday().num_days_from_monday() as i64), today);
+}
+
+fn show_summary(s: &mut Cursive, from: NaiveDate, to: NaiveDate) {
+    let granularity = granularity_picker::get_granularity(s);
+    let entries = clock_entries_table::get_visible_clock_entries(s);
+    let today = time_picker::today();
+    s.add_layer(
+        Dialog::around(TextView::new(render(&summarize(&entries, from, to), granularity)).scrollable().max_height(30))
+            .title("Summary")
+            .button("This week", move |s| {
+                s.pop_layer();
+                show_summary(s, today - Duration::days(today.weekday().num_days_from_monday() as i64), today);
+            })
+            .button("This month", move |s| {
+                s.pop_layer();
+                show_summary(s, today.with_day(1).unwrap(), today);
+            })
+            .button("This year", move |s| {
+                s.pop_layer();
+                show_summary(s, today.with_ordinal(1).unwrap(), today);
+            })
+            .button("Close", |s| { s.pop_layer(); })
+    );
+}
+
+/// Aggregates the entries between `from` and `to` (inclusive) per day, ISO week and month.
+pub fn summarize(entries: &[ClockEntry], from: NaiveDate, to: NaiveDate) -> Summary {
+    let entries: Vec<&ClockEntry> = entries.iter()
+        .filter(|it| it.date >= from && it.date <= to)
+        .collect();
+    let group = |label: fn(NaiveDate) -> String| {
+        let mut groups: BTreeMap<String, Vec<&ClockEntry>> = BTreeMap::new();
+        entries.iter().for_each(|it| groups.entry(label(it.date)).or_default().push(it));
+        groups.into_iter()
+            .map(|(label, entries)| PeriodTotals { label, totals: stats_view::totals(entries) })
+            .collect()
+    };
+    Summary {
+        from,
+        to,
+        days: group(|date| date.to_string()),
+        weeks: group(|date| format!("{}-W{:02}", date.iso_week().year(), date.iso_week().week())),
+        months: group(|date| format!("{}-{:02}", date.year(), date.month())),
+        overall: stats_view::totals(entries.iter().copied()),
+    }
+}
+
+pub fn render(summary: &Summary, granularity: Granularity) -> String {
+    let hms = |seconds: i64| format::format_hms(granularity, seconds);
+    let table = |title: &str, periods: &[PeriodTotals]| {
+        let mut lines = vec![format!("{:<12}{:>12}{:>12}{:>12}", title, "Total", "Clocked", "Unclocked")];
+        lines.extend(periods.iter().map(|it| format!(
+            "{:<12}{:>12}{:>12}{:>12}",
+            it.label,
+            hms(it.totals.total_seconds),
+            hms(it.totals.clocked_seconds),
+            hms(it.totals.remaining_seconds())
+        )));
+        lines.join("\n")
+    };
+    let average = |periods: &[PeriodTotals]| {
+        if periods.is_empty() { 0 } else { summary.overall.total_seconds / periods.len() as i64 }
+    };
+
+    [
+        format!("Summary {} - {}", summary.from, summary.to),
+        table("Day", &summary.days),
+        table("Week", &summary.weeks),
+        table("Month", &summary.months),
+        [
+            ("Total", summary.overall.total_seconds),
+            ("Clocked", summary.overall.clocked_seconds),
+            ("Unclocked", summary.overall.remaining_seconds()),
+            ("Average per day", average(&summary.days)),
+            ("Average per week", average(&summary.weeks)),
+            ("Average per month", average(&summary.months)),
+        ].iter()
+            .map(|(prompt, seconds)| format!("{:<20}{}", format!("{}:", prompt), hms(*seconds)))
+            .collect::<Vec<String>>()
+            .join("\n"),
+    ].join("\n\n") + "\n"
+}
+
+#[cfg(test)]
+mod summarize_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::stats_view::Totals;
+    use crate::summary::{PeriodTotals, summarize};
+
+    fn entry(month: u32, day: u32, hours: u32, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, month, day),
+            from: NaiveTime::from_hms(9, 0, 0),
+            to: NaiveTime::from_hms(9 + hours, 0, 0),
+            description: "Work".to_string(),
+            is_clocked,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    #[test]
+    fn aggregates_per_day_week_and_month() {
+        let entries = vec![
+            entry(3, 31, 2, true),
+            entry(3, 31, 1, false),
+            entry(4, 1, 4, false),
+            entry(4, 4, 8, true),
+            entry(4, 30, 8, true),
+        ];
+        let summary = summarize(&entries, NaiveDate::from_ymd(2022, 3, 31), NaiveDate::from_ymd(2022, 4, 4));
+
+        let labels = |periods: &Vec<PeriodTotals>| periods.iter().map(|it| it.label.clone()).collect::<Vec<String>>();
+        assert_eq!(labels(&summary.days), vec!["2022-03-31", "2022-04-01", "2022-04-04"]);
+        assert_eq!(labels(&summary.weeks), vec!["2022-W13", "2022-W14"]);
+        assert_eq!(labels(&summary.months), vec!["2022-03", "2022-04"]);
+        assert_eq!(summary.days[0].totals, Totals { total_seconds: 3 * 3600, clocked_seconds: 2 * 3600 });
+        assert_eq!(summary.weeks[0].totals, Totals { total_seconds: 7 * 3600, clocked_seconds: 2 * 3600 });
+        assert_eq!(summary.months[1].totals, Totals { total_seconds: 12 * 3600, clocked_seconds: 8 * 3600 });
+        assert_eq!(summary.overall, Totals { total_seconds: 15 * 3600, clocked_seconds: 10 * 3600 });
+    }
+}
+
+#[cfg(test)]
+mod render_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::summary::{render, summarize};
+
+    #[test]
+    fn renders_breakdown_and_averages() {
+        let entries = vec![
+            ClockEntry {
+                date: NaiveDate::from_ymd(2022, 3, 14),
+                from: NaiveTime::from_hms(9, 0, 0),
+                to: NaiveTime::from_hms(12, 30, 0),
+                description: "Work".to_string(),
+                is_clocked: true,
+                granularity: Granularity::Detailed,
+                ..ClockEntry::default()
+            },
+            ClockEntry {
+                date: NaiveDate::from_ymd(2022, 3, 15),
+                from: NaiveTime::from_hms(9, 0, 0),
+                to: NaiveTime::from_hms(10, 0, 0),
+                description: "Work".to_string(),
+                is_clocked: false,
+                granularity: Granularity::Detailed,
+                ..ClockEntry::default()
+            },
+        ];
+        let day = |d| NaiveDate::from_ymd(2022, 3, d);
+        assert_eq!(
+            render(&summarize(&entries, day(14), day(15)), Granularity::Detailed),
+            "Summary 2022-03-14 - 2022-03-15
+
+Day                Total     Clocked   Unclocked
+2022-03-14        3h 30m      3h 30m      0h 00m
+2022-03-15        1h 00m      0h 00m      1h 00m
+
+Week               Total     Clocked   Unclocked
+2022-W11          4h 30m      3h 30m      1h 00m
+
+Month              Total     Clocked   Unclocked
+2022-03           4h 30m      3h 30m      1h 00m
+
+Total:              4h 30m
+Clocked:            3h 30m
+Unclocked:          1h 00m
+Average per day:    2h 15m
+Average per week:   4h 30m
+Average per month:  4h 30m
+"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+
+use crate::ClockEntry;
+
+/// Finds the `#tag` words in a description.
+pub fn parse_inline_tags(description: &str) -> Vec<String> {
+    description
+        .split_whitespace()
+        .filter_map(|word| word.strip_prefix('#'))
+        .map(|tag| tag.trim_end_matches(|c: char| !is_tag_char(c)))
+        .filter(|tag| !tag.is_empty() && tag.chars().all(is_tag_char))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Parses a comma or space separated list of tags, with or without the leading `#`.
+pub fn parse_tag_list(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect()
+}
+
+pub fn merge_tags(a: Vec<String>, b: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    a.into_iter().chain(b).for_each(|tag| {
+        if !result.contains(&tag) {
+            result.push(tag);
+        }
+    });
+    result
+}
+
+pub fn format_tags(tags: &[String]) -> String {
+    tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<String>>().join(" ")
+}
+
+/// All the tags used by the entries, sorted.
+pub fn known_tags<'a>(entries: impl IntoIterator<Item = &'a ClockEntry>) -> Vec<String> {
+    entries.into_iter()
+        .flat_map(|it| it.tags.iter().cloned())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+/// Known tags that complete the last, partially typed tag in the list.
+pub fn suggestions(known_tags: &[String], value: &str) -> Vec<String> {
+    let mut typed = parse_tag_list(value);
+    let is_typing = !value.ends_with(|c: char| c == ',' || c.is_whitespace());
+    let prefix = if is_typing { typed.pop().unwrap_or_default() } else { String::new() };
+    known_tags.iter()
+        .filter(|tag| !typed.contains(tag) && tag.starts_with(&prefix) && **tag != prefix)
+        .cloned()
+        .collect()
+}
+
+/// Replaces the partially typed tag with its first suggestion.
+pub fn complete(known_tags: &[String], value: &str) -> Option<String> {
+    let suggestion = suggestions(known_tags, value).into_iter().next()?;
+    let mut typed = parse_tag_list(value);
+    if !value.ends_with(|c: char| c == ',' || c.is_whitespace()) {
+        typed.pop();
+    }
+    typed.push(suggestion);
+    Some(format!("{}, ", typed.join(", ")))
+}
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod tags_test {
+    use crate::tags::{complete, merge_tags, parse_inline_tags, parse_tag_list, suggestions};
+
+    #[test]
+    fn parses_inline_tags() {
+        assert_eq!(parse_inline_tags("Weekly sync #meeting #Team-A."), vec!["meeting", "team-a"]);
+        assert_eq!(parse_inline_tags("Issue #42, C# code"), vec!["42"]);
+        assert!(parse_inline_tags("No tags # here").is_empty());
+    }
+
+    #[test]
+    fn parses_tag_lists() {
+        assert_eq!(parse_tag_list("#meeting, review  oncall"), vec!["meeting", "review", "oncall"]);
+        assert!(parse_tag_list(" , ").is_empty());
+    }
+
+    #[test]
+    fn merges_without_duplicates() {
+        assert_eq!(
+            merge_tags(vec!["a".to_string(), "b".to_string()], vec!["b".to_string(), "c".to_string()]),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn suggests_completions_for_the_last_tag() {
+        let known = vec!["meeting".to_string(), "mentoring".to_string(), "review".to_string()];
+        assert_eq!(suggestions(&known, "me"), vec!["meeting", "mentoring"]);
+        assert_eq!(suggestions(&known, "review, me"), vec!["meeting", "mentoring"]);
+        assert_eq!(suggestions(&known, "meeting "), vec!["mentoring", "review"]);
+        assert_eq!(suggestions(&known, ""), vec!["meeting", "mentoring", "review"]);
+        assert!(suggestions(&known, "x").is_empty());
+    }
+
+    #[test]
+    fn completes_the_last_tag() {
+        let known = vec!["meeting".to_string(), "review".to_string()];
+        assert_eq!(complete(&known, "review, mee"), Some("review, meeting, ".to_string()));
+        assert_eq!(complete(&known, "review "), Some("review, meeting, ".to_string()));
+        assert_eq!(complete(&known, "x"), None);
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use cursive::Cursive;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, ListView};
+use serde::{Deserialize, Serialize};
+
+use crate::{absences, app_context, ClockEntry, stats_view, time_picker};
+use crate::absences::Absence;
+
+pub const LEFT_TODAY: &str     = "Left today";
+pub const OVERTIME_TODAY: &str = "Overtime today";
+pub const THIS_WEEK: &str      = "This week";
+pub const FLEX_BALANCE: &str   = "Flex balance";
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+const TARGETS_START: &str = "targets_start";
+const VACATION_DAYS: &str = "vacation_days";
+
+/// Working time targets, see the "Working time targets" section of the README.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Targets {
+    /// Hours per weekday, from Monday to Sunday
+    #[serde(default = "default_hours")]
+    pub hours: [f64; 7],
+    /// The first day counted in the flex balance, the day of the first entry when not set
+    #[serde(default)]
+    pub start: Option<NaiveDate>,
+    /// Vacation days per year
+    #[serde(default)]
+    pub vacation_days: Option<f64>,
+    /// Days without a target in older databases, moved to the absences when loaded
+    #[serde(default, skip_serializing)]
+    pub holidays: Vec<NaiveDate>,
+}
+
+impl Default for Targets {
+    fn default() -> Self {
+        Targets {
+            hours: default_hours(),
+            start: None,
+            vacation_days: None,
+            holidays: Vec::default(),
+        }
+    }
+}
+
+fn default_hours() -> [f64; 7] {
+    [8.0, 8.0, 8.0, 8.0, 8.0, 0.0, 0.0]
+}
+
+impl Targets {
+    /// The target of the day, lowered by the absences on it.
+    pub fn target_seconds(&self, date: NaiveDate, absences: &[Absence]) -> i64 {
+        if self.start.is_some_and(|start| date < start) {
+            0
+        } else {
+            let hours = self.hours[date.weekday().num_days_from_monday() as usize];
+            (hours * (1.0 - absences::absent_fraction(absences, date)) * 3600.0).round() as i64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Balance {
+    pub target_seconds: i64,
+    pub worked_seconds: i64,
+}
+
+impl Balance {
+    pub fn difference(&self) -> i64 {
+        self.worked_seconds - self.target_seconds
+    }
+
+    pub fn remaining(&self) -> i64 {
+        (-self.difference()).max(0)
+    }
+
+    pub fn overtime(&self) -> i64 {
+        self.difference().max(0)
+    }
+}
+
+pub fn week(date: NaiveDate) -> RangeInclusive<NaiveDate> {
+    let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+    monday..=monday + Duration::days(6)
+}
+
+pub fn balance(entries: &[ClockEntry], targets: &Targets, absences: &[Absence], period: RangeInclusive<NaiveDate>) -> Balance {
+    Balance {
+        target_seconds: days(&period).map(|date| targets.target_seconds(date, absences)).sum(),
+        worked_seconds: stats_view::totals(entries.iter().filter(|it| period.contains(&it.date))).total_seconds,
+    }
+}
+
+/// The overtime minus the missing hours of every day up to `date`. While `date` is today, its missing hours aren't
+/// counted yet, only its overtime.
+pub fn flex_balance(entries: &[ClockEntry], targets: &Targets, absences: &[Absence], date: NaiveDate, today: NaiveDate) -> i64 {
+    let start = targets.start
+        .or_else(|| entries.iter().map(|it| it.date).min())
+        .unwrap_or(date);
+    let mut worked: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    entries.iter()
+        .filter(|it| it.date >= start && it.date <= date && !it.excluded_from_totals)
+        .for_each(|it| *worked.entry(it.date).or_default() += it.duration().num_seconds());
+    days(&(start..=date))
+        .map(|day| {
+            let balance = Balance { target_seconds: targets.target_seconds(day, absences), worked_seconds: worked.get(&day).copied().unwrap_or_default() };
+            if day >= today { balance.overtime() } else { balance.difference() }
+        })
+        .sum()
+}
+
+fn days(period: &RangeInclusive<NaiveDate>) -> impl Iterator<Item = NaiveDate> {
+    let end = *period.end();
+    period.start().iter_days().take_while(move |it| *it <= end)
+}
+
+pub fn edit_targets(s: &mut Cursive) {
+    let targets = app_context::fetch(s).targets().unwrap_or_default();
+    let mut form = ListView::new();
+    WEEKDAYS.iter().zip(targets.hours).for_each(|(weekday, hours)| {
+        form.add_child(weekday, EditView::new().content(hours.to_string()).with_name(*weekday).min_width(10));
+    });
+    form.add_child(
+        "Count from",
+        EditView::new().content(targets.start.map(|it| it.to_string()).unwrap_or_default()).with_name(TARGETS_START).min_width(10)
+    );
+    form.add_child(
+        "Vacation days",
+        EditView::new().content(targets.vacation_days.map(|it| it.to_string()).unwrap_or_default()).with_name(VACATION_DAYS).min_width(10)
+    );
+    s.add_layer(
+        Dialog::around(form)
+            .title("Working time targets (hours)")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("No targets", |s| {
+                app_context::fetch(s).set_targets(None);
+                s.pop_layer();
+                stats_view::update_stats(s);
+            })
+            .button("Ok", submit_targets)
+    );
+}
+
+fn submit_targets(s: &mut Cursive) {
+    let mut targets = app_context::fetch(s).targets().unwrap_or_default();
+    for (i, weekday) in WEEKDAYS.iter().enumerate() {
+        let value = s.call_on_name(weekday, |e: &mut EditView| e.get_content().to_string()).expect("Weekday hours should be defined");
+        match value.trim().parse::<f64>().ok().filter(|it| (0.0..=24.0).contains(it)) {
+            Some(hours) => targets.hours[i] = hours,
+            None => {
+                s.add_layer(Dialog::info(format!("Invalid hours for {}: {}", weekday, value)));
+                return;
+            },
+        }
+    }
+    let start = s.call_on_name(TARGETS_START, |e: &mut EditView| e.get_content().trim().to_string()).expect("Start should be defined");
+    targets.start = match start.as_str() {
+        "" => None,
+        value => match time_picker::parse_date(value) {
+            Some(date) => Some(date),
+            None => {
+                s.add_layer(Dialog::info(format!("Invalid date: {}", value)));
+                return;
+            },
+        },
+    };
+    let vacation_days = s.call_on_name(VACATION_DAYS, |e: &mut EditView| e.get_content().trim().to_string()).expect("Vacation days should be defined");
+    targets.vacation_days = match vacation_days.as_str() {
+        "" => None,
+        value => match value.parse::<f64>().ok().filter(|it| *it >= 0.0) {
+            Some(days) => Some(days),
+            None => {
+                s.add_layer(Dialog::info(format!("Invalid vacation days: {}", value)));
+                return;
+            },
+        },
+    };
+    app_context::fetch(s).set_targets(Some(targets));
+    s.pop_layer();
+    stats_view::update_stats(s);
+}
+
+/// Parses hours per weekday from Monday, like `8,8,8,8,4`. The days not given have no target.
+pub fn parse_hours(value: &str) -> Result<[f64; 7], String> {
+    let mut hours = [0.0; 7];
+    let values: Vec<&str> = value.split(',').map(str::trim).collect();
+    if values.len() > 7 {
+        return Err(format!("More than 7 weekdays in {}", value));
+    }
+    for (i, value) in values.into_iter().enumerate() {
+        hours[i] = value.parse::<f64>().ok()
+            .filter(|it| (0.0..=24.0).contains(it))
+            .ok_or_else(|| format!("Invalid hours for {}: {}", WEEKDAYS[i], value))?;
+    }
+    Ok(hours)
+}
+
+#[cfg(test)]
+mod targets_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::ClockEntry;
+    use crate::absences::{Absence, AbsenceKind};
+    use crate::targets::{balance, flex_balance, parse_hours, Targets, week};
+
+    fn entry(day: u32, hours: u32) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, day),
+            from: NaiveTime::from_hms(8, 0, 0),
+            to: NaiveTime::from_hms(8 + hours, 0, 0),
+            ..ClockEntry::default()
+        }
+    }
+
+    fn targets() -> Targets {
+        Targets {
+            hours: [8.0, 8.0, 8.0, 8.0, 4.0, 0.0, 0.0],
+            start: None,
+            vacation_days: None,
+            holidays: Vec::default(),
+        }
+    }
+
+    fn absences() -> Vec<Absence> {
+        vec![
+            Absence { date: NaiveDate::from_ymd(2022, 3, 16), kind: AbsenceKind::PublicHoliday, half_day: false, note: String::new() },
+            Absence { date: NaiveDate::from_ymd(2022, 3, 21), kind: AbsenceKind::Vacation, half_day: true, note: String::new() },
+        ]
+    }
+
+    #[test]
+    fn targets_per_weekday_and_absence() {
+        let targets = targets();
+        assert_eq!(targets.target_seconds(NaiveDate::from_ymd(2022, 3, 14), &absences()), 8 * 3600);
+        assert_eq!(targets.target_seconds(NaiveDate::from_ymd(2022, 3, 16), &absences()), 0);
+        assert_eq!(targets.target_seconds(NaiveDate::from_ymd(2022, 3, 18), &absences()), 4 * 3600);
+        assert_eq!(targets.target_seconds(NaiveDate::from_ymd(2022, 3, 19), &absences()), 0);
+        assert_eq!(targets.target_seconds(NaiveDate::from_ymd(2022, 3, 21), &absences()), 4 * 3600);
+    }
+
+    #[test]
+    fn balances_a_week() {
+        let entries = vec![entry(14, 9), entry(15, 7), entry(16, 2), entry(17, 8), entry(21, 8)];
+        let week = week(NaiveDate::from_ymd(2022, 3, 17));
+        assert_eq!(week, NaiveDate::from_ymd(2022, 3, 14)..=NaiveDate::from_ymd(2022, 3, 20));
+        let balance = balance(&entries, &targets(), &absences(), week);
+        assert_eq!(balance.target_seconds, 28 * 3600);
+        assert_eq!(balance.worked_seconds, 26 * 3600);
+        assert_eq!(balance.remaining(), 2 * 3600);
+    }
+
+    #[test]
+    fn flex_balance_ignores_the_missing_hours_of_today() {
+        let entries = vec![entry(14, 9), entry(15, 7), entry(16, 2), entry(17, 3)];
+        let today = NaiveDate::from_ymd(2022, 3, 17);
+        assert_eq!(flex_balance(&entries, &targets(), &absences(), today, today), 2 * 3600);
+        assert_eq!(flex_balance(&entries, &targets(), &absences(), today, NaiveDate::from_ymd(2022, 3, 18)), -3 * 3600);
+        let started = Targets { start: Some(NaiveDate::from_ymd(2022, 3, 15)), ..targets() };
+        assert_eq!(flex_balance(&entries, &started, &absences(), today, today), 3600);
+    }
+
+    #[test]
+    fn parses_hours_per_weekday() {
+        assert_eq!(parse_hours("8, 8,8,8,4.5").unwrap(), [8.0, 8.0, 8.0, 8.0, 4.5, 0.0, 0.0]);
+        assert!(parse_hours("8,25").unwrap_err().contains("Tuesday"));
+        assert!(parse_hours("1,1,1,1,1,1,1,1").is_err());
+    }
+}
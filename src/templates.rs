@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Dialog, OnEventView, SelectView};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_context, clock_entries_table, ClockEntry, format, Granularity, granularity_picker, record, tags, time_picker};
+
+const TEMPLATES_LIST: &str = "templates_list";
+/// How many days it takes for a use of a description to count half as much in the ranking
+const HALF_LIFE_DAYS: f64 = 14.0;
+const RECENT_TEMPLATES: usize = 10;
+
+/// A favorite entry, to add entries or start recordings with its description, project and tags.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Template {
+    pub description: String,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub billable: bool,
+    /// Length of the entries added from the template
+    #[serde(default)]
+    pub minutes: Option<i64>,
+}
+
+impl Template {
+    pub fn from_entry(entry: &ClockEntry) -> Template {
+        Template {
+            description: entry.description.trim().to_string(),
+            project: entry.project.clone(),
+            tags: entry.tags.clone(),
+            billable: entry.billable,
+            minutes: Some(entry.duration().num_minutes()).filter(|it| *it > 0),
+        }
+    }
+
+    /// A new entry from the template starting at `from`, an hour long if the template has no length.
+    pub fn entry(&self, date: NaiveDate, from: NaiveTime, granularity: Granularity) -> ClockEntry {
+        ClockEntry {
+            date,
+            from,
+            to: from + Duration::minutes(self.minutes.unwrap_or(60)),
+            description: self.description.clone(),
+            granularity,
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+            billable: self.billable,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn label(&self, granularity: Granularity) -> String {
+        let mut label = self.description.clone();
+        if let Some(project) = &self.project {
+            label += &format!("  @{}", project);
+        }
+        let inline_tags = tags::parse_inline_tags(&self.description);
+        let tags: Vec<String> = self.tags.iter().filter(|it| !inline_tags.contains(it)).cloned().collect();
+        if !tags.is_empty() {
+            label += &format!("  {}", tags::format_tags(&tags));
+        }
+        if let Some(minutes) = self.minutes {
+            label += &format!("  ({})", format::format_hms(granularity, minutes * 60));
+        }
+        label
+    }
+}
+
+/// The descriptions of the entries, the most used first. Recent uses count more, see `HALF_LIFE_DAYS`.
+pub fn ranked_descriptions(entries: &[ClockEntry], today: NaiveDate) -> Vec<String> {
+    let mut scores: HashMap<&str, (f64, NaiveDate)> = HashMap::new();
+    for entry in entries {
+        let description = entry.description.trim();
+        if description.is_empty() {
+            continue;
+        }
+        let days = (today - entry.date).num_days().max(0) as f64;
+        let score = scores.entry(description).or_insert((0.0, entry.date));
+        score.0 += 0.5f64.powf(days / HALF_LIFE_DAYS);
+        score.1 = score.1.max(entry.date);
+    }
+    let mut ranked: Vec<(&str, (f64, NaiveDate))> = scores.into_iter().collect();
+    ranked.sort_by(|(a, (a_score, a_last)), (b, (b_score, b_last))| {
+        b_score.partial_cmp(a_score).unwrap_or(Ordering::Equal)
+            .then(b_last.cmp(a_last))
+            .then(a.cmp(b))
+    });
+    ranked.into_iter().map(|(description, _)| description.to_string()).collect()
+}
+
+/// The ranked descriptions that complete the typed one.
+pub fn suggestions(ranked: &[String], typed: &str) -> Vec<String> {
+    let typed = typed.trim_start().to_lowercase();
+    if typed.is_empty() {
+        return Vec::new();
+    }
+    ranked.iter()
+        .filter(|it| it.to_lowercase().starts_with(&typed) && it.to_lowercase() != typed)
+        .cloned()
+        .collect()
+}
+
+/// Templates from the latest entries of the top ranked descriptions that aren't favorites yet.
+pub fn recent(entries: &[ClockEntry], favorites: &[Template], today: NaiveDate, count: usize) -> Vec<Template> {
+    ranked_descriptions(entries, today).into_iter()
+        .filter(|description| !favorites.iter().any(|it| it.description == *description))
+        .filter_map(|description| entries.iter()
+            .filter(|it| it.description.trim() == description)
+            .max_by_key(|it| (it.date, it.from))
+            .map(Template::from_entry))
+        .take(count)
+        .collect()
+}
+
+pub fn templates(s: &mut Cursive) {
+    let favorites = app_context::fetch(s).templates();
+    let recent = recent(&clock_entries_table::get_visible_clock_entries(s), &favorites, time_picker::today(), RECENT_TEMPLATES);
+    let granularity = granularity_picker::get_granularity(s);
+    let mut list = SelectView::new();
+    favorites.into_iter().for_each(|it| list.add_item(format!("★ {}", it.label(granularity)), it));
+    recent.into_iter().for_each(|it| list.add_item(format!("  {}", it.label(granularity)), it));
+    s.add_layer(
+        Dialog::around(
+            OnEventView::new(list.on_submit(|s, _: &Template| add_entry(s)).with_name(TEMPLATES_LIST).min_size((60, 5)))
+                .on_event('a', add_entry)
+                .on_event('r', start_recording)
+                .on_event('f', toggle_favorite)
+                .on_event(Key::Del, toggle_favorite)
+        )
+            .title("Templates (★ favorites)")
+            .button("(A)dd entry", add_entry)
+            .button("(R)ecord", start_recording)
+            .button("(F)avorite", toggle_favorite)
+            .button("Close", |s| { s.pop_layer(); })
+    );
+}
+
+fn selected_template(s: &mut Cursive) -> Option<Template> {
+    s.call_on_name(TEMPLATES_LIST, |l: &mut SelectView<Template>| l.selection())
+        .expect("The templates list should be defined")
+        .map(|it| (*it).clone())
+}
+
+/// Opens the entry form with the template, starting where the last entry of today ends, or now.
+fn add_entry(s: &mut Cursive) {
+    if let Some(template) = selected_template(s) {
+        let granularity = granularity_picker::get_granularity(s);
+        let today = time_picker::today();
+        let now = time_picker::now_naive_time(granularity);
+        let from = clock_entries_table::get_visible_clock_entries(s).iter()
+            .filter(|it| it.date == today && it.to <= now)
+            .map(|it| it.to)
+            .max()
+            .unwrap_or(now);
+        s.pop_layer();
+        clock_entries_table::open_new_entry(s, template.entry(today, from, granularity));
+    }
+}
+
+fn start_recording(s: &mut Cursive) {
+    if let Some(template) = selected_template(s) {
+        s.pop_layer();
+        record::start_recording_from(s, &template);
+    }
+}
+
+fn toggle_favorite(s: &mut Cursive) {
+    if let Some(template) = selected_template(s) {
+        let mut favorites = app_context::fetch(s).templates();
+        match favorites.iter().position(|it| *it == template) {
+            Some(index) => { favorites.remove(index); },
+            None => favorites.push(template),
+        }
+        app_context::fetch(s).set_templates(favorites);
+        s.pop_layer();
+        templates(s);
+    }
+}
+
+#[cfg(test)]
+mod templates_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::templates::{ranked_descriptions, recent, suggestions, Template};
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd(2022, 3, 14)
+    }
+
+    fn entry(days_ago: i64, description: &str) -> ClockEntry {
+        ClockEntry {
+            date: today() - chrono::Duration::days(days_ago),
+            from: NaiveTime::from_hms(9, 0, 0),
+            to: NaiveTime::from_hms(9, 30, 0),
+            description: description.to_string(),
+            ..ClockEntry::default()
+        }
+    }
+
+    #[test]
+    fn ranks_descriptions_by_frequency_and_recency() {
+        let entries = vec![
+            entry(60, "Code review"), entry(61, "Code review"), entry(62, "Code review"),
+            entry(0, "Coding"), entry(1, "Coding"),
+            entry(2, "Standup"), entry(3, "Standup"), entry(4, "Standup"),
+            entry(5, "Coffee"), entry(0, " "),
+        ];
+        assert_eq!(ranked_descriptions(&entries, today()), vec!["Standup", "Coding", "Coffee", "Code review"]);
+    }
+
+    #[test]
+    fn suggests_descriptions_completing_the_typed_one() {
+        let ranked = vec!["Standup".to_string(), "Coding".to_string(), "Code review".to_string()];
+        assert_eq!(suggestions(&ranked, "co"), vec!["Coding", "Code review"]);
+        assert_eq!(suggestions(&ranked, "code"), vec!["Code review"]);
+        assert!(suggestions(&ranked, "coding").is_empty());
+        assert!(suggestions(&ranked, "").is_empty());
+    }
+
+    #[test]
+    fn makes_templates_of_recent_entries() {
+        let entries = vec![
+            ClockEntry { project: Some("Website".to_string()), ..entry(3, "Coding") },
+            ClockEntry { to: NaiveTime::from_hms(10, 30, 0), ..entry(1, "Coding") },
+            entry(2, "Standup"),
+        ];
+        let favorites = vec![Template::from_entry(&entries[2])];
+
+        let recent = recent(&entries, &favorites, today(), 10);
+
+        assert_eq!(recent, vec![Template { description: "Coding".to_string(), project: None, tags: vec![], billable: false, minutes: Some(90) }]);
+        let new_entry = recent[0].entry(today(), NaiveTime::from_hms(13, 0, 0), Granularity::Detailed);
+        assert_eq!((new_entry.from, new_entry.to), (NaiveTime::from_hms(13, 0, 0), NaiveTime::from_hms(14, 30, 0)));
+        assert_eq!(new_entry.description, "Coding");
+    }
+}
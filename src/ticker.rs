@@ -0,0 +1,22 @@
+use cursive::Cursive;
+use scheduled_thread_pool::ScheduledThreadPool;
+
+use crate::{app_context, stats_view};
+
+/// Refreshes the elapsed time of the ongoing recording and the timers every second. The ticker stops when the returned pool is dropped.
+pub fn start_ticker_loop(siv: &Cursive) -> ScheduledThreadPool {
+    let cb_sink = siv.cb_sink().clone();
+    let thread_pool = ScheduledThreadPool::new(1);
+    thread_pool.execute_at_fixed_rate(
+        core::time::Duration::from_secs(1),
+        core::time::Duration::from_secs(1),
+        move || { cb_sink.send(Box::new(tick)).unwrap() }
+    );
+    thread_pool
+}
+
+fn tick(s: &mut Cursive) {
+    if app_context::fetch(s).is_recording() || !app_context::fetch(s).timers().is_empty() {
+        stats_view::update_recording_status(s);
+    }
+}
@@ -1,14 +1,75 @@
-use chrono::Local;
+use chrono::{Duration, Local};
 use chrono::prelude::*;
 use cursive::align::HAlign;
 use cursive::Cursive;
+use cursive::event::Key;
 use cursive::traits::*;
-use cursive::views::{NamedView, ResizedView, SelectView};
+use cursive::views::{EditView, NamedView, OnEventView, ResizedView, SelectView};
 use crate::clock_entries_table::ClockEntryColumn;
 
 use crate::{format, granularity_picker};
+use crate::config::TimeInput;
 use crate::granularity_picker::Granularity;
 
+/// The time field of the entry form, typed or picked from a list depending on the `time_input` setting.
+pub fn time_input(col: ClockEntryColumn, value: Option<NaiveTime>, granularity: Granularity, kind: TimeInput) -> Box<dyn View> {
+    match kind {
+        TimeInput::Typed => Box::new(typed_time_input(col, value, granularity)),
+        TimeInput::Picker => Box::new(time_picker_input(col, value, granularity)),
+    }
+}
+
+/// A field taking the times of `parse_time_input`. Enter snaps it to the granularity, up and down step it by one
+/// granularity step.
+pub fn typed_time_input(col: ClockEntryColumn, value: Option<NaiveTime>, granularity: Granularity) -> ResizedView<OnEventView<NamedView<EditView>>> {
+    let content = value.map(|it| format::format_naive_time(granularity, it)).unwrap_or_else(|| now(granularity));
+    let edit_view = EditView::new()
+        .content(content)
+        .on_submit(move |s, _| {
+            if let Some(time) = typed_time_value(s, col) {
+                set_typed_time(s, col, time);
+            }
+        })
+        .with_name(col.as_str());
+    OnEventView::new(edit_view)
+        .on_event(Key::Up, move |s| step_typed_time(s, col, 1))
+        .on_event(Key::Down, move |s| step_typed_time(s, col, -1))
+        .fixed_width(15)
+}
+
+fn step_typed_time(s: &mut Cursive, col: ClockEntryColumn, steps: i32) {
+    let granularity = granularity_picker::get_granularity(s);
+    if let Some(time) = typed_time_value(s, col) {
+        let (stepped, overflow) = time.overflowing_add_signed(granularity_picker::step(granularity) * steps);
+        if overflow == 0 {
+            set_typed_time(s, col, stepped);
+        }
+    }
+}
+
+fn set_typed_time(s: &mut Cursive, col: ClockEntryColumn, time: NaiveTime) {
+    let granularity = granularity_picker::get_granularity(s);
+    s.call_on_name(col.as_str(), |e: &mut EditView| e.set_content(format::format_naive_time(granularity, time)));
+}
+
+/// The typed time, with offsets in `To` counted from `From`, and in `From` from now. Nothing when it's not a valid time,
+/// or when the field is a picker.
+pub fn typed_time_value(s: &mut Cursive, col: ClockEntryColumn) -> Option<NaiveTime> {
+    let granularity = granularity_picker::get_granularity(s);
+    let content = s.call_on_name(col.as_str(), |e: &mut EditView| e.get_content().to_string())?;
+    let now = now_naive_time(Granularity::Scientific);
+    let reference = match col {
+        ClockEntryColumn::To => time_picker_value(s, ClockEntryColumn::From),
+        _ => now,
+    };
+    parse_time_input(&content, granularity, reference, now)
+}
+
+/// Whether the time field holds a valid time, a picked one always is.
+pub fn is_valid_time(s: &mut Cursive, col: ClockEntryColumn) -> bool {
+    s.call_on_name(col.as_str(), |_: &mut EditView| ()).is_none() || typed_time_value(s, col).is_some()
+}
+
 pub fn time_picker_input(col: ClockEntryColumn, value: Option<NaiveTime>, granularity: Granularity) -> NamedView<ResizedView<SelectView>> {
     let content = if value.is_some() {
         value.map(|it| format::format_naive_time(granularity, it)).expect("Time input entry should be some value")
@@ -29,13 +90,85 @@ pub fn time_picker_input(col: ClockEntryColumn, value: Option<NaiveTime>, granul
         .with_name(col.as_str())
 }
 
+/// The time of a typed or picked time field, midnight when the typed time isn't valid.
 pub fn time_picker_value(s: &mut Cursive, col: ClockEntryColumn) -> NaiveTime {
+    if s.call_on_name(col.as_str(), |_: &mut EditView| ()).is_some() {
+        return typed_time_value(s, col).unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0));
+    }
     let granularity = granularity_picker::get_granularity(s);
     s.call_on_name(col.as_str(), |e: &mut ResizedView<SelectView>| {
         parse_time(granularity, e.get_inner().selection().expect("Nothing selected in time field").as_str())
     }).unwrap_or_else(|| panic!("{} should be defined", col.as_str()))
 }
 
+/// Parses a typed time, snapped to the granularity: `9`, `930`, `9:30`, `9:30:15`, `now`, `now-10m`, or an offset
+/// from `reference` like `+15m`, `+1h30m` or `-90s`. Offsets without a unit are minutes.
+pub fn parse_time_input(value: &str, granularity: Granularity, reference: NaiveTime, now: NaiveTime) -> Option<NaiveTime> {
+    let value = value.trim().to_lowercase();
+    let time = match value.strip_prefix("now") {
+        Some("") => now,
+        Some(offset) => add_offset(now, offset.trim())?,
+        None if value.starts_with('+') || value.starts_with('-') => add_offset(reference, &value)?,
+        None => parse_clock(&value)?,
+    };
+    Some(granularity_picker::normalize(time, granularity))
+}
+
+fn parse_clock(value: &str) -> Option<NaiveTime> {
+    let number = |it: &str| if !it.is_empty() && it.len() <= 2 && it.chars().all(|c| c.is_ascii_digit()) { it.parse::<u32>().ok() } else { None };
+    match value.split(':').collect::<Vec<&str>>().as_slice() {
+        [digits] if digits.len() > 2 => {
+            let (hours, minutes) = digits.split_at(digits.len() - 2);
+            NaiveTime::from_hms_opt(number(hours)?, number(minutes)?, 0)
+        },
+        [hours] => NaiveTime::from_hms_opt(number(hours)?, 0, 0),
+        [hours, minutes] => NaiveTime::from_hms_opt(number(hours)?, number(minutes)?, 0),
+        [hours, minutes, seconds] => NaiveTime::from_hms_opt(number(hours)?, number(minutes)?, number(seconds)?),
+        _ => None,
+    }
+}
+
+/// Adds an offset like `+15m` or `-1h30m`, nothing when it leaves the day.
+fn add_offset(time: NaiveTime, offset: &str) -> Option<NaiveTime> {
+    let (sign, duration) = match offset.split_at_checked(1)? {
+        ("+", duration) => (1, duration),
+        ("-", duration) => (-1, duration),
+        _ => return None,
+    };
+    let (result, overflow) = time.overflowing_add_signed(parse_duration(duration.trim())? * sign);
+    if overflow == 0 { Some(result) } else { None }
+}
+
+/// Parses a duration like `90`, in minutes, or `1h30m`, nothing when it's longer than a day.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let seconds = if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        value.parse::<i64>().ok()?.checked_mul(60)?
+    } else {
+        let mut seconds: i64 = 0;
+        let mut number = String::new();
+        for c in value.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let amount: i64 = number.parse().ok()?;
+            number.clear();
+            let unit = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None,
+            };
+            seconds = seconds.checked_add(amount.checked_mul(unit)?)?;
+        }
+        if !number.is_empty() || value.is_empty() {
+            return None;
+        }
+        seconds
+    };
+    if seconds <= 24 * 3600 { Some(Duration::seconds(seconds)) } else { None }
+}
+
 pub fn parse_time(granularity: Granularity, value: &str) -> NaiveTime {
     let time = if granularity == Granularity::Scientific {
         NaiveTime::parse_from_str(value, "%H:%M:%S").expect("Unable to parse time from selection")
@@ -58,20 +191,27 @@ pub fn now(granularity: Granularity) -> String {
     format::format_clock(granularity, now.hour(), now.minute(), now.second())
 }
 
+pub fn today() -> NaiveDate {
+    Local::today().naive_local()
+}
+
+/// Parses `today`, `yesterday`, `tomorrow` or a `YYYY-MM-DD` date.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    match value.trim() {
+        "today" => Some(today()),
+        "yesterday" => today().pred_opt(),
+        "tomorrow" => today().succ_opt(),
+        other => NaiveDate::parse_from_str(other, "%Y-%m-%d").ok(),
+    }
+}
+
 pub fn now_naive_time(granularity: Granularity) -> NaiveTime {
     let now_string = now(granularity);
     parse_time(granularity, now_string.as_str())
 }
 
 fn daily_clock_entries(granularity: Granularity) -> Vec<String> {
-    let minute_step = match granularity {
-        Granularity::Relaxed => 60,
-        Granularity::Reasonable => 30,
-        Granularity::Detailed => 15,
-        Granularity::Paranoid => 5,
-        Granularity::Ocd => 1,
-        Granularity::Scientific => 1,
-    };
+    let minute_step = (granularity_picker::step(granularity).num_minutes() as usize).max(1);
     let second_step = match granularity {
         Granularity::Scientific => 1,
         _ => 60,
@@ -167,6 +307,63 @@ mod parse_time_test {
     }
 }
 
+#[cfg(test)]
+mod parse_time_input_test {
+    use chrono::{Duration, NaiveTime};
+
+    use crate::Granularity;
+    use crate::time_picker::{parse_duration, parse_time_input};
+
+    fn parse(value: &str, granularity: Granularity) -> Option<NaiveTime> {
+        parse_time_input(value, granularity, NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(14, 7, 30))
+    }
+
+    #[test]
+    fn parses_clock_times() {
+        assert_eq!(parse("9", Granularity::Scientific), Some(NaiveTime::from_hms(9, 0, 0)));
+        assert_eq!(parse("930", Granularity::Scientific), Some(NaiveTime::from_hms(9, 30, 0)));
+        assert_eq!(parse("1745", Granularity::Scientific), Some(NaiveTime::from_hms(17, 45, 0)));
+        assert_eq!(parse(" 9:30 ", Granularity::Scientific), Some(NaiveTime::from_hms(9, 30, 0)));
+        assert_eq!(parse("9:30:15", Granularity::Scientific), Some(NaiveTime::from_hms(9, 30, 15)));
+        assert_eq!(parse("25", Granularity::Scientific), None);
+        assert_eq!(parse("9:75", Granularity::Scientific), None);
+        assert_eq!(parse("12345", Granularity::Scientific), None);
+        assert_eq!(parse("nine", Granularity::Scientific), None);
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse("+15m", Granularity::Scientific), Some(NaiveTime::from_hms(9, 15, 0)));
+        assert_eq!(parse("+1h30m", Granularity::Scientific), Some(NaiveTime::from_hms(10, 30, 0)));
+        assert_eq!(parse("-90s", Granularity::Scientific), Some(NaiveTime::from_hms(8, 58, 30)));
+        assert_eq!(parse("+45", Granularity::Scientific), Some(NaiveTime::from_hms(9, 45, 0)));
+        assert_eq!(parse("now", Granularity::Scientific), Some(NaiveTime::from_hms(14, 7, 30)));
+        assert_eq!(parse("now-10m", Granularity::Scientific), Some(NaiveTime::from_hms(13, 57, 30)));
+        assert_eq!(parse("now + 1h", Granularity::Scientific), Some(NaiveTime::from_hms(15, 7, 30)));
+        assert_eq!(parse("-10h", Granularity::Scientific), None);
+        assert_eq!(parse("+15x", Granularity::Scientific), None);
+        assert_eq!(parse("+", Granularity::Scientific), None);
+    }
+
+    #[test]
+    fn rejects_durations_longer_than_a_day() {
+        assert_eq!(parse_duration("90"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("24h"), Some(Duration::hours(24)));
+        assert_eq!(parse_duration("24h1s"), None);
+        assert_eq!(parse_duration("9999999999999999"), None);
+        assert_eq!(parse_duration("9999999999999999h"), None);
+        assert_eq!(parse_duration("99999999999999999999m"), None);
+        assert_eq!(parse("+9999999999999999", Granularity::Scientific), None);
+    }
+
+    #[test]
+    fn snaps_to_the_granularity() {
+        assert_eq!(parse("9:40", Granularity::Detailed), Some(NaiveTime::from_hms(9, 30, 0)));
+        assert_eq!(parse("now-10m", Granularity::Reasonable), Some(NaiveTime::from_hms(13, 30, 0)));
+        assert_eq!(parse("9:30:15", Granularity::Ocd), Some(NaiveTime::from_hms(9, 30, 0)));
+    }
+}
+
 #[cfg(test)]
 mod daily_clock_entries_test {
     use crate::Granularity;
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use chrono::{NaiveDate, NaiveTime};
+use cursive::Cursive;
+use cursive::views::Dialog;
+use cursive_table_view::TableView;
+use uuid::Uuid;
+
+use crate::{app_context, CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, format, Granularity, stats_view};
+use crate::clock_entries_table::{ClockEntryColumn, Row};
+use crate::history::Command;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    pub first: ClockEntry,
+    pub second: ClockEntry,
+}
+
+impl Overlap {
+    pub fn from(&self) -> NaiveTime {
+        self.second.from
+    }
+
+    pub fn to(&self) -> NaiveTime {
+        self.first.to.min(self.second.to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gap {
+    pub date: NaiveDate,
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+    /// The entry ending where the gap starts
+    pub previous: ClockEntry,
+}
+
+impl Gap {
+    /// Identifies the gap row in the table, the previous entry's other fields may have changed since.
+    fn key(&self) -> (NaiveDate, NaiveTime, NaiveTime, Uuid) {
+        (self.date, self.from, self.to, self.previous.id)
+    }
+}
+
+/// The entries of every day that count toward the totals, ordered by their start.
+fn days(entries: &[ClockEntry]) -> BTreeMap<NaiveDate, Vec<&ClockEntry>> {
+    let mut days: BTreeMap<NaiveDate, Vec<&ClockEntry>> = BTreeMap::new();
+    entries.iter()
+        .filter(|it| !it.excluded_from_totals)
+        .for_each(|it| days.entry(it.date).or_default().push(it));
+    days.values_mut().for_each(|entries| entries.sort_by_key(|it| (it.from, it.to)));
+    days
+}
+
+pub fn overlaps(entries: &[ClockEntry]) -> Vec<Overlap> {
+    let mut overlaps = Vec::new();
+    for entries in days(entries).values() {
+        for (i, first) in entries.iter().enumerate() {
+            entries[i + 1..].iter()
+                .take_while(|second| second.from < first.to)
+                .filter(|second| second.to > second.from)
+                .for_each(|second| overlaps.push(Overlap { first: (*first).clone(), second: (*second).clone() }));
+        }
+    }
+    overlaps
+}
+
+pub fn gaps(entries: &[ClockEntry]) -> Vec<Gap> {
+    let mut gaps = Vec::new();
+    for (date, entries) in days(entries) {
+        let mut previous: Option<&ClockEntry> = None;
+        for entry in entries {
+            match previous {
+                Some(it) if entry.from > it.to => {
+                    gaps.push(Gap { date, from: it.to, to: entry.from, previous: it.clone() });
+                    previous = Some(entry);
+                },
+                Some(it) if entry.to <= it.to => {},
+                _ => previous = Some(entry),
+            }
+        }
+    }
+    gaps
+}
+
+/// The entry shortened so it no longer overlaps the other entries of its day, or nothing when it's covered entirely.
+pub fn trimmed(entry: &ClockEntry, entries: &[ClockEntry]) -> Option<ClockEntry> {
+    let mut others: Vec<&ClockEntry> = entries.iter()
+        .filter(|it| it.id != entry.id && it.date == entry.date && it.to > it.from && !it.excluded_from_totals)
+        .collect();
+    others.sort_by_key(|it| it.from);
+    let from = others.iter().fold(entry.from, |from, it| if it.from <= from && it.to > from { it.to } else { from });
+    let to = others.iter()
+        .map(|it| it.from)
+        .filter(|it| *it >= from)
+        .fold(entry.to, NaiveTime::min);
+    if from < to {
+        Some(ClockEntry { from, to, ..entry.clone() })
+    } else {
+        None
+    }
+}
+
+/// Lists the overlaps and gaps of the period, one per line.
+pub fn render_issues(entries: &[ClockEntry], period: RangeInclusive<NaiveDate>, granularity: Granularity) -> String {
+    let entries: Vec<ClockEntry> = entries.iter().filter(|it| period.contains(&it.date)).cloned().collect();
+    let time = |it: NaiveTime| format::format_naive_time(granularity, it);
+    let mut issues: Vec<(NaiveDate, NaiveTime, String)> = overlaps(&entries).into_iter()
+        .map(|it| (
+            it.first.date,
+            it.from(),
+            format!("{}  {} - {}  Overlap of '{}' and '{}'", it.first.date, time(it.from()), time(it.to()), it.first.description, it.second.description)
+        ))
+        .collect();
+    issues.extend(gaps(&entries).into_iter().map(|it| (
+        it.date,
+        it.from,
+        format!("{}  {} - {}  Gap after '{}'", it.date, time(it.from), time(it.to), it.previous.description)
+    )));
+    issues.sort_by_key(|it| (it.0, it.1));
+    issues.into_iter().map(|(_, _, line)| line + "\n").collect()
+}
+
+/// Marks the overlaps and keeps a row for every gap of the days in the table while it's sorted by time. The rows are
+/// updated in place, so the selection and scrolling stay. Both are found among all the entries, a filter hiding some
+/// doesn't make up gaps.
+pub fn update_table(s: &mut Cursive) {
+    let entries = clock_entries_table::get_clock_entries(s);
+    let overlapping: HashSet<Uuid> = overlaps(&entries).iter()
+        .flat_map(|it| [it.first.id, it.second.id])
+        .collect();
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        let by_time = matches!(t.order(), Some((ClockEntryColumn::Date | ClockEntryColumn::From, _)));
+        let days: HashSet<NaiveDate> = t.borrow_items().iter().filter_map(Row::entry).map(|it| it.date).collect();
+        let mut gaps: HashMap<_, Gap> = gaps(&entries).into_iter()
+            .filter(|it| by_time && days.contains(&it.date))
+            .map(|it| (it.key(), it))
+            .collect();
+        let mut stale = vec![];
+        for (index, row) in t.borrow_items_mut().iter_mut().enumerate() {
+            match row {
+                Row::Entry { entry, overlaps, .. } => *overlaps = overlapping.contains(&entry.id),
+                Row::Gap(gap) => match gaps.remove(&gap.key()) {
+                    Some(current) => *gap = current,
+                    None => stale.push(index),
+                },
+            }
+        }
+        // From the last one, so the indexes of the others stay the same
+        stale.into_iter().rev().for_each(|index| { t.remove_item(index); });
+        gaps.into_values().for_each(|gap| t.insert_item(Row::Gap(gap)));
+    }).expect("Unable to get clock entries table");
+}
+
+fn selected_row(s: &mut Cursive) -> Option<Row> {
+    s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+        t.item().and_then(|index| t.borrow_item(index)).cloned()
+    }).expect("Unable to get clock entries table")
+}
+
+/// The gap of the selected gap row, or the one right before the selected entry.
+fn selected_gap(s: &mut Cursive) -> Option<Gap> {
+    let row = selected_row(s)?;
+    gaps(&clock_entries_table::get_clock_entries(s)).into_iter()
+        .find(|it| match &row {
+            Row::Gap(gap) => it.date == gap.date && it.from == gap.from,
+            Row::Entry { entry, .. } => it.date == entry.date && it.to == entry.from,
+        })
+}
+
+pub fn fill_gap(s: &mut Cursive) {
+    match selected_gap(s) {
+        Some(gap) => clock_entries_table::open_new_entry(s, ClockEntry {
+            date: gap.date,
+            from: gap.from,
+            to: gap.to,
+            granularity: gap.previous.granularity,
+            project: gap.previous.project.clone(),
+            billable: gap.previous.billable,
+            ..ClockEntry::default()
+        }),
+        None => s.add_layer(Dialog::info("Select a gap, or the entry after it, to fill it.")),
+    }
+}
+
+pub fn extend_previous(s: &mut Cursive) {
+    match selected_gap(s) {
+        Some(gap) => {
+            let before = gap.previous;
+            let mut after = ClockEntry { to: gap.to, ..before.clone() };
+            after.touch();
+            if clock_entries_table::replace_entry(s, after.clone()).is_ok() {
+                app_context::fetch(s).record(Command::Edit { before, after });
+            }
+            stats_view::update_stats(s);
+        },
+        None => s.add_layer(Dialog::info("Select a gap, or the entry after it, to extend the entry before it.")),
+    }
+}
+
+pub fn trim_overlap(s: &mut Cursive) {
+    let before = match selected_row(s) {
+        Some(Row::Entry { entry, overlaps: true, .. }) => entry,
+        _ => {
+            s.add_layer(Dialog::info("Select an overlapping entry to trim it."));
+            return;
+        },
+    };
+    match trimmed(&before, &clock_entries_table::get_clock_entries(s)) {
+        Some(mut after) => {
+            after.touch();
+            if clock_entries_table::replace_entry(s, after.clone()).is_ok() {
+                app_context::fetch(s).record(Command::Edit { before, after });
+            }
+            stats_view::update_stats(s);
+        },
+        None => s.add_layer(Dialog::info(format!("'{}' is covered entirely by other entries.", before.description))),
+    }
+}
+
+#[cfg(test)]
+mod timeline_test {
+    use std::collections::BTreeSet;
+
+    use chrono::{NaiveDate, NaiveTime};
+
+    use cursive::Cursive;
+    use cursive_table_view::{TableView, TableViewItem};
+
+    use crate::{CLOCK_ENTRIES_TABLE, clock_entries_table, ClockEntry, ClockKing, GlobalContext, Granularity};
+    use crate::clock_entries_table::{ClockEntryColumn, Row};
+    use crate::timeline::{gaps, overlaps, render_issues, trimmed, update_table};
+
+    fn entry(from: (u32, u32), to: (u32, u32), description: &str) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(from.0, from.1, 0),
+            to: NaiveTime::from_hms(to.0, to.1, 0),
+            description: description.to_string(),
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn day() -> Vec<ClockEntry> {
+        vec![
+            entry((9, 0), (10, 30), "Coding"),
+            entry((10, 0), (11, 0), "Standup"),
+            entry((11, 0), (12, 0), "Review"),
+            entry((13, 0), (14, 0), "Meeting"),
+            entry((13, 30), (13, 45), "Call"),
+            entry((14, 30), (15, 0), "Deploy"),
+        ]
+    }
+
+    #[test]
+    fn finds_overlaps() {
+        let overlaps: Vec<(String, String)> = overlaps(&day()).into_iter()
+            .map(|it| (it.first.description, it.second.description))
+            .collect();
+        assert_eq!(overlaps, vec![
+            ("Coding".to_string(), "Standup".to_string()),
+            ("Meeting".to_string(), "Call".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn finds_gaps_between_consecutive_entries() {
+        let gaps: Vec<(NaiveTime, NaiveTime, String)> = gaps(&day()).into_iter()
+            .map(|it| (it.from, it.to, it.previous.description))
+            .collect();
+        assert_eq!(gaps, vec![
+            (NaiveTime::from_hms(12, 0, 0), NaiveTime::from_hms(13, 0, 0), "Review".to_string()),
+            (NaiveTime::from_hms(14, 0, 0), NaiveTime::from_hms(14, 30, 0), "Meeting".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn sorts_gap_rows_between_the_entries() {
+        let mut rows: Vec<Row> = day().into_iter().map(Row::from).collect();
+        rows.extend(gaps(&day()).into_iter().map(Row::Gap));
+        rows.sort_by(|a, b| a.cmp(b, ClockEntryColumn::From));
+
+        let descriptions: Vec<&str> = rows.iter()
+            .map(|it| it.entry().map_or("gap", |it| it.description.as_str()))
+            .collect();
+        assert_eq!(descriptions, vec!["Coding", "Standup", "Review", "gap", "Meeting", "Call", "gap", "Deploy"]);
+    }
+
+    #[test]
+    fn leaves_out_entries_excluded_from_the_totals() {
+        let mut day = day();
+        day[1].excluded_from_totals = true;
+        day.push(ClockEntry { excluded_from_totals: true, ..entry((12, 0), (13, 0), "Side timer") });
+
+        assert_eq!(overlaps(&day).len(), 1);
+        assert_eq!(gaps(&day).iter().map(|it| it.from).collect::<Vec<NaiveTime>>(), vec![
+            NaiveTime::from_hms(10, 30, 0),
+            NaiveTime::from_hms(12, 0, 0),
+            NaiveTime::from_hms(14, 0, 0),
+        ]);
+    }
+
+    #[test]
+    fn trims_overlaps() {
+        let day = day();
+        assert_eq!(trimmed(&day[1], &day).map(|it| (it.from, it.to)), Some((NaiveTime::from_hms(10, 30, 0), NaiveTime::from_hms(11, 0, 0))));
+        assert_eq!(trimmed(&day[0], &day).map(|it| (it.from, it.to)), Some((NaiveTime::from_hms(9, 0, 0), NaiveTime::from_hms(10, 0, 0))));
+        assert_eq!(trimmed(&day[4], &day), None);
+    }
+
+    #[test]
+    fn renders_issues_in_order() {
+        let date = NaiveDate::from_ymd(2022, 3, 14);
+        assert_eq!(render_issues(&day(), date..=date, Granularity::Detailed), "\
+2022-03-14  10:00 - 10:30  Overlap of 'Coding' and 'Standup'
+2022-03-14  12:00 - 13:00  Gap after 'Review'
+2022-03-14  13:30 - 13:45  Overlap of 'Meeting' and 'Call'
+2022-03-14  14:00 - 14:30  Gap after 'Meeting'
+");
+        assert_eq!(render_issues(&day(), date.succ()..=date.succ(), Granularity::Detailed), "");
+    }
+
+    #[test]
+    fn updates_the_gap_rows_in_place() {
+        let model = ClockKing { clock_entries: day(), ..ClockKing::default() };
+        let mut s = Cursive::new();
+        s.set_user_data(GlobalContext::new(&model));
+        s.add_layer(clock_entries_table::new(model));
+        let gap_rows = |s: &mut Cursive| s.call_on_name(CLOCK_ENTRIES_TABLE, |t: &mut TableView<Row, ClockEntryColumn>| {
+            t.borrow_items().iter()
+                .filter_map(|it| match it { Row::Gap(gap) => Some(gap.previous.description.clone()), _ => None })
+                .collect::<BTreeSet<String>>()
+        }).unwrap();
+
+        update_table(&mut s);
+        assert_eq!(gap_rows(&mut s), BTreeSet::from(["Meeting".to_string(), "Review".to_string()]));
+
+        let review = day()[2].clone();
+        let renamed = clock_entries_table::get_clock_entries(&mut s).into_iter().find(|it| it.description == review.description).unwrap();
+        clock_entries_table::replace_entry(&mut s, ClockEntry { description: "Code review".to_string(), ..renamed }).unwrap();
+        update_table(&mut s);
+        assert_eq!(gap_rows(&mut s), BTreeSet::from(["Code review".to_string(), "Meeting".to_string()]));
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use cursive::Cursive;
+use cursive::event::Key;
+use cursive::traits::{Nameable, Resizable};
+use cursive::views::{Checkbox, Dialog, EditView, ListView, NamedView, OnEventView, SelectView, TextView};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{app_context, clock_entries_table, clock_entry_form, ClockEntry, format, Granularity, granularity_picker, record, stats_view, time_picker};
+use crate::history::Command;
+use crate::validation::Scope;
+
+pub const TIMERS_PANEL: &str = "TIMERS_PANEL";
+const TIMERS_LIST: &str = "timers_list";
+const TIMER_NAME: &str = "timer_name";
+const TIMER_COUNTS: &str = "timer_counts";
+
+/// A named timer running next to the recording, like for a deploy or a meeting on the side. Its entry has the name as
+/// description and only its start yet.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Timer {
+    pub entry: ClockEntry,
+    /// Whether the running time is part of the total hours
+    pub counts_toward_total: bool,
+}
+
+impl Timer {
+    pub fn elapsed(&self, now: NaiveDateTime) -> Duration {
+        Duration::zero().max(now.signed_duration_since(self.entry.date.and_time(self.entry.from)))
+    }
+
+    pub fn status_line(&self, granularity: Granularity, now: NaiveDateTime) -> String {
+        format!(
+            "'{}' ({} - ..., {}{})",
+            self.entry.description,
+            format::format_naive_time(granularity, self.entry.from),
+            format::format_hms(Granularity::Scientific, self.elapsed(now).num_seconds()),
+            if self.counts_toward_total { "" } else { ", not counted" }
+        )
+    }
+}
+
+/// The running time of the timers that count toward the total.
+pub fn counted(timers: &[Timer], now: NaiveDateTime) -> Duration {
+    timers.iter()
+        .filter(|it| it.counts_toward_total)
+        .fold(Duration::zero(), |total, it| total + it.elapsed(now))
+}
+
+pub fn new() -> NamedView<TextView> {
+    TextView::new("").with_name(TIMERS_PANEL)
+}
+
+/// The status line of each timer, or nothing without timers.
+pub fn panel_lines(timers: &[Timer], granularity: Granularity, now: NaiveDateTime) -> String {
+    if timers.is_empty() {
+        return String::new();
+    }
+    std::iter::once("Timers (w):".to_string())
+        .chain(timers.iter().map(|it| format!("  {}", it.status_line(granularity, now))))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn update_panel(s: &mut Cursive) {
+    let granularity = granularity_picker::get_granularity(s);
+    let lines = panel_lines(&app_context::fetch(s).timers(), granularity, Local::now().naive_local());
+    s.call_on_name(TIMERS_PANEL, move |t: &mut TextView| {
+        t.set_content(lines);
+    });
+}
+
+pub fn timers(s: &mut Cursive) {
+    let granularity = granularity_picker::get_granularity(s);
+    let now = Local::now().naive_local();
+    let mut list = SelectView::new();
+    app_context::fetch(s).timers().into_iter().for_each(|it| list.add_item(it.status_line(granularity, now), it.entry.id));
+    // The keys are on the whole dialog, as an empty list can't be focused
+    s.add_layer(
+        OnEventView::new(
+            Dialog::around(list.on_submit(|s, _: &Uuid| stop_timer(s)).with_name(TIMERS_LIST).min_size((60, 5)))
+                .title("Timers")
+                .button("(N)ew", start_timer)
+                .button("(S)top", stop_timer)
+                .button("(C)ount in total", toggle_counts_toward_total)
+                .button("Discard", discard_timer)
+                .button("Close", |s| { s.pop_layer(); })
+        )
+            .on_event('n', start_timer)
+            .on_event('s', stop_timer)
+            .on_event('c', toggle_counts_toward_total)
+            .on_event(Key::Del, discard_timer)
+    );
+}
+
+fn selected_timer(s: &mut Cursive) -> Option<Timer> {
+    let id = s.call_on_name(TIMERS_LIST, |l: &mut SelectView<Uuid>| l.selection())
+        .expect("The timers list should be defined")?;
+    app_context::fetch(s).timers().into_iter().find(|it| it.entry.id == *id)
+}
+
+fn set_timers(s: &mut Cursive, timers: Vec<Timer>) {
+    app_context::fetch(s).set_timers(timers);
+    stats_view::update_recording_status(s);
+}
+
+/// Asks for the name of the timer, with `#tags` and an `@project` like the next task of a switch.
+fn start_timer(s: &mut Cursive) {
+    s.pop_layer();
+    s.add_layer(
+        Dialog::around(
+            ListView::new()
+                .child("Name", EditView::new().with_name(TIMER_NAME).min_width(40))
+                .child("Counts toward total", Checkbox::new().checked().with_name(TIMER_COUNTS))
+        )
+            .title("Start a timer")
+            .button("Cancel", |s| { s.pop_layer(); })
+            .button("Start", |s| {
+                let name = s.call_on_name(TIMER_NAME, |e: &mut EditView| e.get_content().to_string()).expect("Timer name should be defined");
+                let counts_toward_total = s.call_on_name(TIMER_COUNTS, |c: &mut Checkbox| c.is_checked()).expect("Timer counting should be defined");
+                let granularity = granularity_picker::get_granularity(s);
+                let projects = app_context::fetch(s).projects();
+                match record::parse_next_task(&name, &projects, granularity, time_picker::today(), time_picker::now_naive_time(granularity)) {
+                    Ok(entry) => {
+                        s.pop_layer();
+                        let mut started = app_context::fetch(s).timers();
+                        started.push(Timer { entry, counts_toward_total });
+                        set_timers(s, started);
+                    },
+                    Err(e) => s.add_layer(Dialog::info(e)),
+                }
+            })
+    );
+}
+
+/// Opens the stop form of the selected timer, its entry is added like a stopped recording.
+fn stop_timer(s: &mut Cursive) {
+    if let Some(timer) = selected_timer(s) {
+        s.pop_layer();
+        let granularity = granularity_picker::get_granularity(s);
+        let stopped = ClockEntry {
+            to: time_picker::now_naive_time(granularity),
+            excluded_from_totals: !timer.counts_toward_total,
+            ..timer.entry.clone()
+        };
+        let title = format!("Stop timer '{}'", timer.entry.description);
+        let form = clock_entry_form::new(s, &title, Some(&stopped), Scope::Entry, move |s, entry| add_timer_entry(s, &timer, entry));
+        s.add_layer(form);
+    }
+}
+
+/// Adds the stopped timer as an entry, undoing it starts the timer again. The entry of a timer that didn't count
+/// toward the total is left out of the totals.
+fn add_timer_entry(s: &mut Cursive, timer: &Timer, entry: ClockEntry) {
+    let entry = ClockEntry { excluded_from_totals: !timer.counts_toward_total, ..entry };
+    let remaining = app_context::fetch(s).timers().into_iter().filter(|it| it.entry.id != timer.entry.id).collect();
+    app_context::fetch(s).set_timers(remaining);
+    clock_entries_table::insert_entry(s, entry.clone());
+    app_context::fetch(s).record(Command::StopTimer { timer: timer.clone(), entry });
+    s.pop_layer();
+    stats_view::update_stats(s);
+}
+
+/// Toggles whether the selected timer counts toward the total, and updates its line in place.
+fn toggle_counts_toward_total(s: &mut Cursive) {
+    if let Some(timer) = selected_timer(s) {
+        let toggled = Timer { counts_toward_total: !timer.counts_toward_total, ..timer };
+        let all = app_context::fetch(s).timers().into_iter()
+            .map(|it| if it.entry.id == toggled.entry.id { toggled.clone() } else { it })
+            .collect();
+        set_timers(s, all);
+        let label = toggled.status_line(granularity_picker::get_granularity(s), Local::now().naive_local());
+        s.call_on_name(TIMERS_LIST, |l: &mut SelectView<Uuid>| {
+            if let Some(index) = l.selected_id() {
+                l.remove_item(index);
+                l.insert_item(index, label, toggled.entry.id);
+                l.set_selection(index);
+            }
+        }).expect("The timers list should be defined");
+    }
+}
+
+/// Drops the selected timer without adding an entry.
+fn discard_timer(s: &mut Cursive) {
+    if let Some(timer) = selected_timer(s) {
+        s.add_layer(
+            cursive_extras::confirm_dialog(
+                "Discard timer",
+                format!("Discard the timer '{}' without adding an entry?", timer.entry.description),
+                move |s| {
+                    s.pop_layer();
+                    s.pop_layer();
+                    let remaining = app_context::fetch(s).timers().into_iter().filter(|it| it.entry.id != timer.entry.id).collect();
+                    set_timers(s, remaining);
+                    timers(s);
+                }
+            ));
+    }
+}
+
+#[cfg(test)]
+mod timers_test {
+    use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::timers::{counted, panel_lines, Timer};
+
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd(2022, 3, 14).and_hms(10, 30, 5)
+    }
+
+    fn timer(from: u32, name: &str, counts_toward_total: bool) -> Timer {
+        let entry = ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(from, 0, 0),
+            to: NaiveTime::from_hms(from, 0, 0),
+            description: name.to_string(),
+            ..ClockEntry::default()
+        };
+        Timer { entry, counts_toward_total }
+    }
+
+    #[test]
+    fn counts_only_the_counted_timers() {
+        let timers = vec![timer(10, "Deploy", true), timer(9, "All hands", false)];
+
+        assert_eq!(counted(&timers, now()), Duration::seconds(30 * 60 + 5));
+        assert_eq!(counted(&[], now()), Duration::zero());
+    }
+
+    #[test]
+    fn lists_the_timers_with_their_elapsed_time() {
+        let timers = vec![timer(10, "Deploy", true), timer(9, "All hands", false)];
+
+        assert_eq!(panel_lines(&timers, Granularity::Detailed, now()), [
+            "Timers (w):",
+            "  'Deploy' (10:00 - ..., 0h 30m 05s)",
+            "  'All hands' (09:00 - ..., 1h 30m 05s, not counted)",
+        ].join("\n"));
+        assert_eq!(panel_lines(&[], Granularity::Detailed, now()), "");
+    }
+}
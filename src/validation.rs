@@ -0,0 +1,140 @@
+use chrono::Duration;
+
+use crate::{ClockEntry, timeline};
+use crate::config::ValidationConfig;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    /// Shown once, submitting again saves anyway
+    Warning,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(message: impl Into<String>) -> Issue {
+        Issue { severity: Severity::Error, message: message.into() }
+    }
+
+    fn warning(message: impl Into<String>) -> Issue {
+        Issue { severity: Severity::Warning, message: message.into() }
+    }
+}
+
+/// What the entry is for: a recording only has its start yet, so its times aren't checked.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Scope {
+    Entry,
+    RecordingStart,
+}
+
+/// Checks the entry against the rules, see the "Validation" section of the README. `entries` may contain the entry
+/// itself, in its state before the edit.
+pub fn validate(entry: &ClockEntry, entries: &[ClockEntry], rules: &ValidationConfig, scope: Scope) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if rules.require_description && entry.description.trim().is_empty() {
+        issues.push(Issue::error("The description is required"));
+    }
+    if scope == Scope::RecordingStart {
+        return issues;
+    }
+    if entry.to < entry.from {
+        issues.push(Issue::error("To is before From"));
+    } else if entry.to == entry.from {
+        issues.push(Issue::error("The entry has no duration"));
+    }
+    if let Some(hours) = rules.max_duration_hours {
+        if entry.duration() > Duration::seconds((hours * 3600.0) as i64) {
+            issues.push(Issue::error(format!("The entry is longer than {} hours", hours)));
+        }
+    }
+    if rules.warn_on_overlap {
+        let mut day: Vec<ClockEntry> = entries.iter()
+            .filter(|it| it.id != entry.id && it.date == entry.date)
+            .cloned()
+            .collect();
+        day.push(entry.clone());
+        timeline::overlaps(&day).iter()
+            .filter_map(|it| match (it.first.id == entry.id, it.second.id == entry.id) {
+                (true, _) => Some(&it.second),
+                (_, true) => Some(&it.first),
+                _ => None,
+            })
+            .for_each(|other| issues.push(Issue::warning(format!("Overlaps '{}'", other.description))));
+    }
+    issues
+}
+
+pub fn has_errors(issues: &[Issue]) -> bool {
+    issues.iter().any(|it| it.severity == Severity::Error)
+}
+
+pub fn render(issues: &[Issue]) -> String {
+    issues.iter()
+        .map(|it| match it.severity {
+            Severity::Error => format!("✗ {}", it.message),
+            Severity::Warning => format!("⚠ {}", it.message),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod validation_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::ClockEntry;
+    use crate::config::ValidationConfig;
+    use crate::validation::{has_errors, Issue, Scope, Severity, validate};
+
+    fn entry(from: u32, to: u32, description: &str) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(from, 0, 0),
+            to: NaiveTime::from_hms(to, 0, 0),
+            description: description.to_string(),
+            ..ClockEntry::default()
+        }
+    }
+
+    fn messages(issues: Vec<Issue>) -> Vec<String> {
+        issues.into_iter().map(|it| it.message).collect()
+    }
+
+    #[test]
+    fn rejects_entries_without_duration() {
+        let rules = ValidationConfig::default();
+        assert_eq!(messages(validate(&entry(10, 9, "Coding"), &[], &rules, Scope::Entry)), vec!["To is before From"]);
+        assert_eq!(messages(validate(&entry(9, 9, "Coding"), &[], &rules, Scope::Entry)), vec!["The entry has no duration"]);
+        assert!(validate(&entry(9, 9, ""), &[], &rules, Scope::RecordingStart).is_empty());
+    }
+
+    #[test]
+    fn applies_the_configured_rules() {
+        let rules = ValidationConfig { require_description: true, max_duration_hours: Some(4.0), warn_on_overlap: true };
+        assert_eq!(
+            messages(validate(&entry(8, 13, " "), &[], &rules, Scope::Entry)),
+            vec!["The description is required", "The entry is longer than 4 hours"]
+        );
+        assert_eq!(messages(validate(&entry(9, 9, ""), &[], &rules, Scope::RecordingStart)), vec!["The description is required"]);
+    }
+
+    #[test]
+    fn warns_on_overlaps_with_other_entries() {
+        let coding = entry(9, 11, "Coding");
+        let entries = vec![coding.clone(), entry(10, 12, "Meeting"), entry(12, 13, "Lunch")];
+        let edited = ClockEntry { to: NaiveTime::from_hms(10, 30, 0), ..coding };
+
+        let issues = validate(&edited, &entries, &ValidationConfig::default(), Scope::Entry);
+
+        assert_eq!(issues, vec![Issue { severity: Severity::Warning, message: "Overlaps 'Meeting'".to_string() }]);
+        assert!(!has_errors(&issues));
+        let rules = ValidationConfig { warn_on_overlap: false, ..ValidationConfig::default() };
+        assert!(validate(&edited, &entries, &rules, Scope::Entry).is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{app_context, clock_entries_table, ClockEntry, config, format, jira, stats_view};
+use crate::config::WebhookConfig;
+
+const OUTBOX_LOCATION: &str = "./.clockking/outbox.json";
+
+/// An entry waiting to be delivered to the webhook. Kept on disk until delivery succeeds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct OutboxItem {
+    pub entry: ClockEntry,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryResult {
+    pub id: Uuid,
+    pub description: String,
+    pub outcome: Result<(), String>,
+}
+
+/// Set while a sync runs in the background, so the outbox isn't delivered twice at once.
+static SYNCING: AtomicBool = AtomicBool::new(false);
+
+/// Delivers the entries on a background thread, as retries can take a while. The delivered entries are marked clocked
+/// when it's done.
+pub fn sync(s: &mut Cursive) {
+    let webhook_config = match config::load() {
+        Ok(config) => config.webhook,
+        Err(e) => {
+            s.add_layer(Dialog::info(format!("Unable to read the config: {}", e)));
+            return;
+        }
+    };
+    let webhook_config = match webhook_config {
+        Some(it) => it,
+        None => {
+            s.add_layer(Dialog::info("No `webhook` section found in ./.clockking/config.json"));
+            return;
+        }
+    };
+    if SYNCING.swap(true, Ordering::SeqCst) {
+        s.add_layer(Dialog::info("The entries are being sent to the webhook already."));
+        return;
+    }
+    let mut entries = clock_entries_table::get_clock_entries(s);
+    let cb_sink = s.cb_sink().clone();
+    thread::spawn(move || {
+        let results = sync_entries(&webhook_config, &mut entries);
+        SYNCING.store(false, Ordering::SeqCst);
+        cb_sink.send(Box::new(move |s| show_results(s, results))).ok();
+    });
+}
+
+/// Marks the delivered entries clocked by ID, as they may have been edited during the sync.
+fn show_results(s: &mut Cursive, results: Vec<DeliveryResult>) {
+    let delivered: HashSet<Uuid> = results.iter().filter(|it| it.outcome.is_ok()).map(|it| it.id).collect();
+    let mark = |entry: &mut ClockEntry| if delivered.contains(&entry.id) && !entry.is_clocked {
+        entry.set_clocked(true);
+    };
+    clock_entries_table::edit_visible_entries(s, mark);
+    app_context::fetch(s).update_hidden(mark);
+    stats_view::update_stats(s);
+    s.add_layer(Dialog::info(format_results(&results)));
+}
+
+pub fn sync_entries(config: &WebhookConfig, entries: &mut [ClockEntry]) -> Vec<DeliveryResult> {
+    let mut outbox = load_outbox();
+    enqueue(&mut outbox, entries);
+    let results = deliver(config, &mut outbox, entries);
+    store_outbox(&outbox);
+    results
+}
+
+pub fn format_results(results: &[DeliveryResult]) -> String {
+    if results.is_empty() {
+        return "Nothing to sync.".to_string();
+    }
+    results.iter()
+        .map(|result| match &result.outcome {
+            Ok(()) => format!("[ok]     '{}'", result.description),
+            Err(e) => format!("[failed] '{}': {}", result.description, e),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Queues the unclocked entries. Queued entries are refreshed, so an edit made while offline is what gets delivered,
+/// and dropped once the entry is deleted.
+fn enqueue(outbox: &mut Vec<OutboxItem>, entries: &[ClockEntry]) {
+    outbox.retain_mut(|item| match entries.iter().find(|entry| is_same_entry(entry, &item.entry)) {
+        Some(entry) => {
+            item.entry = entry.clone();
+            true
+        },
+        None => false,
+    });
+    let new_items: Vec<OutboxItem> = entries.iter()
+        .filter(|entry| !entry.is_clocked)
+        .filter(|entry| !outbox.iter().any(|item| is_same_entry(&item.entry, entry)))
+        .map(|entry| OutboxItem {
+            entry: entry.clone(),
+            attempts: 0,
+            last_error: None,
+        })
+        .collect();
+    outbox.extend(new_items);
+}
+
+fn deliver(config: &WebhookConfig, outbox: &mut Vec<OutboxItem>, entries: &mut [ClockEntry]) -> Vec<DeliveryResult> {
+    let mut results = Vec::new();
+    outbox.retain_mut(|item| {
+        let outcome = send_with_retries(config, item);
+        if outcome.is_ok() {
+            entries.iter_mut()
+                .filter(|entry| is_same_entry(entry, &item.entry))
+                .for_each(|entry| entry.set_clocked(true));
+        }
+        // Only failures worth retrying stay in the outbox, a rejected entry would be rejected again
+        let keep = matches!(outcome, Err((true, _)));
+        results.push(DeliveryResult {
+            id: item.entry.id,
+            description: item.entry.description.clone(),
+            outcome: outcome.map_err(|(retryable, e)| if retryable { e } else { format!("{}, dropped from the outbox", e) }),
+        });
+        keep
+    });
+    results
+}
+
+fn is_same_entry(a: &ClockEntry, b: &ClockEntry) -> bool {
+    a.id == b.id
+}
+
+/// Sends the entry, on failure returning whether it makes sense to try again later.
+fn send_with_retries(config: &WebhookConfig, item: &mut OutboxItem) -> Result<(), (bool, String)> {
+    let body = render_body(&config.body_template, &item.entry);
+    let mut delay = config.retry_delay_ms;
+    for attempt in 1..=config.max_attempts.max(1) {
+        item.attempts += 1;
+        match send(config, &body) {
+            Ok(()) => {
+                item.last_error = None;
+                return Ok(());
+            },
+            Err((retryable, e)) => {
+                item.last_error = Some(e.clone());
+                if !retryable || attempt >= config.max_attempts {
+                    return Err((retryable, e));
+                }
+                thread::sleep(Duration::from_millis(delay));
+                delay = delay.saturating_mul(2);
+            }
+        }
+    }
+    Err((true, item.last_error.clone().unwrap_or_default()))
+}
+
+/// Sends the request, on failure returning whether it makes sense to retry.
+fn send(config: &WebhookConfig, body: &str) -> Result<(), (bool, String)> {
+    let request = config.headers.iter().fold(
+        ureq::request(&config.method, &config.url),
+        |request, (name, value)| request.set(name, value)
+    );
+    request.send_string(body)
+        .map(|_| ())
+        .map_err(|e| match e {
+            ureq::Error::Status(code, response) => (
+                code >= 500 || code == 429,
+                format!("Webhook responded with {}: {}", code, response.into_string().unwrap_or_default())
+            ),
+            ureq::Error::Transport(transport) => (true, transport.to_string()),
+        })
+}
+
+/// Replaces the `{{placeholder}}` values in the template. Values are JSON-escaped, without the surrounding quotes.
+pub fn render_body(template: &str, entry: &ClockEntry) -> String {
+    let issue_key = jira::extract_issue_key(&entry.description).unwrap_or_default();
+    let values = [
+        ("id", entry.id.to_string()),
+        ("description", entry.description.clone()),
+        ("from", format::format_naive_time(entry.granularity, entry.from)),
+        ("to", format::format_naive_time(entry.granularity, entry.to)),
+        ("date", entry.date.to_string()),
+        ("duration", format::format_hms(entry.granularity, entry.duration().num_seconds())),
+        ("duration_seconds", entry.duration().num_seconds().to_string()),
+        ("issue_key", issue_key),
+    ];
+    values.iter().fold(template.to_string(), |body, (name, value)| {
+        let escaped = serde_json::to_string(value).expect("Strings are always serializable");
+        body.replace(&format!("{{{{{}}}}}", name), &escaped[1..escaped.len() - 1])
+    })
+}
+
+fn load_outbox() -> Vec<OutboxItem> {
+    File::open(OUTBOX_LOCATION)
+        .ok()
+        .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok())
+        .unwrap_or_default()
+}
+
+fn store_outbox(outbox: &[OutboxItem]) {
+    let file = File::create(OUTBOX_LOCATION).expect("Unable to open the outbox file");
+    let writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(writer, outbox).expect("Saving the outbox failed");
+}
+
+#[cfg(test)]
+mod render_body_test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::webhook::render_body;
+
+    #[test]
+    fn renders_all_placeholders() {
+        let entry = ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(9, 0, 0),
+            to: NaiveTime::from_hms(10, 15, 0),
+            description: "ABC-7 \"quoted\" work".to_string(),
+            is_clocked: false,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        };
+        assert_eq!(
+            render_body(
+                r#"{"text": "{{description}}", "key": "{{issue_key}}", "on": "{{date}} {{from}}-{{to}}", "took": {{duration_seconds}}, "pretty": "{{duration}}"}"#,
+                &entry
+            ),
+            r#"{"text": "ABC-7 \"quoted\" work", "key": "ABC-7", "on": "2022-03-14 09:00-10:15", "took": 4500, "pretty": "1h 15m"}"#
+        );
+    }
+}
+
+#[cfg(test)]
+mod deliver_test {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use chrono::{NaiveDate, NaiveTime};
+
+    use crate::{ClockEntry, Granularity};
+    use crate::config::WebhookConfig;
+    use crate::test_server::TestServer;
+    use crate::webhook::{deliver, enqueue, OutboxItem};
+
+    fn entry(description: &str, hour: u32, is_clocked: bool) -> ClockEntry {
+        ClockEntry {
+            date: NaiveDate::from_ymd(2022, 3, 14),
+            from: NaiveTime::from_hms(hour, 0, 0),
+            to: NaiveTime::from_hms(hour + 1, 0, 0),
+            description: description.to_string(),
+            is_clocked,
+            granularity: Granularity::Detailed,
+            ..ClockEntry::default()
+        }
+    }
+
+    fn config(url: String, max_attempts: u32) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            method: "PUT".to_string(),
+            headers: BTreeMap::from([("X-Token".to_string(), "secret".to_string())]),
+            body_template: "{{description}}".to_string(),
+            max_attempts,
+            retry_delay_ms: 0,
+        }
+    }
+
+    #[test]
+    fn delivers_unclocked_entries_and_marks_them_clocked() {
+        let server = TestServer::start(|_| (200, "ok".to_string()));
+        let mut entries = vec![entry("Coding", 9, false), entry("Clocked", 10, true)];
+        let mut outbox = Vec::new();
+
+        enqueue(&mut outbox, &entries);
+        let results = deliver(&config(server.url(), 3), &mut outbox, &mut entries);
+
+        assert_eq!(results.len(), 1);
+        assert!(results[0].outcome.is_ok());
+        assert!(outbox.is_empty());
+        assert!(entries.iter().all(|it| it.is_clocked));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].header("x-token"), Some("secret".to_string()));
+        assert_eq!(requests[0].body, "Coding");
+    }
+
+    #[test]
+    fn retries_server_errors() {
+        let calls = AtomicU32::new(0);
+        let server = TestServer::start(move |_| {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 { (503, "busy".to_string()) } else { (200, "ok".to_string()) }
+        });
+        let mut entries = vec![entry("Coding", 9, false)];
+        let mut outbox = Vec::new();
+
+        enqueue(&mut outbox, &entries);
+        let results = deliver(&config(server.url(), 3), &mut outbox, &mut entries);
+
+        assert!(results[0].outcome.is_ok());
+        assert_eq!(server.requests().len(), 3);
+        assert!(entries[0].is_clocked);
+    }
+
+    #[test]
+    fn drops_rejected_entries_from_the_outbox() {
+        let server = TestServer::start(|_| (400, "bad request".to_string()));
+        let mut entries = vec![entry("Coding", 9, false)];
+        let mut outbox = Vec::new();
+
+        enqueue(&mut outbox, &entries);
+        enqueue(&mut outbox, &entries);
+        let results = deliver(&config(server.url(), 3), &mut outbox, &mut entries);
+
+        assert!(results[0].outcome.as_ref().unwrap_err().contains("400"));
+        assert!(results[0].outcome.as_ref().unwrap_err().contains("dropped from the outbox"));
+        assert_eq!(server.requests().len(), 1);
+        assert!(outbox.is_empty());
+        assert!(!entries[0].is_clocked);
+    }
+
+    #[test]
+    fn keeps_undeliverable_entries_when_offline() {
+        let mut entries = vec![entry("Coding", 9, false), entry("Review", 10, false)];
+        let mut outbox = vec![OutboxItem {
+            entry: entry("Coding", 11, false),
+            attempts: 2,
+            last_error: None,
+        }];
+
+        enqueue(&mut outbox, &entries);
+        let results = deliver(&config("http://127.0.0.1:1".to_string(), 2), &mut outbox, &mut entries);
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|it| it.outcome.is_err()));
+        assert_eq!(outbox.iter().map(|it| it.attempts).collect::<Vec<u32>>(), vec![2, 2]);
+        assert!(entries.iter().all(|it| !it.is_clocked));
+    }
+
+    #[test]
+    fn refreshes_queued_entries_and_drops_deleted_ones() {
+        let coding = entry("Coding", 9, false);
+        let deleted = entry("Deleted meanwhile", 11, false);
+        let edited = ClockEntry { description: "Coding ABC-1".to_string(), ..coding.clone() };
+        let mut outbox = Vec::new();
+        enqueue(&mut outbox, &[coding, deleted]);
+
+        enqueue(&mut outbox, std::slice::from_ref(&edited));
+
+        assert_eq!(outbox.iter().map(|it| it.entry.clone()).collect::<Vec<ClockEntry>>(), vec![edited]);
+    }
+}